@@ -0,0 +1,3 @@
+layout (location = 3) in mat4 instance_model;
+layout (location = 7) in vec4 instance_tint;
+layout (location = 8) in vec4 instance_data;
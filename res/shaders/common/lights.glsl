@@ -0,0 +1,63 @@
+// Number of lights in the block, must match `light::MAX_LIGHTS`.
+#define LIGHT_CAPACITY 16
+// Lights evaluated per draw, can be lowered through the defines of a material.
+#ifndef MAX_LIGHTS
+#define MAX_LIGHTS LIGHT_CAPACITY
+#endif
+
+#define LIGHT_DIRECTIONAL 0u
+#define LIGHT_POINT 1u
+#define LIGHT_SPOT 2u
+
+struct Light {
+    vec3 position;
+    float range;
+    vec3 direction;
+    uint kind;
+    vec3 color;
+    float intensity;
+    // Cosines of the inner and outer cone angle.
+    vec2 cone;
+};
+
+layout (std140) uniform Lights {
+    vec3 ambient;
+    uint light_count;
+    Light lights[LIGHT_CAPACITY];
+};
+
+// Inverse square falloff, smoothly reaching zero at the range.
+float attenuation(float distance_to_light, float range) {
+    float ratio = distance_to_light / range;
+    float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window / (distance_to_light * distance_to_light + 1.0);
+}
+
+// Blinn-Phong lighting of a surface at `position` with unit `normal`, seen from the camera
+// at `eye`. Colours are linear.
+vec3 shade(vec3 position, vec3 normal, vec3 eye, vec3 diffuse, vec3 specular, float shininess) {
+    vec3 view_direction = normalize(eye - position);
+    vec3 color = ambient * diffuse;
+    int count = min(int(light_count), MAX_LIGHTS);
+    for (int i = 0; i < count; i++) {
+        Light light = lights[i];
+        vec3 to_light = -light.direction;
+        float strength = light.intensity;
+        if (light.kind != LIGHT_DIRECTIONAL) {
+            vec3 offset = light.position - position;
+            float distance_to_light = length(offset);
+            to_light = offset / distance_to_light;
+            strength *= attenuation(distance_to_light, light.range);
+            if (light.kind == LIGHT_SPOT) {
+                float cosine = dot(-to_light, light.direction);
+                strength *= smoothstep(light.cone.y, light.cone.x, cosine);
+            }
+        }
+
+        float lambert = max(dot(normal, to_light), 0.0);
+        vec3 halfway = normalize(to_light + view_direction);
+        float highlight = lambert > 0.0 ? pow(max(dot(normal, halfway), 0.0), shininess) : 0.0;
+        color += (diffuse * lambert + specular * highlight) * light.color * strength;
+    }
+    return color;
+}
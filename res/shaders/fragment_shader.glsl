@@ -1,11 +1,19 @@
 #version 330 core
 out vec4 FragColor;
 
+in vec3 WorldPos;
+in vec3 Normal;
 in vec2 TexCoord;
+in vec4 Tint;
+
+#include "shaders/common/camera.glsl"
+#include "shaders/common/lights.glsl"
 
 uniform sampler2D texture1;
 uniform sampler2D texture2;
 
 void main() {
-    FragColor = mix(texture(texture1, TexCoord), texture(texture2, TexCoord), 0.2);
+    vec4 diffuse = mix(texture(texture1, TexCoord), texture(texture2, TexCoord), 0.2) * Tint;
+    vec3 color = shade(WorldPos, normalize(Normal), camera_position, diffuse.rgb, vec3(0.3), 32.0);
+    FragColor = vec4(color, diffuse.a);
 }
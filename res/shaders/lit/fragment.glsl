@@ -0,0 +1,48 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec3 world_position;
+in vec3 world_normal;
+in vec2 uv;
+in vec4 tint;
+
+#include "shaders/common/camera.glsl"
+#include "shaders/common/lights.glsl"
+
+uniform sampler2D diffuse_map;
+uniform sampler2D specular_map;
+// Tangent space normals, stored linearly.
+uniform sampler2D normal_map;
+uniform vec4 diffuse_color;
+uniform vec3 specular_color;
+uniform float shininess;
+
+// Tangent frame from screen space derivatives, so meshes need no tangents.
+mat3 tangent_frame(vec3 normal) {
+    vec3 dp1 = dFdx(world_position);
+    vec3 dp2 = dFdy(world_position);
+    vec2 duv1 = dFdx(uv);
+    vec2 duv2 = dFdy(uv);
+    vec3 dp2_perp = cross(dp2, normal);
+    vec3 dp1_perp = cross(normal, dp1);
+    vec3 tangent = dp2_perp * duv1.x + dp1_perp * duv2.x;
+    vec3 bitangent = dp2_perp * duv1.y + dp1_perp * duv2.y;
+    // Guarded for surfaces whose coordinates do not change, e.g. a constant uv.
+    float scale = inversesqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-20));
+    return mat3(tangent * scale, bitangent * scale, normal);
+}
+
+void main() {
+    vec4 diffuse = texture(diffuse_map, uv) * diffuse_color * tint;
+    vec3 specular = texture(specular_map, uv).rgb * specular_color;
+
+    vec3 normal = normalize(world_normal);
+    if (!gl_FrontFacing) {
+        normal = -normal;
+    }
+    vec3 mapped = texture(normal_map, uv).xyz * 2.0 - 1.0;
+    normal = normalize(tangent_frame(normal) * mapped);
+
+    vec3 color = shade(world_position, normal, camera_position, diffuse.rgb, specular, shininess);
+    FragColor = vec4(color, diffuse.a);
+}
@@ -0,0 +1,32 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec3 aNormal;
+layout (location = 2) in vec2 aTexCoord;
+
+out vec3 world_position;
+out vec3 world_normal;
+out vec2 uv;
+out vec4 tint;
+
+#include "shaders/common/camera.glsl"
+
+// With `INSTANCED` defined the render queue draws items sharing mesh and material at once.
+#ifdef INSTANCED
+#include "shaders/common/instancing.glsl"
+#else
+uniform mat4 model;
+#endif
+
+void main() {
+#ifdef INSTANCED
+    mat4 model = instance_model;
+    tint = instance_tint;
+#else
+    tint = vec4(1.0);
+#endif
+    vec4 position = model * vec4(aPos, 1.0);
+    gl_Position = projection * view * position;
+    world_position = position.xyz;
+    world_normal = mat3(transpose(inverse(model))) * aNormal;
+    uv = aTexCoord;
+}
@@ -0,0 +1,13 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D source;
+uniform sampler2D bloom;
+uniform float intensity;
+
+void main() {
+    vec4 color = texture(source, uv);
+    FragColor = vec4(color.rgb + texture(bloom, uv).rgb * intensity, color.a);
+}
@@ -0,0 +1,19 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D source;
+// One texel along the blurred axis.
+uniform vec2 direction;
+
+const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec3 color = texture(source, uv).rgb * WEIGHTS[0];
+    for (int i = 1; i < 5; i++) {
+        color += texture(source, uv + direction * float(i)).rgb * WEIGHTS[i];
+        color += texture(source, uv - direction * float(i)).rgb * WEIGHTS[i];
+    }
+    FragColor = vec4(color, 1.0);
+}
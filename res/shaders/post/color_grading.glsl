@@ -0,0 +1,17 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D source;
+uniform sampler3D lut;
+uniform float strength;
+
+void main() {
+    vec4 color = texture(source, uv);
+    vec3 size = vec3(textureSize(lut, 0));
+    // Sample the centres of the first and last texels for 0 and 1.
+    vec3 coordinates = clamp(color.rgb, 0.0, 1.0) * (size - 1.0) / size + 0.5 / size;
+    vec3 graded = texture(lut, coordinates).rgb;
+    FragColor = vec4(mix(color.rgb, graded, strength), color.a);
+}
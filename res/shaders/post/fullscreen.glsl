@@ -0,0 +1,9 @@
+#version 330 core
+out vec2 uv;
+
+// Single triangle covering the screen, drawn without vertex buffers.
+void main() {
+    vec2 position = vec2((gl_VertexID & 1) * 4 - 1, (gl_VertexID & 2) * 2 - 1);
+    uv = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
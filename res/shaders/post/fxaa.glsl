@@ -0,0 +1,42 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D source;
+uniform vec2 texel_size;
+
+const float SPAN_MAX = 8.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+vec3 sample_at(vec2 offset) {
+    return texture(source, uv + offset).rgb;
+}
+
+// Blurs along the edge through each pixel, based on FXAA by Timothy Lottes.
+void main() {
+    vec4 center = texture(source, uv);
+    float luma_nw = luma(sample_at(vec2(-1.0, -1.0) * texel_size));
+    float luma_ne = luma(sample_at(vec2(1.0, -1.0) * texel_size));
+    float luma_sw = luma(sample_at(vec2(-1.0, 1.0) * texel_size));
+    float luma_se = luma(sample_at(vec2(1.0, 1.0) * texel_size));
+    float luma_m = luma(center.rgb);
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    vec2 direction = vec2(luma_sw + luma_se - luma_nw - luma_ne, luma_nw + luma_sw - luma_ne - luma_se);
+    float reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, -SPAN_MAX, SPAN_MAX) * texel_size;
+
+    vec3 two_tap = 0.5 * (sample_at(direction * (1.0 / 3.0 - 0.5)) + sample_at(direction * (2.0 / 3.0 - 0.5)));
+    vec3 four_tap = two_tap * 0.5 + 0.25 * (sample_at(direction * -0.5) + sample_at(direction * 0.5));
+    float luma_four_tap = luma(four_tap);
+    vec3 color = luma_four_tap < luma_min || luma_four_tap > luma_max ? two_tap : four_tap;
+    FragColor = vec4(color, center.a);
+}
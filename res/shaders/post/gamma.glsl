@@ -0,0 +1,12 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D source;
+uniform float gamma;
+
+void main() {
+    vec4 color = texture(source, uv);
+    FragColor = vec4(pow(max(color.rgb, 0.0), vec3(1.0 / gamma)), color.a);
+}
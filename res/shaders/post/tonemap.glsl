@@ -0,0 +1,21 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D source;
+uniform float exposure;
+// 0 is Reinhard, 1 is ACES.
+uniform int curve;
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve.
+vec3 aces(vec3 color) {
+    return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+    vec4 color = texture(source, uv);
+    vec3 exposed = color.rgb * exposure;
+    vec3 mapped = curve == 0 ? exposed / (exposed + 1.0) : aces(exposed);
+    FragColor = vec4(mapped, color.a);
+}
@@ -0,0 +1,16 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D source;
+uniform float intensity;
+uniform float smoothness;
+
+void main() {
+    vec4 color = texture(source, uv);
+    // 0 at the centre, 1 in the corners.
+    float edge_distance = length(uv - 0.5) * sqrt(2.0);
+    float darkening = intensity * smoothstep(1.0 - smoothness, 1.0, edge_distance);
+    FragColor = vec4(color.rgb * (1.0 - darkening), color.a);
+}
@@ -1,14 +1,32 @@
 #version 330 core
 layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec3 aNormal;
 layout (location = 2) in vec2 aTexCoord;
 
+out vec3 WorldPos;
+out vec3 Normal;
 out vec2 TexCoord;
+out vec4 Tint;
 
+#include "shaders/common/camera.glsl"
+
+// With `INSTANCED` defined the render queue draws items sharing mesh and material at once.
+#ifdef INSTANCED
+#include "shaders/common/instancing.glsl"
+#else
 uniform mat4 model;
-uniform mat4 view;
-uniform mat4 projection;
+#endif
 
 void main() {
-    gl_Position = projection * view * model * vec4(aPos, 1.0);
+#ifdef INSTANCED
+    mat4 model = instance_model;
+    Tint = instance_tint;
+#else
+    Tint = vec4(1.0);
+#endif
+    vec4 position = model * vec4(aPos, 1.0);
+    gl_Position = projection * view * position;
+    WorldPos = position.xyz;
+    Normal = mat3(transpose(inverse(model))) * aNormal;
     TexCoord = aTexCoord;
 }
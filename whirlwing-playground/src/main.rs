@@ -1,7 +1,78 @@
 #[allow(unused_imports)]
 use whirlwing::log;
 
+use whirlwing::{
+    app::{App, Game},
+    context::Context,
+    error::WhirlwingError,
+    event::{ElementState, VirtualKeyCode, WindowEvent},
+    light::{Light, LightKind},
+    math::{Isometry3, Rotor3, Similarity3, Vec3},
+    scene::{Attachment, Projection},
+    time::Time,
+    window::WindowConfig,
+};
+
+struct Playground;
+
+impl Playground {
+    /// The container cube, turned towards the camera and lit by the sun.
+    fn build_scene(ctx: &mut Context) -> Result<(), WhirlwingError> {
+        let assets = ctx.assets_mut();
+        let mesh = assets.load_mesh("meshes/cube.obj")?;
+        let material = assets.load_material("materials/container.toml")?;
+
+        // Lights shine along -Z of their placement.
+        let direction = Vec3::new(-0.3, -0.6, -1.0).normalized();
+        let rotation = Rotor3::from_rotation_between(Vec3::new(0.0, 0.0, -1.0), direction);
+        let sun = Light::new(LightKind::Directional, Vec3::one(), 3.0);
+        ctx.world_mut()
+            .spawn((sun, Isometry3::new(Vec3::zero(), rotation)));
+
+        let mut scene = ctx.scene_mut();
+        let rotation = Rotor3::from_rotation_xz(f32::to_radians(30.0));
+        let cube = scene.add_node("cube", Similarity3::new(Vec3::zero(), rotation, 1.0));
+        scene.attach(cube, Attachment::Model { mesh, material })?;
+
+        let transform = Similarity3::new(Vec3::new(0.0, 0.0, 5.0), Rotor3::identity(), 1.0);
+        let camera = scene.add_node("camera", transform);
+        scene.attach(camera, Attachment::Camera(Projection::default()))?;
+        scene.set_active_camera(Some(camera))
+    }
+}
+
+impl Game for Playground {
+    fn init(&mut self, ctx: &mut Context) {
+        if let Err(e) = ctx.assets_mut().enable_hot_reload() {
+            log::err!("{e}");
+        }
+        if let Err(e) = Self::build_scene(ctx) {
+            log::err!("{e}");
+        }
+    }
+
+    fn update(&mut self, _ctx: &mut Context, _time: &Time) {}
+
+    fn on_event(&mut self, ctx: &mut Context, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state == ElementState::Pressed
+                && input.virtual_keycode == Some(VirtualKeyCode::Escape)
+            {
+                ctx.exit();
+            }
+        }
+    }
+}
+
 fn main() {
     log::log_utils::set_engine_log_default(log::log_utils::Severity::Debug);
-    whirlwing::app::run();
+    let window_config = WindowConfig::from_toml_file("res/window.toml").unwrap_or_else(|e| {
+        log::err!("{e}");
+        WindowConfig::default()
+    });
+    App::builder()
+        .with_window_config(window_config)
+        .with_game(Playground)
+        .build()
+        .run();
 }
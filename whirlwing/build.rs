@@ -0,0 +1,6 @@
+fn main() {
+    // Backend cfgs of the glutin examples the window setup is taken from. They are not set
+    // by this crate, declaring them keeps `unexpected_cfgs` quiet.
+    println!("cargo:rustc-check-cfg=cfg(cgl_backend)");
+    println!("cargo:rustc-check-cfg=cfg(wgl_backend)");
+}
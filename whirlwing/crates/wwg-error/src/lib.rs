@@ -1,10 +1,13 @@
 use std::error::Error;
+use std::sync::Arc;
 
-#[derive(Debug)]
+/// Errors are `Send` so they can be produced on worker threads, and `Clone` so a failed
+/// load can be reported to everyone holding the asset.
+#[derive(Debug, Clone)]
 pub struct WhirlwingError {
     content: String,
     kind: WhirlwingErrorKind,
-    source: Option<Box<dyn Error>>,
+    source: Option<Arc<dyn Error + Send + Sync>>,
 }
 
 impl WhirlwingError {
@@ -19,25 +22,31 @@ impl WhirlwingError {
     pub fn new_with_source(
         content: String,
         kind: WhirlwingErrorKind,
-        source: Box<dyn Error>,
+        source: Box<dyn Error + Send + Sync>,
     ) -> Self {
         WhirlwingError {
             content,
             kind,
-            source: Some(source),
+            source: Some(Arc::from(source)),
         }
     }
+
+    pub fn kind(&self) -> &WhirlwingErrorKind {
+        &self.kind
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
 }
 
 impl std::fmt::Display for WhirlwingError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.source.is_some() {
+        if let Some(source) = &self.source {
             write!(
                 f,
                 "Whirlwing Error: {}\nError Content: {}\nError Source: {}",
-                self.kind,
-                self.content,
-                self.source.as_ref().unwrap()
+                self.kind, self.content, source
             )
         } else {
             write!(
@@ -51,19 +60,41 @@ impl std::fmt::Display for WhirlwingError {
 
 impl std::error::Error for WhirlwingError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        self.source.as_deref()
+        self.source.as_deref().map(|source| source as &(dyn Error + 'static))
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WhirlwingErrorKind {
     ShaderCompilationFailure,
+    ConfigLoadFailure,
+    ContextCreationFailure,
+    TextureLoadFailure,
+    MeshLoadFailure,
+    MaterialLoadFailure,
+    HotReloadFailure,
+    UniformFailure,
+    UnsupportedFeature,
+    SceneFailure,
+    EcsFailure,
+    FramebufferFailure,
 }
 
 impl std::fmt::Display for WhirlwingErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let output = match self {
             WhirlwingErrorKind::ShaderCompilationFailure => "Shader Compilation Failure",
+            WhirlwingErrorKind::ConfigLoadFailure => "Config Load Failure",
+            WhirlwingErrorKind::ContextCreationFailure => "Context Creation Failure",
+            WhirlwingErrorKind::TextureLoadFailure => "Texture Load Failure",
+            WhirlwingErrorKind::MeshLoadFailure => "Mesh Load Failure",
+            WhirlwingErrorKind::MaterialLoadFailure => "Material Load Failure",
+            WhirlwingErrorKind::HotReloadFailure => "Hot Reload Failure",
+            WhirlwingErrorKind::UniformFailure => "Uniform Failure",
+            WhirlwingErrorKind::UnsupportedFeature => "Unsupported Feature",
+            WhirlwingErrorKind::SceneFailure => "Scene Failure",
+            WhirlwingErrorKind::EcsFailure => "ECS Failure",
+            WhirlwingErrorKind::FramebufferFailure => "Framebuffer Failure",
         };
         write!(f, "{output}")
     }
@@ -1,7 +1,9 @@
 pub use ultraviolet::{
     Vec2, Vec3, Vec4,
-    Mat3, Mat4,
+    IVec2, IVec3, IVec4,
+    UVec2, UVec3, UVec4,
+    Mat2, Mat3, Mat4,
     Rotor3,
     Isometry3, Similarity3,
     projection::perspective_gl,
-};
\ No newline at end of file
+};
@@ -1,178 +1,308 @@
-use crate::{renderer::Renderer, time::Time, input::Input, camera::Camera};
+use crate::{
+    assets::Assets,
+    context::Context,
+    light::Lights,
+    post_process::Effect,
+    program_cache::ProgramCache,
+    renderer::{self, Renderer},
+    schedule::Stage,
+    time::Time,
+    window::WindowConfig,
+};
 
-use std::num::NonZeroU32;
+use std::{cmp::Reverse, num::NonZeroU32, path::PathBuf};
 
 use glutin::{
-    config::{ConfigTemplateBuilder, GlConfig},
-    context::{ContextAttributesBuilder, GlProfile},
+    config::{Config, GlConfig},
     display::{GetGlDisplay, GlDisplay},
     prelude::{NotCurrentGlContextSurfaceAccessor, PossiblyCurrentGlContext},
     surface::GlSurface,
 };
 use glutin_winit::{DisplayBuilder, GlWindow};
 use raw_window_handle::HasRawWindowHandle;
-use winit::event::{Event, VirtualKeyCode, WindowEvent};
-
-pub fn run() {
-    let event_loop = winit::event_loop::EventLoop::new();
-    let window_builder = winit::window::WindowBuilder::new()
-        .with_title("Whirlwing Window")
-        .with_inner_size(winit::dpi::PhysicalSize::new(800, 600))
-        .with_transparent(true);
-
-    // I stole next expression from glutin example
-    // And I don't know what's going on there
-    #[cfg(cgl_backend)]
-    let template = ConfigTemplateBuilder::new()
-        .with_alpha_size(8)
-        .with_transparency(true);
-    #[cfg(not(cgl_backend))]
-    let template = ConfigTemplateBuilder::new()
-        .with_alpha_size(8)
-        .with_transparency(false);
-
-    let display_builder = DisplayBuilder::new().with_window_builder(Some(window_builder));
-
-    let (mut window, gl_config) = display_builder
-        .build(&event_loop, template, |configs| {
-            configs
-                .reduce(|accum, config| {
-                    let transparency_check = config.supports_transparency().unwrap_or(false)
-                        & !accum.supports_transparency().unwrap_or(false);
-
-                    if transparency_check || config.num_samples() > accum.num_samples() {
-                        config
-                    } else {
-                        accum
-                    }
-                })
-                .unwrap()
-        })
-        .unwrap();
-
-    let raw_window_handle = window.as_ref().map(|window| window.raw_window_handle());
-    let gl_display = gl_config.display();
-
-    #[cfg(not(wgl_backend))]
-    let context_attributes = ContextAttributesBuilder::new()
-        .with_profile(GlProfile::Core)
-        .with_context_api(glutin::context::ContextApi::Gles(Some(
-            glutin::context::Version::new(3, 3),
-        )));
-
-    #[cfg(not(wgl_backend))]
-    #[cfg(debug_assertions)]
-    let context_attributes = context_attributes
-        .with_debug(true)
-        .with_robustness(glutin::context::Robustness::RobustLoseContextOnReset);
-
-    #[cfg(not(wgl_backend))]
-    let context_attributes = context_attributes.build(raw_window_handle);
-
-    let mut not_current_gl_context = Some(unsafe {
-        gl_display
-            .create_context(&gl_config, &context_attributes)
-            .expect("Failed to create OpenGL Context.")
-    });
-
-    let mut renderer = None;
-    let mut state = None;
-
-
-    // Temporary values
-    let mut camera = Camera::create_cam_tmp();
-    let mut time = Time::start();
-    let mut input_actions = Input::default();
-
-    event_loop.run(move |event, elwt, control_flow| {
-        control_flow.set_poll();
-
-        match event {
-            Event::Resumed => {
-                let window = window.take().unwrap_or_else(|| {
-                    let window_builder = winit::window::WindowBuilder::new()
-                        .with_title("Whirlwing Window")
-                        .with_inner_size(winit::dpi::PhysicalSize::new(800, 600))
-                        .with_transparent(true);
-                    glutin_winit::finalize_window(elwt, window_builder, &gl_config).unwrap()
-                });
-                let attributes = window.build_surface_attributes(<_>::default());
-
-                let gl_surface = unsafe {
-                    gl_display
-                        .create_window_surface(&gl_config, &attributes)
-                        .unwrap()
+use winit::event::{Event, WindowEvent};
+
+/// User code driven by the engine event loop.
+///
+/// Only `update` is required, the other callbacks do nothing by default.
+pub trait Game {
+    /// Called once, after the OpenGL context has been created.
+    fn init(&mut self, _ctx: &mut Context) {}
+
+    /// Called every frame before the scene is drawn.
+    fn update(&mut self, ctx: &mut Context, time: &Time);
+
+    /// Called every frame after the scene is drawn and before buffers are swapped.
+    fn render(&mut self, _ctx: &mut Context) {}
+
+    /// Called for every window event, before the engine handles it.
+    fn on_event(&mut self, _ctx: &mut Context, _event: &WindowEvent) {}
+}
+
+struct NoGame;
+
+impl Game for NoGame {
+    fn update(&mut self, _ctx: &mut Context, _time: &Time) {}
+}
+
+pub struct App {
+    game: Box<dyn Game>,
+    window_config: WindowConfig,
+    asset_root: PathBuf,
+    program_cache: Option<ProgramCache>,
+}
+
+impl App {
+    pub fn builder() -> AppBuilder {
+        AppBuilder {
+            game: None,
+            window_config: WindowConfig::default(),
+            asset_root: None,
+            program_cache: None,
+        }
+    }
+
+    pub fn run(self) -> ! {
+        let App {
+            mut game,
+            window_config,
+            asset_root,
+            program_cache,
+        } = self;
+
+        let mut assets = Assets::new(asset_root);
+        assets.set_program_cache(program_cache);
+        let mut ctx = Context::new(assets);
+        // The scene is drawn into the target of the stack, which has to be multisampled.
+        // The window does not need samples of its own then.
+        ctx.post_process_mut()
+            .set_samples(window_config.samples as u32);
+        let surface_config = WindowConfig {
+            samples: if ctx.post_process_mut().is_active() {
+                0
+            } else {
+                window_config.samples
+            },
+            ..window_config.clone()
+        };
+
+        let event_loop = winit::event_loop::EventLoop::new();
+        let window_builder = window_config.window_builder(&event_loop);
+        let template = surface_config.config_template();
+        let requested_samples = surface_config.samples;
+        let requested_srgb = window_config.srgb;
+
+        let display_builder = DisplayBuilder::new().with_window_builder(Some(window_builder));
+
+        let (mut window, gl_config) = display_builder
+            .build(&event_loop, template, |configs| {
+                // Compared in order: sRGB if requested, closest sample count, transparency.
+                let key = |config: &Config| {
+                    (
+                        requested_srgb && config.srgb_capable(),
+                        Reverse(config.num_samples().abs_diff(requested_samples)),
+                        config.supports_transparency().unwrap_or(false),
+                    )
                 };
-                let gl_context = not_current_gl_context
-                    .take()
+                configs
+                    .reduce(|accum, config| {
+                        if key(&config) > key(&accum) {
+                            config
+                        } else {
+                            accum
+                        }
+                    })
                     .unwrap()
-                    .make_current(&gl_surface)
-                    .unwrap();
+            })
+            .unwrap();
 
-                if renderer.is_none() {
-                    renderer = Some(Renderer::new(&gl_display));
-                }
+        let raw_window_handle = window.as_ref().map(|window| window.raw_window_handle());
+        let gl_display = gl_config.display();
 
-                if let Err(res) = gl_surface.set_swap_interval(
-                    &gl_context,
-                    glutin::surface::SwapInterval::Wait(NonZeroU32::new(1).unwrap()),
-                ) {
-                    wwg_log::wwg_warn!("Error setting vsync: {res}");
-                }
+        let context_attributes = window_config.context_attributes().build(raw_window_handle);
 
-                assert!(state.replace((gl_context, gl_surface, window)).is_none());
-                time.reset();
-            }
-            Event::Suspended => {
-                let (gl_context, ..) = state.take().unwrap();
-                assert!(not_current_gl_context
-                    .replace(gl_context.make_not_current().unwrap())
-                    .is_none());
-            }
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::KeyboardInput { input, .. } => {
-                    if let Some(keycode) = input.virtual_keycode {
-                        wwg_log::wwg_info!("Key is pressed: {keycode:?}");
-                        match keycode {
-                            VirtualKeyCode::Escape => {
+        let mut not_current_gl_context = Some(unsafe {
+            gl_display
+                .create_context(&gl_config, &context_attributes)
+                .expect("Failed to create OpenGL Context.")
+        });
+
+        let mut renderer = None;
+        let mut state = None;
+
+        event_loop.run(move |event, elwt, control_flow| {
+            control_flow.set_poll();
+
+            match event {
+                Event::Resumed => {
+                    let window = window.take().unwrap_or_else(|| {
+                        let window_builder = window_config.window_builder(elwt);
+                        glutin_winit::finalize_window(elwt, window_builder, &gl_config).unwrap()
+                    });
+                    let attributes = window.build_surface_attributes(window_config.surface_attributes());
+
+                    let gl_surface = unsafe {
+                        gl_display
+                            .create_window_surface(&gl_config, &attributes)
+                            .unwrap()
+                    };
+                    let gl_context = not_current_gl_context
+                        .take()
+                        .unwrap()
+                        .make_current(&gl_surface)
+                        .unwrap();
+
+                    if renderer.is_none() {
+                        let size = window.inner_size();
+                        renderer::load_gl(&gl_display);
+                        let srgb = requested_srgb && gl_config.srgb_capable();
+                        if requested_srgb && !srgb {
+                            wwg_log::wwg_warn!(
+                                "No sRGB framebuffer available, the output is encoded by a gamma pass"
+                            );
+                            ctx.post_process_mut().push("gamma", Effect::Gamma(2.2));
+                        }
+                        let assets = ctx.assets_mut();
+                        match Renderer::new(size.width, size.height, srgb, assets) {
+                            Ok(new) => renderer = Some(new),
+                            Err(e) => {
+                                wwg_log::wwg_err!("{e}");
                                 control_flow.set_exit();
+                                return;
                             }
-                            _ => input_actions.add_keyboard_input(input),
                         }
+                        // Targets created in `init` already get the window size.
+                        let _ = ctx.render_targets_mut().resize(size.width, size.height);
+                        game.init(&mut ctx);
                     }
+
+                    if let Err(res) =
+                        gl_surface.set_swap_interval(&gl_context, window_config.swap_interval())
+                    {
+                        wwg_log::wwg_warn!("Error setting vsync: {res}");
+                    }
+
+                    assert!(state.replace((gl_context, gl_surface, window)).is_none());
+                    ctx.time_mut().reset();
                 }
-                WindowEvent::Resized(size) => {
-                    if size.width != 0 && size.height != 0 {
-                        if let Some((gl_context, gl_surface, _)) = &state {
-                            gl_surface.resize(
-                                gl_context,
-                                NonZeroU32::new(size.width).unwrap(),
-                                NonZeroU32::new(size.height).unwrap(),
-                            );
-                            if let Some(rend) = &renderer {
-                                rend.resize(size.width as i32, size.height as i32);
+                Event::Suspended => {
+                    let (gl_context, ..) = state.take().unwrap();
+                    assert!(not_current_gl_context
+                        .replace(gl_context.make_not_current().unwrap())
+                        .is_none());
+                }
+                Event::WindowEvent { event, .. } => {
+                    game.on_event(&mut ctx, &event);
+
+                    match event {
+                        WindowEvent::KeyboardInput { input, .. } => {
+                            if let Some(keycode) = input.virtual_keycode {
+                                wwg_log::wwg_info!("Key is pressed: {keycode:?}");
+                                ctx.input_mut().add_keyboard_input(input);
                             }
                         }
+                        WindowEvent::CursorMoved { position, .. } => {
+                            ctx.input_mut()
+                                .set_mouse_position(position.x as i32, position.y as i32);
+                        }
+                        WindowEvent::Resized(size) if size.width != 0 && size.height != 0 => {
+                            if let Some((gl_context, gl_surface, _)) = &state {
+                                gl_surface.resize(
+                                    gl_context,
+                                    NonZeroU32::new(size.width).unwrap(),
+                                    NonZeroU32::new(size.height).unwrap(),
+                                );
+                                if let Some(rend) = &mut renderer {
+                                    rend.resize(size.width as i32, size.height as i32);
+                                }
+                                let mut targets = ctx.render_targets_mut();
+                                if let Err(e) = targets.resize(size.width, size.height) {
+                                    wwg_log::wwg_err!("{e}");
+                                }
+                            }
+                        }
+                        WindowEvent::CloseRequested => {
+                            control_flow.set_exit();
+                        }
+                        _ => (),
                     }
                 }
-                WindowEvent::CloseRequested => {
-                    control_flow.set_exit();
-                }
-                _ => (),
-            },
-            Event::MainEventsCleared => {
-                if let Some((gl_context, gl_surface, window)) = &state {
-                    if let Some(rend) = &renderer {
-                        rend.redraw(&camera, &time);
+                Event::MainEventsCleared => {
+                    if let Some((gl_context, gl_surface, window)) = &state {
+                        ctx.assets_mut().update();
+                        ctx.run_stage(Stage::PreUpdate);
+                        let time = ctx.time().clone();
+                        game.update(&mut ctx, &time);
+                        ctx.run_stage(Stage::Update);
+                        ctx.run_stage(Stage::PostUpdate);
+                        if let Some(rend) = &renderer {
+                            let world = ctx.world();
+                            let mut queue = world.resource_mut().unwrap();
+                            let mut lights = world.resource_mut::<Lights>().unwrap();
+                            lights.gather(world);
+                            let mut post_process = world.resource_mut().unwrap();
+                            let (scene, camera) = (ctx.scene(), ctx.camera());
+                            let (queue, lights) = (&mut queue, &mut lights);
+                            let post_process = &mut post_process;
+                            rend.redraw(None, &scene, &camera, &time, queue, lights, post_process);
+                        }
+                        ctx.run_stage(Stage::Render);
+                        game.render(&mut ctx);
+                        window.request_redraw();
+                        gl_surface.swap_buffers(gl_context).unwrap();
+                        ctx.time_mut().tick();
+                        ctx.input_mut().end_frame();
+                        let time = ctx.time();
+                        wwg_log::wwg_trace!("FPS: {}", 1f32 / time.delta_time().as_secs_f32());
+                        wwg_log::wwg_trace!("Frame took {} seconds.", time.delta_time().as_secs_f32());
                     }
-                    window.request_redraw();
-                    gl_surface.swap_buffers(gl_context).unwrap();
-                    time.tick();
-                    wwg_log::wwg_trace!("FPS: {}", 1f32 / time.delta_time().as_secs_f32());
-                    wwg_log::wwg_trace!("Frame took {} seconds.", time.delta_time().as_secs_f32());
                 }
+                _ => (),
             }
-            _ => (),
+
+            if ctx.exit_requested() {
+                control_flow.set_exit();
+            }
+        });
+    }
+}
+
+pub struct AppBuilder {
+    game: Option<Box<dyn Game>>,
+    window_config: WindowConfig,
+    asset_root: Option<PathBuf>,
+    program_cache: Option<ProgramCache>,
+}
+
+impl AppBuilder {
+    pub fn with_game<G: Game + 'static>(mut self, game: G) -> Self {
+        self.game = Some(Box::new(game));
+        self
+    }
+
+    pub fn with_window_config(mut self, window_config: WindowConfig) -> Self {
+        self.window_config = window_config;
+        self
+    }
+
+    /// Directory assets are loaded from, `Assets::default_root` if not set.
+    pub fn with_asset_root<P: Into<PathBuf>>(mut self, asset_root: P) -> Self {
+        self.asset_root = Some(asset_root.into());
+        self
+    }
+
+    /// Stores linked shader programs in `directory` to skip compiling them on later launches.
+    pub fn with_program_cache<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.program_cache = Some(ProgramCache::new(directory));
+        self
+    }
+
+    pub fn build(self) -> App {
+        App {
+            game: self.game.unwrap_or_else(|| Box::new(NoGame)),
+            window_config: self.window_config,
+            asset_root: self.asset_root.unwrap_or_else(Assets::default_root),
+            program_cache: self.program_cache,
         }
-    });
+    }
 }
@@ -0,0 +1,533 @@
+use std::{
+    cell::OnceCell,
+    collections::HashMap,
+    hash::Hash,
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
+    rc::{Rc, Weak},
+    sync::mpsc,
+};
+
+use image::DynamicImage;
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+
+use crate::{
+    material::Material,
+    mesh::{Mesh, MeshData},
+    preprocessor::Preprocessor,
+    program_cache::ProgramCache,
+    shader::Shader,
+    texture::{Texture2D, TextureDesc},
+    worker_pool::WorkerPool,
+};
+
+#[cfg(feature = "hot_reload")]
+use crate::hot_reload::{self, FileWatcher};
+
+#[derive(Debug, Clone)]
+pub enum LoadState {
+    Pending,
+    Loaded,
+    Failed(WhirlwingError),
+}
+
+/// Shared reference to an asset which is either loaded or still loading.
+///
+/// Cloning a handle is cheap, the asset and its GPU resources are freed when the last handle drops.
+/// Handles returned by the synchronous `Assets::load_*` functions are always loaded.
+#[derive(Debug)]
+pub struct Handle<T> {
+    slot: Rc<OnceCell<Result<T, WhirlwingError>>>,
+}
+
+impl<T> Handle<T> {
+    /// Wraps an asset created in code, it is not cached by `Assets`.
+    pub fn new(asset: T) -> Self {
+        Handle {
+            slot: Rc::new(OnceCell::from(Ok(asset))),
+        }
+    }
+
+    fn pending() -> Self {
+        Handle {
+            slot: Rc::new(OnceCell::new()),
+        }
+    }
+
+    /// Returns the asset if it has finished loading.
+    pub fn get(&self) -> Option<&T> {
+        self.slot.get()?.as_ref().ok()
+    }
+
+    pub fn state(&self) -> LoadState {
+        match self.slot.get() {
+            None => LoadState::Pending,
+            Some(Ok(_)) => LoadState::Loaded,
+            Some(Err(error)) => LoadState::Failed(error.clone()),
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.get().is_some()
+    }
+
+    /// Returns true if both handles point to the same asset.
+    pub fn ptr_eq(this: &Handle<T>, other: &Handle<T>) -> bool {
+        Rc::ptr_eq(&this.slot, &other.slot)
+    }
+
+    // The first result wins, a late asynchronous upload is dropped.
+    fn finish(&self, result: Result<T, WhirlwingError>) {
+        let _ = self.slot.set(result);
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle {
+            slot: Rc::clone(&self.slot),
+        }
+    }
+}
+
+/// Loads assets relative to an asset root and hands out shared handles to them.
+///
+/// Loading a path which is still referenced by a handle returns that handle instead
+/// of loading the asset again, unless the previous load failed.
+/// Assets must be loaded while the OpenGL context is current.
+///
+/// The `*_async` functions decode files on worker threads and return pending handles.
+/// The GPU upload happens in `update`, which `App` calls once per frame.
+pub struct Assets {
+    root: PathBuf,
+    textures: Cache<PathBuf, Texture2D>,
+    shaders: Cache<(PathBuf, PathBuf, Preprocessor), Shader>,
+    meshes: Cache<PathBuf, Mesh>,
+    materials: Cache<PathBuf, Material>,
+    loader: Loader,
+    program_cache: Option<ProgramCache>,
+    #[cfg(feature = "hot_reload")]
+    watcher: Option<FileWatcher>,
+}
+
+impl Assets {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Assets {
+            root: root.into(),
+            textures: Cache::default(),
+            shaders: Cache::default(),
+            meshes: Cache::default(),
+            materials: Cache::default(),
+            loader: Loader::new(),
+            program_cache: None,
+            #[cfg(feature = "hot_reload")]
+            watcher: None,
+        }
+    }
+
+    /// `res` directory next to the executable, or in the working directory if there is none.
+    pub fn default_root() -> PathBuf {
+        std::env::current_exe()
+            .ok()
+            .and_then(|exe| Some(exe.parent()?.join("res")))
+            .filter(|root| root.is_dir())
+            .unwrap_or_else(|| PathBuf::from("res"))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Joins a relative path to the asset root, absolute paths are returned unchanged.
+    pub fn resolve<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// `desc` only applies when the texture is not loaded yet.
+    pub fn load_texture<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        desc: &TextureDesc,
+    ) -> Result<Handle<Texture2D>, WhirlwingError> {
+        let path = self.resolve(path);
+        self.textures
+            .get_or_load(path.clone(), || Texture2D::from_path(&path, desc))
+    }
+
+    /// Decodes the image on a worker thread, see `load_texture`.
+    pub fn load_texture_async<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        desc: &TextureDesc,
+    ) -> Handle<Texture2D> {
+        let path = self.resolve(path);
+        let (handle, is_new) = self.textures.get_or_insert_pending(path.clone());
+        if is_new {
+            let desc = *desc;
+            self.loader.load(
+                path,
+                WhirlwingErrorKind::TextureLoadFailure,
+                |path| Texture2D::decode(path).map(Decoded::Texture),
+                Rc::downgrade(&handle.slot),
+                move |decoded| match decoded {
+                    Decoded::Texture(image) => Texture2D::from_image(&image, &desc),
+                    Decoded::Mesh(_) => unreachable!(),
+                },
+            );
+        }
+        handle
+    }
+
+    /// Shaders loaded afterwards store and reuse their program binaries in `cache`.
+    pub fn set_program_cache(&mut self, cache: Option<ProgramCache>) {
+        self.program_cache = cache;
+    }
+
+    pub fn program_cache(&self) -> Option<&ProgramCache> {
+        self.program_cache.as_ref()
+    }
+
+    /// Includes in the shader sources are resolved relative to the asset root.
+    pub fn load_shader<P: AsRef<Path>>(
+        &mut self,
+        vertex_shader_path: P,
+        fragment_shader_path: P,
+    ) -> Result<Handle<Shader>, WhirlwingError> {
+        self.load_shader_with_defines(vertex_shader_path, fragment_shader_path, &[])
+    }
+
+    /// Loads a variant of a shader with `(name, value)` pairs injected as `#define`s.
+    /// Each set of defines is compiled and cached separately.
+    pub fn load_shader_with_defines<P: AsRef<Path>>(
+        &mut self,
+        vertex_shader_path: P,
+        fragment_shader_path: P,
+        defines: &[(&str, &str)],
+    ) -> Result<Handle<Shader>, WhirlwingError> {
+        let preprocessor = defines.iter().fold(
+            Preprocessor::new(&self.root),
+            |preprocessor, (name, value)| preprocessor.define(name, value),
+        );
+        let vertex_shader_path = self.resolve(vertex_shader_path);
+        let fragment_shader_path = self.resolve(fragment_shader_path);
+        let key = (
+            vertex_shader_path.clone(),
+            fragment_shader_path.clone(),
+            preprocessor.clone(),
+        );
+        let cache = self.program_cache.as_ref();
+        let shader = self.shaders.get_or_load(key, || {
+            let (vertex, fragment) = (&vertex_shader_path, &fragment_shader_path);
+            match cache {
+                Some(cache) => Shader::from_files_cached(vertex, fragment, &preprocessor, cache),
+                None => Shader::from_files(vertex, fragment, &preprocessor),
+            }
+        })?;
+
+        #[cfg(feature = "hot_reload")]
+        if let Some(watcher) = &mut self.watcher {
+            for path in shader.get().unwrap().dependencies() {
+                if let Err(error) = watcher.watch(&path) {
+                    wwg_log::wwg_warn!("{error}");
+                }
+            }
+        }
+
+        Ok(shader)
+    }
+
+    /// Recompiles shaders loaded from files whenever one of their sources changes on disk,
+    /// including files pulled in through `#include`.
+    /// Checked in `update`, a shader which fails to compile keeps its previous program.
+    #[cfg(feature = "hot_reload")]
+    pub fn enable_hot_reload(&mut self) -> Result<(), WhirlwingError> {
+        let mut watcher = FileWatcher::new()?;
+        for handle in self.shaders.loaded() {
+            for path in handle.get().unwrap().dependencies() {
+                watcher.watch(&path)?;
+            }
+        }
+        self.watcher = Some(watcher);
+        Ok(())
+    }
+
+    #[cfg(feature = "hot_reload")]
+    fn reload_changed_shaders(&mut self) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        let changed = watcher.changed_files();
+        if changed.is_empty() {
+            return;
+        }
+
+        for handle in self.shaders.loaded() {
+            let shader = handle.get().unwrap();
+            let is_changed = shader
+                .dependencies()
+                .iter()
+                .any(|path| changed.contains(&hot_reload::canonicalize(path)));
+            if !is_changed {
+                continue;
+            }
+
+            match shader.reload() {
+                Ok(()) => {
+                    // A reload may pull in new includes.
+                    for path in shader.dependencies() {
+                        if let Err(error) = watcher.watch(&path) {
+                            wwg_log::wwg_warn!("{error}");
+                        }
+                    }
+                    let (_, vertex_shader_path) = shader.source_paths()[0];
+                    wwg_log::wwg_info!("Reloaded shader: {}", vertex_shader_path.display());
+                }
+                Err(error) => {
+                    wwg_log::wwg_err!("{error}");
+                }
+            }
+        }
+    }
+
+    pub fn load_mesh<P: AsRef<Path>>(&mut self, path: P) -> Result<Handle<Mesh>, WhirlwingError> {
+        let path = self.resolve(path);
+        self.meshes.get_or_load(path.clone(), || Mesh::from_obj(&path))
+    }
+
+    /// Parses the OBJ file on a worker thread, see `load_mesh`.
+    pub fn load_mesh_async<P: AsRef<Path>>(&mut self, path: P) -> Handle<Mesh> {
+        let path = self.resolve(path);
+        let (handle, is_new) = self.meshes.get_or_insert_pending(path.clone());
+        if is_new {
+            self.loader.load(
+                path,
+                WhirlwingErrorKind::MeshLoadFailure,
+                |path| MeshData::from_obj(path).map(Decoded::Mesh),
+                Rc::downgrade(&handle.slot),
+                |decoded| match decoded {
+                    Decoded::Mesh(data) => Mesh::from_data(&data),
+                    Decoded::Texture(_) => unreachable!(),
+                },
+            );
+        }
+        handle
+    }
+
+    /// Loads a material file, see `Material` for the format.
+    /// Its textures load asynchronously like with `load_texture_async`.
+    pub fn load_material<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<Handle<Material>, WhirlwingError> {
+        let path = self.resolve(path);
+        // Not `get_or_load`, loading the material needs `self` for its shader and textures.
+        if let Some(handle) = self.materials.get(&path) {
+            return Ok(handle);
+        }
+        let handle = Handle::new(Material::from_toml_file(&path, self)?);
+        self.materials.insert(path, &handle);
+        Ok(handle)
+    }
+
+    /// Uploads assets decoded since the last call. Must be called with the OpenGL context current.
+    pub fn update(&mut self) {
+        self.loader.upload_finished();
+        #[cfg(feature = "hot_reload")]
+        self.reload_changed_shaders();
+    }
+
+    /// Blocks until every asynchronous load has been uploaded, e.g. at the end of a loading screen.
+    pub fn wait(&mut self) {
+        self.loader.wait();
+    }
+
+    /// Number of asynchronous loads which have not been uploaded yet.
+    pub fn pending_loads(&self) -> usize {
+        self.loader.uploads.len()
+    }
+}
+
+impl Default for Assets {
+    fn default() -> Self {
+        Assets::new(Assets::default_root())
+    }
+}
+
+struct Cache<K, T> {
+    entries: HashMap<K, Weak<OnceCell<Result<T, WhirlwingError>>>>,
+}
+
+impl<K, T> Default for Cache<K, T> {
+    fn default() -> Self {
+        Cache {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, T> Cache<K, T> {
+    // Pending and loaded entries are reused, failed ones are loaded again.
+    fn get(&self, key: &K) -> Option<Handle<T>> {
+        let slot = self.entries.get(key)?.upgrade()?;
+        match slot.get() {
+            Some(Err(_)) => None,
+            _ => Some(Handle { slot }),
+        }
+    }
+
+    /// Live handles which finished loading successfully.
+    #[cfg(feature = "hot_reload")]
+    fn loaded(&self) -> Vec<Handle<T>> {
+        self.entries
+            .values()
+            .filter_map(Weak::upgrade)
+            .map(|slot| Handle { slot })
+            .filter(Handle::is_loaded)
+            .collect()
+    }
+
+    fn insert(&mut self, key: K, handle: &Handle<T>) {
+        self.entries.retain(|_, slot| slot.strong_count() > 0);
+        self.entries.insert(key, Rc::downgrade(&handle.slot));
+    }
+
+    fn get_or_load<F>(&mut self, key: K, load: F) -> Result<Handle<T>, WhirlwingError>
+    where
+        F: FnOnce() -> Result<T, WhirlwingError>,
+    {
+        match self.get(&key) {
+            Some(handle) if handle.is_loaded() => Ok(handle),
+            // Still loading asynchronously, finish it here instead of waiting for the worker.
+            Some(handle) => {
+                handle.finish(load());
+                match handle.state() {
+                    LoadState::Failed(error) => Err(error),
+                    _ => Ok(handle),
+                }
+            }
+            None => {
+                let handle = Handle::new(load()?);
+                self.insert(key, &handle);
+                Ok(handle)
+            }
+        }
+    }
+
+    /// Returns the cached handle, or a new pending one and `true` if it has to be loaded.
+    fn get_or_insert_pending(&mut self, key: K) -> (Handle<T>, bool) {
+        match self.get(&key) {
+            Some(handle) => (handle, false),
+            None => {
+                let handle = Handle::pending();
+                self.insert(key, &handle);
+                (handle, true)
+            }
+        }
+    }
+}
+
+/// CPU side data produced by worker threads.
+enum Decoded {
+    Texture(DynamicImage),
+    Mesh(MeshData),
+}
+
+type Upload = Box<dyn FnOnce(Result<Decoded, WhirlwingError>)>;
+
+struct Loader {
+    // Spawned on the first asynchronous load.
+    pool: Option<WorkerPool>,
+    sender: mpsc::Sender<(u64, Result<Decoded, WhirlwingError>)>,
+    receiver: mpsc::Receiver<(u64, Result<Decoded, WhirlwingError>)>,
+    uploads: HashMap<u64, Upload>,
+    next_id: u64,
+}
+
+impl Loader {
+    const MAX_THREADS: usize = 4;
+
+    fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Loader {
+            pool: None,
+            sender,
+            receiver,
+            uploads: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Failures and panics of `decode` finish the handle with an error of `kind`.
+    fn load<T: 'static>(
+        &mut self,
+        path: PathBuf,
+        kind: WhirlwingErrorKind,
+        decode: impl FnOnce(&Path) -> Result<Decoded, WhirlwingError> + Send + 'static,
+        slot: Weak<OnceCell<Result<T, WhirlwingError>>>,
+        upload: impl FnOnce(Decoded) -> T + 'static,
+    ) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.uploads.insert(
+            id,
+            Box::new(move |decoded| {
+                // Nobody holds the handle anymore, skip the upload.
+                let Some(slot) = slot.upgrade() else {
+                    return;
+                };
+                Handle { slot }.finish(decoded.map(upload));
+            }),
+        );
+
+        let sender = self.sender.clone();
+        self.pool
+            .get_or_insert_with(|| {
+                let threads = std::thread::available_parallelism()
+                    .map(|threads| threads.get())
+                    .unwrap_or(1);
+                WorkerPool::new(threads.min(Self::MAX_THREADS))
+            })
+            .execute(move || {
+                // Decoders may panic on malformed files, the result has to be sent anyway or
+                // `wait` would block forever.
+                let decoded = std::panic::catch_unwind(AssertUnwindSafe(|| decode(&path)))
+                    .unwrap_or_else(|panic| {
+                        let message = panic
+                            .downcast_ref::<&str>()
+                            .copied()
+                            .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                            .unwrap_or("unknown panic");
+                        Err(WhirlwingError::new(
+                            format!("Decoder panicked on {}: {message}", path.display()),
+                            kind,
+                        ))
+                    });
+                let _ = sender.send((id, decoded));
+            });
+    }
+
+    fn upload_finished(&mut self) {
+        while let Ok((id, decoded)) = self.receiver.try_recv() {
+            self.upload(id, decoded);
+        }
+    }
+
+    fn wait(&mut self) {
+        while !self.uploads.is_empty() {
+            // The loader owns a sender, so the channel is never closed. Every job sends a
+            // result, even if its decoder panics.
+            let (id, decoded) = self.receiver.recv().unwrap();
+            self.upload(id, decoded);
+        }
+    }
+
+    fn upload(&mut self, id: u64, decoded: Result<Decoded, WhirlwingError>) {
+        if let Some(upload) = self.uploads.remove(&id) {
+            if let Err(error) = &decoded {
+                wwg_log::wwg_err!("{error}");
+            }
+            upload(decoded);
+        }
+    }
+}
@@ -1,27 +1,49 @@
-use wwg_math::{Vec3, Rotor3, Isometry3, Mat4};
+use wwg_math::{Vec3, Vec4, Rotor3, Isometry3, Mat4};
 
-pub struct Camera(Isometry3);
+use crate::uniform_buffer::Padding;
+
+pub struct Camera {
+    transform: Isometry3,
+    clear_color: Vec4,
+}
 
 impl Camera {
     // To set default rotation use Rotor3::identity()
     pub fn new(position: Vec3, rotation: Rotor3) -> Self {
-        Camera(Isometry3::new(position, rotation))
+        Camera {
+            transform: Isometry3::new(position, rotation),
+            // The former gamma space (0.2, 0.3, 0.3) in linear space.
+            clear_color: Vec4::new(0.033, 0.073, 0.073, 1.0),
+        }
+    }
+
+    /// Linear colour the frame is cleared to, values above 1 are kept in HDR.
+    pub fn clear_color(&self) -> Vec4 {
+        self.clear_color
+    }
+
+    pub fn set_clear_color(&mut self, color: Vec4) {
+        self.clear_color = color;
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.transform.translation
     }
 
     pub fn set_position(&mut self, position: Vec3) {
-        self.0.translation = position;
+        self.transform.translation = position;
     }
 
     pub fn translate(&mut self, translation: Vec3) {
-        self.0.prepend_translation(translation);
+        self.transform.prepend_translation(translation);
     }
 
     pub fn rotate_xz(&mut self, angle: f32) {
-        self.0.rotation = Rotor3::from_rotation_xz(angle) * self.0.rotation;
+        self.transform.rotation = Rotor3::from_rotation_xz(angle) * self.transform.rotation;
     }
 
     pub fn rotate_yz(&mut self, angle: f32) {
-        self.0.rotation = Rotor3::from_rotation_yz(angle) * self.0.rotation;
+        self.transform.rotation = Rotor3::from_rotation_yz(angle) * self.transform.rotation;
     }
 
     pub(crate) fn create_cam_tmp() -> Self {
@@ -29,6 +51,37 @@ impl Camera {
     }
 
     pub(crate) fn view_matrix(&self) -> Mat4 {
-        self.0.into_homogeneous_matrix().inversed()
+        self.transform.into_homogeneous_matrix().inversed()
+    }
+}
+
+crate::std140! {
+    /// Contents of the `Camera` uniform block, uploaded once per frame by the renderer.
+    /// Shaders can include `shaders/common/camera.glsl` to declare it:
+    ///
+    /// ```glsl
+    /// layout (std140) uniform Camera {
+    ///     mat4 view;
+    ///     mat4 projection;
+    ///     vec3 camera_position;
+    /// };
+    /// ```
+    #[derive(Debug, Default)]
+    pub struct CameraUniforms {
+        pub view: Mat4,
+        pub projection: Mat4,
+        pub position: Vec3,
+        _padding: Padding<4>,
+    }
+}
+
+impl CameraUniforms {
+    pub fn new(camera: &Camera, projection: Mat4) -> Self {
+        CameraUniforms {
+            view: camera.view_matrix(),
+            projection,
+            position: camera.position(),
+            _padding: Padding::default(),
+        }
     }
-}
\ No newline at end of file
+}
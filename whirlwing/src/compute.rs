@@ -0,0 +1,137 @@
+use std::{ops::BitOr, path::Path};
+
+use wwg_error::WhirlwingError;
+
+use crate::{
+    preprocessor::Preprocessor,
+    program_cache::ProgramCache,
+    shader::{Shader, ShaderProgramBuilder, ShaderStage},
+};
+
+/// Program made of a single compute shader, run with `dispatch`.
+///
+/// Needs OpenGL 4.3 or OpenGL ES 3.1, creating one on an older context fails with
+/// `WhirlwingErrorKind::UnsupportedFeature`. Uniforms, blocks and reloading work through
+/// `shader`. Writes are only visible to later commands after a `memory_barrier`.
+#[derive(Debug)]
+pub struct ComputeProgram {
+    shader: Shader,
+}
+
+impl ComputeProgram {
+    pub fn from_file<P: AsRef<Path>>(
+        path: P,
+        preprocessor: &Preprocessor,
+    ) -> Result<ComputeProgram, WhirlwingError> {
+        ComputeProgram::link(
+            ShaderProgramBuilder::new()
+                .with_file(ShaderStage::Compute, path.as_ref())
+                .with_preprocessor(preprocessor.clone()),
+        )
+    }
+
+    /// Like `from_file`, but reuses the program binary stored in `cache` if there is one.
+    pub fn from_file_cached<P: AsRef<Path>>(
+        path: P,
+        preprocessor: &Preprocessor,
+        cache: &ProgramCache,
+    ) -> Result<ComputeProgram, WhirlwingError> {
+        ComputeProgram::link(
+            ShaderProgramBuilder::new()
+                .with_file(ShaderStage::Compute, path.as_ref())
+                .with_preprocessor(preprocessor.clone())
+                .with_program_cache(cache.clone()),
+        )
+    }
+
+    /// Includes are resolved relative to the working directory.
+    pub fn from_source(source: &str) -> Result<ComputeProgram, WhirlwingError> {
+        ComputeProgram::link(ShaderProgramBuilder::new().with_source(ShaderStage::Compute, source))
+    }
+
+    fn link(builder: ShaderProgramBuilder) -> Result<ComputeProgram, WhirlwingError> {
+        Ok(ComputeProgram {
+            shader: builder.link()?,
+        })
+    }
+
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    /// `local_size_x`, `local_size_y` and `local_size_z` declared in the shader.
+    pub fn work_group_size(&self) -> [u32; 3] {
+        let mut size = [0i32; 3];
+        unsafe {
+            gl::GetProgramiv(
+                self.shader.id(),
+                gl::COMPUTE_WORK_GROUP_SIZE,
+                size.as_mut_ptr(),
+            );
+        }
+        size.map(|size| size as u32)
+    }
+
+    /// Binds the program and runs `x * y * z` work groups.
+    pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+        self.shader.bind();
+        unsafe {
+            gl::DispatchCompute(x, y, z);
+        }
+    }
+
+    /// Runs enough work groups for at least `invocations` along each axis,
+    /// the shader has to skip the invocations past the end.
+    pub fn dispatch_invocations(&self, invocations: [u32; 3]) {
+        let size = self.work_group_size();
+        let [x, y, z] = [0, 1, 2].map(|axis| {
+            let size = size[axis].max(1);
+            // `u32::div_ceil` needs Rust 1.73, adding `size - 1` first could overflow.
+            invocations[axis] / size + u32::from(invocations[axis] % size != 0)
+        });
+        self.dispatch(x, y, z);
+    }
+}
+
+/// Kinds of access which have to see the writes of earlier compute dispatches,
+/// combined with `|`. Named after the `GL_*_BARRIER_BIT` they stand for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoryBarrier(u32);
+
+impl MemoryBarrier {
+    /// Vertex attributes read from buffers, e.g. particle positions.
+    pub const VERTEX_ATTRIB_ARRAY: MemoryBarrier =
+        MemoryBarrier(gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT);
+    pub const ELEMENT_ARRAY: MemoryBarrier = MemoryBarrier(gl::ELEMENT_ARRAY_BARRIER_BIT);
+    pub const UNIFORM: MemoryBarrier = MemoryBarrier(gl::UNIFORM_BARRIER_BIT);
+    pub const TEXTURE_FETCH: MemoryBarrier = MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT);
+    pub const SHADER_IMAGE_ACCESS: MemoryBarrier =
+        MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+    /// Indirect draw and dispatch arguments, e.g. written by GPU culling.
+    pub const COMMAND: MemoryBarrier = MemoryBarrier(gl::COMMAND_BARRIER_BIT);
+    /// Reading buffers back or copying them, e.g. with `glGetBufferSubData`.
+    pub const BUFFER_UPDATE: MemoryBarrier = MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+    pub const TEXTURE_UPDATE: MemoryBarrier = MemoryBarrier(gl::TEXTURE_UPDATE_BARRIER_BIT);
+    pub const FRAMEBUFFER: MemoryBarrier = MemoryBarrier(gl::FRAMEBUFFER_BARRIER_BIT);
+    pub const SHADER_STORAGE: MemoryBarrier = MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+    pub const ALL: MemoryBarrier = MemoryBarrier(gl::ALL_BARRIER_BITS);
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+impl BitOr for MemoryBarrier {
+    type Output = MemoryBarrier;
+
+    fn bitor(self, other: MemoryBarrier) -> MemoryBarrier {
+        MemoryBarrier(self.0 | other.0)
+    }
+}
+
+/// Waits for earlier shader writes before the accesses in `barriers` happen.
+pub fn memory_barrier(barriers: MemoryBarrier) {
+    unsafe {
+        gl::MemoryBarrier(barriers.0);
+    }
+}
@@ -0,0 +1,142 @@
+use std::cell::{Ref, RefMut};
+
+use crate::{
+    assets::Assets,
+    camera::Camera,
+    ecs::World,
+    input::Input,
+    light::Lights,
+    post_process::PostProcess,
+    render_queue::RenderQueue,
+    render_target::RenderTargets,
+    scene::Scene,
+    schedule::{Commands, Schedule, Stage},
+    time::Time,
+};
+
+/// Engine state shared with the `Game` callbacks.
+///
+/// The world always holds the `Time`, `Input`, active `Camera`, `Scene`, `RenderQueue`,
+/// `Lights`, `RenderTargets` and `PostProcess` resources, `World::remove_resource` refuses to
+/// remove them.
+pub struct Context {
+    world: World,
+    schedule: Schedule,
+    assets: Assets,
+    exit_requested: bool,
+}
+
+impl Context {
+    pub(crate) fn new(assets: Assets) -> Self {
+        let mut world = World::new();
+        world.insert_required_resource(Time::start());
+        world.insert_required_resource(Input::default());
+        world.insert_required_resource(Camera::create_cam_tmp());
+        world.insert_required_resource(Scene::new());
+        world.insert_required_resource(RenderQueue::new());
+        world.insert_required_resource(Lights::new());
+        world.insert_required_resource(RenderTargets::new());
+        world.insert_required_resource(PostProcess::hdr());
+        Context {
+            world,
+            schedule: Schedule::new(),
+            assets,
+            exit_requested: false,
+        }
+    }
+
+    pub fn camera(&self) -> Ref<'_, Camera> {
+        self.world.resource().unwrap()
+    }
+
+    pub fn camera_mut(&mut self) -> RefMut<'_, Camera> {
+        self.world.resource_mut().unwrap()
+    }
+
+    /// Drawn every frame, from its active camera if it has one.
+    pub fn scene(&self) -> Ref<'_, Scene> {
+        self.world.resource().unwrap()
+    }
+
+    pub fn scene_mut(&mut self) -> RefMut<'_, Scene> {
+        self.world.resource_mut().unwrap()
+    }
+
+    pub fn input(&self) -> Ref<'_, Input> {
+        self.world.resource().unwrap()
+    }
+
+    pub fn time(&self) -> Ref<'_, Time> {
+        self.world.resource().unwrap()
+    }
+
+    /// Items submitted during a frame are drawn together with the scene.
+    pub fn render_queue_mut(&mut self) -> RefMut<'_, RenderQueue> {
+        self.world.resource_mut().unwrap()
+    }
+
+    /// Lights submitted during a frame are used for that frame only, entities with a
+    /// `Light` and an `Isometry3` component are added before drawing.
+    pub fn lights_mut(&mut self) -> RefMut<'_, Lights> {
+        self.world.resource_mut().unwrap()
+    }
+
+    /// Passes applied to the scene before it reaches the window.
+    pub fn post_process_mut(&mut self) -> RefMut<'_, PostProcess> {
+        self.world.resource_mut().unwrap()
+    }
+
+    pub fn render_targets(&self) -> Ref<'_, RenderTargets> {
+        self.world.resource().unwrap()
+    }
+
+    /// Targets added here are resized with the window.
+    pub fn render_targets_mut(&mut self) -> RefMut<'_, RenderTargets> {
+        self.world.resource_mut().unwrap()
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Runs `system` every frame during `stage`, see `Schedule`.
+    pub fn add_system<F>(&mut self, stage: Stage, system: F)
+    where
+        F: FnMut(&World, &mut Commands) + 'static,
+    {
+        self.schedule.add_system(stage, system);
+    }
+
+    pub fn assets(&self) -> &Assets {
+        &self.assets
+    }
+
+    pub fn assets_mut(&mut self) -> &mut Assets {
+        &mut self.assets
+    }
+
+    /// Asks the event loop to stop after the current event has been handled.
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    pub(crate) fn input_mut(&mut self) -> RefMut<'_, Input> {
+        self.world.resource_mut().unwrap()
+    }
+
+    pub(crate) fn time_mut(&mut self) -> RefMut<'_, Time> {
+        self.world.resource_mut().unwrap()
+    }
+
+    pub(crate) fn run_stage(&mut self, stage: Stage) {
+        self.schedule.run_stage(stage, &mut self.world);
+    }
+
+    pub(crate) fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+}
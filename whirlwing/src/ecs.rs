@@ -0,0 +1,580 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    cell::{Ref, RefCell, RefMut},
+    collections::{HashMap, HashSet},
+};
+
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+
+/// Identifies a game object of a `World`. Ids of despawned entities are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+/// Data attached to an entity, any `'static` type is one.
+pub trait Component: 'static {}
+
+impl<T: 'static> Component for T {}
+
+/// Components inserted together, tuples of up to 8 components.
+pub trait Bundle: 'static {
+    fn insert_into(self, world: &mut World, entity: Entity);
+}
+
+impl Bundle for () {
+    fn insert_into(self, _world: &mut World, _entity: Entity) {}
+}
+
+macro_rules! bundle_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Component),+> Bundle for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn insert_into(self, world: &mut World, entity: Entity) {
+                let ($($name,)+) = self;
+                $(world.storage_mut::<$name>().insert(entity, $name);)+
+            }
+        }
+    };
+}
+
+bundle_tuple!(A);
+bundle_tuple!(A, B);
+bundle_tuple!(A, B, C);
+bundle_tuple!(A, B, C, D);
+bundle_tuple!(A, B, C, D, E);
+bundle_tuple!(A, B, C, D, E, F);
+bundle_tuple!(A, B, C, D, E, F, G);
+bundle_tuple!(A, B, C, D, E, F, G, H);
+
+#[derive(Default)]
+struct Entities {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+}
+
+impl Entities {
+    fn alloc(&mut self) -> Entity {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.generations.push(0);
+                self.alive.push(false);
+                self.alive.len() as u32 - 1
+            }
+        };
+        self.alive[index as usize] = true;
+        Entity {
+            index,
+            generation: self.generations[index as usize],
+        }
+    }
+
+    fn free(&mut self, entity: Entity) -> bool {
+        if !self.contains(entity) {
+            return false;
+        }
+        self.alive[entity.index as usize] = false;
+        self.generations[entity.index as usize] += 1;
+        self.free.push(entity.index);
+        true
+    }
+
+    fn contains(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        self.alive.get(index) == Some(&true) && self.generations[index] == entity.generation
+    }
+
+    fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        (0..self.alive.len())
+            .filter(|index| self.alive[*index])
+            .map(|index| Entity {
+                index: index as u32,
+                generation: self.generations[index],
+            })
+    }
+}
+
+const NONE: u32 = u32::MAX;
+
+/// Components of one type packed densely, with a sparse entity index → dense index lookup.
+struct SparseSet<T> {
+    sparse: Vec<u32>,
+    entities: Vec<Entity>,
+    components: Vec<T>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        SparseSet {
+            sparse: Vec::new(),
+            entities: Vec::new(),
+            components: Vec::new(),
+        }
+    }
+}
+
+impl<T> SparseSet<T> {
+    fn index(&self, entity: Entity) -> Option<usize> {
+        let index = *self.sparse.get(entity.index as usize)?;
+        (index != NONE && self.entities[index as usize] == entity).then_some(index as usize)
+    }
+
+    fn get(&self, entity: Entity) -> Option<&T> {
+        self.index(entity).map(|index| &self.components[index])
+    }
+
+    fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.index(entity).map(|index| &mut self.components[index])
+    }
+
+    fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        if let Some(index) = self.index(entity) {
+            return Some(std::mem::replace(&mut self.components[index], component));
+        }
+        let slot = entity.index as usize;
+        if self.sparse.len() <= slot {
+            self.sparse.resize(slot + 1, NONE);
+        }
+        self.sparse[slot] = self.entities.len() as u32;
+        self.entities.push(entity);
+        self.components.push(component);
+        None
+    }
+
+    fn remove(&mut self, entity: Entity) -> Option<T> {
+        let index = self.index(entity)?;
+        self.sparse[entity.index as usize] = NONE;
+        self.entities.swap_remove(index);
+        let component = self.components.swap_remove(index);
+        if let Some(moved) = self.entities.get(index) {
+            self.sparse[moved.index as usize] = index as u32;
+        }
+        Some(component)
+    }
+}
+
+trait Storage: Any {
+    fn despawn(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Component> Storage for SparseSet<T> {
+    fn despawn(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Entities with their components, and resources: values which exist once per world.
+///
+/// Components of each type are stored in their own sparse set. Queries and resources
+/// borrow their storage at runtime like a `RefCell`, so systems can read and write
+/// through a shared `&World`. Borrowing the same storage mutably twice panics.
+#[derive(Default)]
+pub struct World {
+    // Behind a `RefCell` so `Commands` can reserve entities through a shared world.
+    entities: RefCell<Entities>,
+    storages: HashMap<TypeId, RefCell<Box<dyn Storage>>>,
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>,
+    // Resources the engine relies on, they can be replaced but not removed.
+    required: HashSet<TypeId>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        World::default()
+    }
+
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let entity = self.entities.get_mut().alloc();
+        bundle.insert_into(self, entity);
+        entity
+    }
+
+    /// Entity without components which exists right away, for `Commands`.
+    pub(crate) fn reserve(&self) -> Entity {
+        self.entities.borrow_mut().alloc()
+    }
+
+    /// Removes the entity and all its components.
+    pub fn despawn(&mut self, entity: Entity) -> Result<(), WhirlwingError> {
+        if !self.entities.get_mut().free(entity) {
+            return Err(missing_entity(entity));
+        }
+        for storage in self.storages.values_mut() {
+            storage.get_mut().despawn(entity);
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.borrow().contains(entity)
+    }
+
+    /// Adds the components to the entity, replacing components of the same type.
+    pub fn insert<B: Bundle>(&mut self, entity: Entity, bundle: B) -> Result<(), WhirlwingError> {
+        if !self.contains(entity) {
+            return Err(missing_entity(entity));
+        }
+        bundle.insert_into(self, entity);
+        Ok(())
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())?
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .unwrap()
+            .remove(entity)
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.storage::<T>()?, |storage| storage.get(entity)).ok()
+    }
+
+    pub fn get_mut<T: Component>(&self, entity: Entity) -> Option<RefMut<'_, T>> {
+        RefMut::filter_map(self.storage_write::<T>()?, |storage| {
+            storage.get_mut(entity)
+        })
+        .ok()
+    }
+
+    /// Live entities, in no particular order.
+    pub fn entities(&self) -> Vec<Entity> {
+        self.entities.borrow().iter().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        let entities = self.entities.borrow();
+        entities.alive.len() - entities.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Borrows the storages the query reads or writes until the `QueryBorrow` is dropped:
+    ///
+    /// ```
+    /// # use whirlwing::ecs::World;
+    /// struct Position(f32);
+    /// struct Velocity(f32);
+    ///
+    /// let mut world = World::new();
+    /// world.spawn((Position(0.0), Velocity(1.0)));
+    /// for (_, (position, velocity)) in world.query::<(&mut Position, &Velocity)>().iter() {
+    ///     position.0 += velocity.0;
+    /// }
+    /// ```
+    pub fn query<Q: Query>(&self) -> QueryBorrow<'_, Q> {
+        let fetch = Q::borrow(self);
+        // Queries made only of optional components visit every entity.
+        let all = match &fetch {
+            Some(fetch) if Q::candidates(fetch).is_none() => self.entities(),
+            _ => Vec::new(),
+        };
+        QueryBorrow { fetch, all }
+    }
+
+    /// Replaces the resource of the same type and returns it.
+    pub fn insert_resource<R: 'static>(&mut self, resource: R) -> Option<R> {
+        self.resources
+            .insert(TypeId::of::<R>(), RefCell::new(Box::new(resource)))
+            .map(|previous| *previous.into_inner().downcast().unwrap())
+    }
+
+    /// Inserts a resource which `remove_resource` refuses to remove.
+    pub(crate) fn insert_required_resource<R: 'static>(&mut self, resource: R) {
+        self.insert_resource(resource);
+        self.required.insert(TypeId::of::<R>());
+    }
+
+    /// Returns `None` for resources the engine relies on, like the `Camera` of a
+    /// `Context`, those can only be replaced.
+    pub fn remove_resource<R: 'static>(&mut self) -> Option<R> {
+        if self.required.contains(&TypeId::of::<R>()) {
+            wwg_log::wwg_warn!("Resource {} cannot be removed", type_name::<R>());
+            return None;
+        }
+        let resource = self.resources.remove(&TypeId::of::<R>())?;
+        Some(*resource.into_inner().downcast().unwrap())
+    }
+
+    pub fn contains_resource<R: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    /// Panics if the resource is borrowed mutably.
+    pub fn resource<R: 'static>(&self) -> Option<Ref<'_, R>> {
+        let resource = self.resources.get(&TypeId::of::<R>())?;
+        let resource = resource
+            .try_borrow()
+            .unwrap_or_else(|_| panic!("Resource {} is borrowed mutably", type_name::<R>()));
+        Some(Ref::map(resource, |resource| {
+            resource.downcast_ref().unwrap()
+        }))
+    }
+
+    /// Panics if the resource is borrowed.
+    pub fn resource_mut<R: 'static>(&self) -> Option<RefMut<'_, R>> {
+        let resource = self.resources.get(&TypeId::of::<R>())?;
+        let resource = resource
+            .try_borrow_mut()
+            .unwrap_or_else(|_| panic!("Resource {} is already borrowed", type_name::<R>()));
+        Some(RefMut::map(resource, |resource| {
+            resource.downcast_mut().unwrap()
+        }))
+    }
+
+    fn storage<T: Component>(&self) -> Option<Ref<'_, SparseSet<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?;
+        let storage = storage
+            .try_borrow()
+            .unwrap_or_else(|_| panic!("Component {} is borrowed mutably", type_name::<T>()));
+        Some(Ref::map(storage, |storage| {
+            storage.as_any().downcast_ref().unwrap()
+        }))
+    }
+
+    fn storage_write<T: Component>(&self) -> Option<RefMut<'_, SparseSet<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?;
+        let storage = storage
+            .try_borrow_mut()
+            .unwrap_or_else(|_| panic!("Component {} is already borrowed", type_name::<T>()));
+        Some(RefMut::map(storage, |storage| {
+            storage.as_any_mut().downcast_mut().unwrap()
+        }))
+    }
+
+    fn storage_mut<T: Component>(&mut self) -> &mut SparseSet<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| RefCell::new(Box::<SparseSet<T>>::default()))
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+}
+
+fn missing_entity(entity: Entity) -> WhirlwingError {
+    WhirlwingError::new(
+        format!("{entity:?} does not exist"),
+        WhirlwingErrorKind::EcsFailure,
+    )
+}
+
+/// Components an entity must have to be visited by `World::query`, and how they are accessed.
+///
+/// Implemented for `&T`, `&mut T`, `Option<&T>`, `Option<&mut T>` and tuples of up to
+/// 8 of those. Entities missing a component in an `Option` are still visited.
+pub trait Query {
+    type Item<'q>;
+    /// Borrowed storages.
+    type Fetch<'w>;
+
+    /// `None` if a required component was never inserted.
+    fn borrow(world: &World) -> Option<Self::Fetch<'_>>;
+
+    /// Entities which may match, `None` if the query has no required components.
+    fn candidates<'a>(fetch: &'a Self::Fetch<'_>) -> Option<&'a [Entity]>;
+
+    /// # Safety
+    ///
+    /// Must not be called twice for the same entity while the returned item is alive.
+    unsafe fn get<'q>(fetch: &'q Self::Fetch<'_>, entity: Entity) -> Option<Self::Item<'q>>;
+}
+
+/// Fetch of `&T`.
+pub struct Read<'w, T>(Ref<'w, SparseSet<T>>);
+
+/// Fetch of `&mut T`.
+pub struct Write<'w, T> {
+    storage: RefMut<'w, SparseSet<T>>,
+    // Taken while the storage is borrowed mutably, nothing else can move the components.
+    components: *mut T,
+}
+
+impl<'w, T: Component> Write<'w, T> {
+    fn new(mut storage: RefMut<'w, SparseSet<T>>) -> Self {
+        let components = storage.components.as_mut_ptr();
+        Write {
+            storage,
+            components,
+        }
+    }
+
+    // Callers make sure no two items of the same entity are alive.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn get(&self, entity: Entity) -> Option<&mut T> {
+        let index = self.storage.index(entity)?;
+        Some(&mut *self.components.add(index))
+    }
+}
+
+impl<T: Component> Query for &T {
+    type Item<'q> = &'q T;
+    type Fetch<'w> = Read<'w, T>;
+
+    fn borrow(world: &World) -> Option<Self::Fetch<'_>> {
+        world.storage().map(Read)
+    }
+
+    fn candidates<'f>(fetch: &'f Self::Fetch<'_>) -> Option<&'f [Entity]> {
+        Some(&fetch.0.entities)
+    }
+
+    unsafe fn get<'q>(fetch: &'q Self::Fetch<'_>, entity: Entity) -> Option<Self::Item<'q>> {
+        fetch.0.get(entity)
+    }
+}
+
+impl<T: Component> Query for &mut T {
+    type Item<'q> = &'q mut T;
+    type Fetch<'w> = Write<'w, T>;
+
+    fn borrow(world: &World) -> Option<Self::Fetch<'_>> {
+        world.storage_write().map(Write::new)
+    }
+
+    fn candidates<'f>(fetch: &'f Self::Fetch<'_>) -> Option<&'f [Entity]> {
+        Some(&fetch.storage.entities)
+    }
+
+    unsafe fn get<'q>(fetch: &'q Self::Fetch<'_>, entity: Entity) -> Option<Self::Item<'q>> {
+        fetch.get(entity)
+    }
+}
+
+impl<T: Component> Query for Option<&T> {
+    type Item<'q> = Option<&'q T>;
+    type Fetch<'w> = Option<Read<'w, T>>;
+
+    fn borrow(world: &World) -> Option<Self::Fetch<'_>> {
+        Some(world.storage().map(Read))
+    }
+
+    fn candidates<'f>(_fetch: &'f Self::Fetch<'_>) -> Option<&'f [Entity]> {
+        None
+    }
+
+    unsafe fn get<'q>(fetch: &'q Self::Fetch<'_>, entity: Entity) -> Option<Self::Item<'q>> {
+        Some(fetch.as_ref().and_then(|fetch| fetch.0.get(entity)))
+    }
+}
+
+impl<T: Component> Query for Option<&mut T> {
+    type Item<'q> = Option<&'q mut T>;
+    type Fetch<'w> = Option<Write<'w, T>>;
+
+    fn borrow(world: &World) -> Option<Self::Fetch<'_>> {
+        Some(world.storage_write().map(Write::new))
+    }
+
+    fn candidates<'f>(_fetch: &'f Self::Fetch<'_>) -> Option<&'f [Entity]> {
+        None
+    }
+
+    unsafe fn get<'q>(fetch: &'q Self::Fetch<'_>, entity: Entity) -> Option<Self::Item<'q>> {
+        Some(fetch.as_ref().and_then(|fetch| fetch.get(entity)))
+    }
+}
+
+macro_rules! query_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Query),+> Query for ($($name,)+) {
+            type Item<'q> = ($($name::Item<'q>,)+);
+            type Fetch<'w> = ($($name::Fetch<'w>,)+);
+
+            fn borrow(world: &World) -> Option<Self::Fetch<'_>> {
+                Some(($($name::borrow(world)?,)+))
+            }
+
+            #[allow(non_snake_case)]
+            fn candidates<'f>(fetch: &'f Self::Fetch<'_>) -> Option<&'f [Entity]> {
+                let ($($name,)+) = fetch;
+                [$($name::candidates($name)),+]
+                    .into_iter()
+                    .flatten()
+                    .min_by_key(|candidates| candidates.len())
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn get<'q>(
+                fetch: &'q Self::Fetch<'_>,
+                entity: Entity,
+            ) -> Option<Self::Item<'q>> {
+                let ($($name,)+) = fetch;
+                Some(($($name::get($name, entity)?,)+))
+            }
+        }
+    };
+}
+
+query_tuple!(A);
+query_tuple!(A, B);
+query_tuple!(A, B, C);
+query_tuple!(A, B, C, D);
+query_tuple!(A, B, C, D, E);
+query_tuple!(A, B, C, D, E, F);
+query_tuple!(A, B, C, D, E, F, G);
+query_tuple!(A, B, C, D, E, F, G, H);
+
+/// Storages borrowed by a query, see `World::query`.
+pub struct QueryBorrow<'w, Q: Query> {
+    fetch: Option<Q::Fetch<'w>>,
+    all: Vec<Entity>,
+}
+
+impl<'w, Q: Query> QueryBorrow<'w, Q> {
+    /// Matching entities with their components, in no particular order.
+    pub fn iter(&mut self) -> QueryIter<'_, 'w, Q> {
+        let entities = match &self.fetch {
+            Some(fetch) => Q::candidates(fetch).unwrap_or(&self.all),
+            None => &[],
+        };
+        QueryIter {
+            fetch: self.fetch.as_ref(),
+            entities: entities.iter(),
+        }
+    }
+
+    /// Components of one entity, `None` if it does not match.
+    pub fn get(&mut self, entity: Entity) -> Option<Q::Item<'_>> {
+        // Only one item can be alive since `self` is borrowed mutably.
+        unsafe { Q::get(self.fetch.as_ref()?, entity) }
+    }
+}
+
+pub struct QueryIter<'q, 'w, Q: Query> {
+    fetch: Option<&'q Q::Fetch<'w>>,
+    entities: std::slice::Iter<'q, Entity>,
+}
+
+impl<'q, 'w, Q: Query> Iterator for QueryIter<'q, 'w, Q> {
+    type Item = (Entity, Q::Item<'q>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let fetch = self.fetch?;
+        for entity in self.entities.by_ref() {
+            // Every entity is visited once.
+            if let Some(item) = unsafe { Q::get(fetch, *entity) } {
+                return Some((*entity, item));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,193 @@
+use glutin::{
+    api::egl::{self, context::PossiblyCurrentContext, device::Device},
+    config::{Api, ConfigSurfaceTypes, ConfigTemplateBuilder},
+    display::GlDisplay,
+};
+use image::RgbaImage;
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+
+use crate::{
+    assets::Assets,
+    camera::Camera,
+    light::Lights,
+    post_process::PostProcess,
+    render_queue::{RenderQueue, RenderStats},
+    render_target::{AttachmentStorage, RenderTarget, RenderTargetBuilder},
+    renderer::Renderer,
+    scene::Scene,
+    texture::TextureFormat,
+    time::Time,
+    window::{GlApi, WindowConfig},
+};
+
+/// Windowless OpenGL context rendering into an offscreen framebuffer.
+///
+/// The context is created on the first EGL device that accepts it, which makes
+/// software rasterizers like Mesa llvmpipe usable on machines without a GPU or display.
+/// GL version, API and debug flag are taken from the `WindowConfig`, `size`, `samples` and
+/// `srgb` describe the framebuffer and the window related fields are ignored.
+///
+/// The context is current on the thread which created it and must not be used from others.
+pub struct HeadlessContext {
+    renderer: Renderer,
+    scene: Scene,
+    render_queue: RenderQueue,
+    lights: Lights,
+    post_process: PostProcess,
+    target: RenderTarget,
+    _context: PossiblyCurrentContext,
+}
+
+impl HeadlessContext {
+    pub fn new(config: &WindowConfig, assets: &mut Assets) -> Result<HeadlessContext, WhirlwingError> {
+        let [width, height] = config.size;
+        let context = Self::create_context(config)?;
+
+        // Deleted before the context, fields are dropped in declaration order.
+        let format = if config.srgb {
+            TextureFormat::Srgb8Alpha8
+        } else {
+            TextureFormat::Rgba8
+        };
+        let target = RenderTargetBuilder::new(width, height)
+            .with_color(format)
+            .with_depth(
+                TextureFormat::Depth24Stencil8,
+                AttachmentStorage::Renderbuffer,
+            )
+            .with_samples(config.samples as u32)
+            .build()?;
+        let renderer = Renderer::new(width, height, config.srgb, assets)?;
+        // The scene is drawn into the target of the stack, which has to be multisampled.
+        let mut post_process = PostProcess::hdr();
+        post_process.set_samples(config.samples as u32);
+
+        Ok(HeadlessContext {
+            renderer,
+            scene: Scene::new(),
+            render_queue: RenderQueue::new(),
+            lights: Lights::new(),
+            post_process,
+            target,
+            _context: context,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.target.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.target.height()
+    }
+
+    /// Offscreen framebuffer which `redraw` renders into.
+    pub fn render_target(&self) -> &RenderTarget {
+        &self.target
+    }
+
+    /// Drawn by every `redraw`, from its active camera if it has one.
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    pub fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+
+    /// Items submitted here are drawn by the next `redraw`.
+    pub fn render_queue_mut(&mut self) -> &mut RenderQueue {
+        &mut self.render_queue
+    }
+
+    /// Lights submitted here light the next `redraw`.
+    pub fn lights_mut(&mut self) -> &mut Lights {
+        &mut self.lights
+    }
+
+    /// Passes applied by `redraw` before the offscreen framebuffer.
+    pub fn post_process_mut(&mut self) -> &mut PostProcess {
+        &mut self.post_process
+    }
+
+    /// Counts of the last `redraw`.
+    pub fn render_stats(&self) -> RenderStats {
+        self.render_queue.stats()
+    }
+
+    /// `camera` places the view unless the scene has an active camera.
+    pub fn redraw(&mut self, camera: &Camera, time: &Time) {
+        let (target, scene) = (Some(&self.target), &self.scene);
+        let (queue, lights) = (&mut self.render_queue, &mut self.lights);
+        let post_process = &mut self.post_process;
+        self.renderer
+            .redraw(target, scene, camera, time, queue, lights, post_process);
+    }
+
+    /// Reads back the offscreen framebuffer, with the first row being the top of the image.
+    pub fn read_pixels(&self) -> RgbaImage {
+        self.target.resolve();
+        self.target.read_pixels(0).unwrap()
+    }
+
+    fn create_context(config: &WindowConfig) -> Result<PossiblyCurrentContext, WhirlwingError> {
+        let devices = Device::query_devices().map_err(|error| {
+            WhirlwingError::new_with_source(
+                "Failed to query EGL devices".to_string(),
+                WhirlwingErrorKind::ContextCreationFailure,
+                Box::new(error),
+            )
+        })?;
+
+        let api = match config.gl_api {
+            GlApi::OpenGl => Api::OPENGL,
+            GlApi::Gles => Api::GLES3,
+        };
+        let context_attributes = config.context_attributes().build(None);
+
+        let mut last_error = None;
+        for device in devices {
+            let attempt = unsafe {
+                egl::display::Display::with_device(&device, None).and_then(|display| {
+                    let template = ConfigTemplateBuilder::new()
+                        .with_alpha_size(8)
+                        .with_api(api)
+                        .with_surface_type(ConfigSurfaceTypes::empty())
+                        .build();
+                    let gl_config = display.find_configs(template)?.next().ok_or_else(|| {
+                        glutin::error::Error::from(glutin::error::ErrorKind::BadConfig)
+                    })?;
+                    let context = display
+                        .create_context(&gl_config, &context_attributes)?
+                        .make_current_surfaceless()?;
+                    Ok((display, context))
+                })
+            };
+
+            match attempt {
+                Ok((display, context)) => {
+                    wwg_log::wwg_info!(
+                        "Created headless context on EGL device: {}",
+                        device.name().unwrap_or("unknown")
+                    );
+                    crate::renderer::load_gl(&glutin::display::Display::Egl(display));
+                    return Ok(context);
+                }
+                Err(error) => last_error = Some(error),
+            }
+        }
+
+        let content = "Failed to create headless OpenGL context".to_string();
+        Err(match last_error {
+            Some(error) => WhirlwingError::new_with_source(
+                content,
+                WhirlwingErrorKind::ContextCreationFailure,
+                Box::new(error),
+            ),
+            None => WhirlwingError::new(
+                format!("{content}: no EGL devices found"),
+                WhirlwingErrorKind::ContextCreationFailure,
+            ),
+        })
+    }
+}
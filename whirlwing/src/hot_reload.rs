@@ -0,0 +1,80 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+
+/// Reports files which changed on disk since the last poll.
+///
+/// Parent directories are watched instead of the files themselves, because many editors
+/// save by writing a new file and renaming it over the old one.
+pub(crate) struct FileWatcher {
+    watcher: RecommendedWatcher,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+    watched_directories: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+    pub(crate) fn new() -> Result<FileWatcher, WhirlwingError> {
+        let (sender, events) = mpsc::channel();
+        let watcher = notify::recommended_watcher(sender).map_err(|error| {
+            WhirlwingError::new_with_source(
+                "Failed to create file watcher".to_string(),
+                WhirlwingErrorKind::HotReloadFailure,
+                Box::new(error),
+            )
+        })?;
+        Ok(FileWatcher {
+            watcher,
+            events,
+            watched_directories: HashSet::new(),
+        })
+    }
+
+    pub(crate) fn watch(&mut self, file: &Path) -> Result<(), WhirlwingError> {
+        let directory = canonicalize(file)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        if self.watched_directories.contains(&directory) {
+            return Ok(());
+        }
+
+        self.watcher
+            .watch(&directory, RecursiveMode::NonRecursive)
+            .map_err(|error| {
+                WhirlwingError::new_with_source(
+                    format!("Failed to watch directory: {}", directory.display()),
+                    WhirlwingErrorKind::HotReloadFailure,
+                    Box::new(error),
+                )
+            })?;
+        self.watched_directories.insert(directory);
+        Ok(())
+    }
+
+    /// Canonical paths of files created or modified since the last call.
+    pub(crate) fn changed_files(&mut self) -> HashSet<PathBuf> {
+        let mut changed = HashSet::new();
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    changed.extend(event.paths.iter().map(|path| canonicalize(path)));
+                }
+                Ok(_) => (),
+                Err(error) => {
+                    wwg_log::wwg_warn!("File watcher error: {error}");
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// Falls back to the path itself, e.g. when the file was removed in the meantime.
+pub(crate) fn canonicalize(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -14,4 +14,30 @@ impl Input {
     pub fn add_keyboard_input(&mut self, input: KeyboardInput) {
         self.keyboard_inputs.push(input);
     }
-}
\ No newline at end of file
+
+    /// Keyboard inputs received since the start of the current frame.
+    pub fn keyboard_inputs(&self) -> &[KeyboardInput] {
+        &self.keyboard_inputs
+    }
+
+    pub fn mouse_position(&self) -> (i32, i32) {
+        (self.mouse_position.x, self.mouse_position.y)
+    }
+
+    /// Mouse movement since the start of the current frame.
+    pub fn mouse_delta(&self) -> (i32, i32) {
+        (self.mouse_delta.x, self.mouse_delta.y)
+    }
+
+    pub(crate) fn set_mouse_position(&mut self, x: i32, y: i32) {
+        self.mouse_delta.x += x - self.mouse_position.x;
+        self.mouse_delta.y += y - self.mouse_position.y;
+        self.mouse_position = Vec2i { x, y };
+    }
+
+    // Called by the event loop once a frame has been presented.
+    pub(crate) fn end_frame(&mut self) {
+        self.keyboard_inputs.clear();
+        self.mouse_delta = Vec2i::default();
+    }
+}
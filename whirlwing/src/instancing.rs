@@ -0,0 +1,133 @@
+use std::{ffi::c_void, marker::PhantomData, mem::size_of_val, ptr::null};
+
+use wwg_math::{Mat4, Vec4};
+
+use crate::mesh::{AttributeType, VertexLayout};
+
+/// First attribute location of `InstanceData`, after the position, normal and texture
+/// coordinates of meshes.
+pub const INSTANCE_LOCATION: u32 = 3;
+
+/// Per-instance attributes the render queue draws instanced materials with.
+///
+/// Shaders opt in by declaring `instance_model`, which the render queue checks for.
+/// They can include `shaders/common/instancing.glsl`:
+///
+/// ```glsl
+/// layout (location = 3) in mat4 instance_model;
+/// layout (location = 7) in vec4 instance_tint;
+/// layout (location = 8) in vec4 instance_data;
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstanceData {
+    pub model: Mat4,
+    /// Multiplied with the colour by the shader, white by default.
+    pub tint: Vec4,
+    /// Free for the shader to use.
+    pub data: Vec4,
+}
+
+impl InstanceData {
+    pub fn new(model: Mat4) -> Self {
+        InstanceData {
+            model,
+            tint: Vec4::one(),
+            data: Vec4::zero(),
+        }
+    }
+
+    /// `model` at locations 3 to 6, `tint` at 7 and `data` at 8.
+    pub fn layout() -> VertexLayout {
+        VertexLayout::new()
+            .push_mat4(INSTANCE_LOCATION)
+            .push(INSTANCE_LOCATION + 4, 4, AttributeType::Float, false)
+            .push(INSTANCE_LOCATION + 5, 4, AttributeType::Float, false)
+    }
+}
+
+impl Default for InstanceData {
+    fn default() -> Self {
+        InstanceData::new(Mat4::identity())
+    }
+}
+
+/// Vertex buffer with one element per instance, drawn with `Mesh::draw_instanced`.
+///
+/// `layout` describes one instance, its stride must be the size of `I`. Its locations
+/// must not overlap the attributes of the meshes it is drawn with.
+/// Must be created and dropped while the OpenGL context is current.
+#[derive(Debug)]
+pub struct InstanceBuffer<I> {
+    buffer: u32,
+    layout: VertexLayout,
+    len: usize,
+    capacity: usize,
+    _instance: PhantomData<I>,
+}
+
+impl<I: Copy> InstanceBuffer<I> {
+    pub fn new(layout: VertexLayout) -> Self {
+        let mut buffer = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer);
+        }
+        InstanceBuffer {
+            buffer,
+            layout,
+            len: 0,
+            capacity: 0,
+            _instance: PhantomData,
+        }
+    }
+
+    /// Replaces the instances, the buffer only grows.
+    pub fn upload(&mut self, instances: &[I]) {
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.buffer);
+            if instances.len() > self.capacity {
+                self.capacity = instances.len().next_power_of_two();
+                let size = self.capacity * std::mem::size_of::<I>();
+                gl::BufferData(gl::ARRAY_BUFFER, size as isize, null(), gl::DYNAMIC_DRAW);
+            }
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                size_of_val(instances) as isize,
+                instances.as_ptr() as *const c_void,
+            );
+        }
+        self.len = instances.len();
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    /// Points the instance attributes of the bound vertex array at the buffer,
+    /// starting at instance `first`.
+    pub(crate) unsafe fn attach(&self, first: usize) {
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.buffer);
+        self.layout.apply_at(first * self.layout.stride(), 1);
+    }
+
+    pub(crate) unsafe fn detach(&self) {
+        self.layout.disable();
+    }
+}
+
+impl<I> Drop for InstanceBuffer<I> {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.buffer);
+        }
+    }
+}
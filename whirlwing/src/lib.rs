@@ -1,11 +1,38 @@
 #[cfg(feature = "log")]
 pub use wwg_log as log;
 pub use wwg_math as math;
+pub use wwg_error as error;
+pub use winit::event;
 
 pub mod app;
+pub mod assets;
 pub mod camera;
+pub mod compute;
+pub mod context;
+pub mod ecs;
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub mod headless;
+pub mod input;
+pub mod instancing;
+pub mod light;
+pub mod material;
+pub mod mesh;
+pub mod post_process;
+pub mod preprocessor;
+pub mod program_cache;
+pub mod render_queue;
+pub mod render_target;
+pub mod scene;
+pub mod schedule;
+pub mod shader;
+pub mod shader_error;
+pub mod texture;
+pub mod time;
+pub mod uniform;
+pub mod uniform_buffer;
+pub mod window;
 
+#[cfg(feature = "hot_reload")]
+pub(crate) mod hot_reload;
 pub(crate) mod renderer;
-pub(crate) mod shader;
-pub(crate) mod time;
-pub(crate) mod input;
\ No newline at end of file
+pub(crate) mod worker_pool;
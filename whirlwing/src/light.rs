@@ -0,0 +1,195 @@
+use wwg_math::{Isometry3, Vec2, Vec3};
+
+use crate::{ecs::World, uniform_buffer::Padding};
+
+/// Light source, placed and aimed by the scene node it is attached to.
+///
+/// Lights shine along the node's local -Z axis, like the camera looks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    /// Linear RGB.
+    pub color: Vec3,
+    pub intensity: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    /// Infinitely far away, only the direction matters.
+    Directional,
+    /// Shines in every direction, falling off with the squared distance and fading out
+    /// at `range`.
+    Point { range: f32 },
+    /// Cone of light, full intensity inside `inner_angle` and fading out until
+    /// `outer_angle`. Angles are in radians, measured from the axis.
+    Spot {
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+impl Light {
+    pub fn new(kind: LightKind, color: Vec3, intensity: f32) -> Self {
+        Light {
+            kind,
+            color,
+            intensity,
+        }
+    }
+}
+
+/// Lights the `Lights` uniform block holds. Lit shaders use all of them unless their
+/// `MAX_LIGHTS` define lowers the count evaluated per draw.
+pub const MAX_LIGHTS: usize = 16;
+
+/// Lights of the current frame, uploaded to the `Lights` uniform block before drawing.
+///
+/// Like the `RenderQueue` it is emptied every frame. `App` gathers entities with a
+/// `Light` and an `Isometry3` component into it, the isometry placing the light like a
+/// scene node. If there are more than `MAX_LIGHTS`, directional lights are kept first,
+/// then the ones closest to the camera.
+#[derive(Debug)]
+pub struct Lights {
+    ambient: Vec3,
+    lights: Vec<(Light, Vec3, Vec3)>,
+}
+
+impl Default for Lights {
+    fn default() -> Self {
+        Lights {
+            ambient: Vec3::broadcast(0.03),
+            lights: Vec::new(),
+        }
+    }
+}
+
+impl Lights {
+    pub fn new() -> Self {
+        Lights::default()
+    }
+
+    /// Adds a light at `position` shining along `direction` for this frame.
+    pub fn submit(&mut self, light: Light, position: Vec3, direction: Vec3) {
+        self.lights.push((light, position, direction.normalized()));
+    }
+
+    /// Submits every entity with a `Light` and an `Isometry3` component.
+    pub fn gather(&mut self, world: &World) {
+        for (_, (light, isometry)) in world.query::<(&Light, &Isometry3)>().iter() {
+            let mut direction = Vec3::new(0.0, 0.0, -1.0);
+            isometry.rotation.rotate_vec(&mut direction);
+            self.submit(*light, isometry.translation, direction);
+        }
+    }
+
+    /// Linear colour added to every lit surface regardless of the lights.
+    pub fn set_ambient(&mut self, ambient: Vec3) {
+        self.ambient = ambient;
+    }
+
+    pub fn ambient(&self) -> Vec3 {
+        self.ambient
+    }
+
+    /// Submitted lights with their position and direction.
+    pub fn iter(&self) -> impl Iterator<Item = &(Light, Vec3, Vec3)> {
+        self.lights.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.lights.clear();
+    }
+
+    /// Block contents for a camera at `camera_position`, which the renderer uploads.
+    pub fn uniforms(&self, camera_position: Vec3) -> LightsUniforms {
+        let mut lights: Vec<_> = self.lights.iter().collect();
+        if lights.len() > MAX_LIGHTS {
+            lights.sort_by(|(a, a_position, _), (b, b_position, _)| {
+                let key = |light: &Light, position: &Vec3| match light.kind {
+                    LightKind::Directional => -1.0,
+                    _ => (*position - camera_position).mag_sq(),
+                };
+                key(a, a_position).total_cmp(&key(b, b_position))
+            });
+            lights.truncate(MAX_LIGHTS);
+        }
+
+        let mut uniforms = LightsUniforms {
+            ambient: self.ambient,
+            count: lights.len() as u32,
+            lights: [LightUniform::default(); MAX_LIGHTS],
+        };
+        for (uniform, (light, position, direction)) in uniforms.lights.iter_mut().zip(lights) {
+            *uniform = LightUniform::new(light, *position, *direction);
+        }
+        uniforms
+    }
+}
+
+crate::std140! {
+    /// One element of the `lights` array in the `Lights` uniform block, see
+    /// `shaders/common/lights.glsl`. `kind` is 0 for directional, 1 for point and 2 for
+    /// spot lights, `cone` holds the cosines of the inner and outer angle.
+    #[derive(Debug, Default)]
+    pub struct LightUniform {
+        pub position: Vec3,
+        pub range: f32,
+        pub direction: Vec3,
+        pub kind: u32,
+        pub color: Vec3,
+        pub intensity: f32,
+        pub cone: Vec2,
+        _padding: Padding<8>,
+    }
+}
+
+impl LightUniform {
+    pub fn new(light: &Light, position: Vec3, direction: Vec3) -> Self {
+        let (kind, range, cone) = match light.kind {
+            LightKind::Directional => (0, 0.0, Vec2::zero()),
+            LightKind::Point { range } => (1, range, Vec2::zero()),
+            LightKind::Spot {
+                range,
+                inner_angle,
+                outer_angle,
+            } => (2, range, Vec2::new(inner_angle.cos(), outer_angle.cos())),
+        };
+        LightUniform {
+            position,
+            range,
+            direction,
+            kind,
+            color: light.color,
+            intensity: light.intensity,
+            cone,
+            _padding: Padding::default(),
+        }
+    }
+}
+
+crate::std140! {
+    /// Contents of the `Lights` uniform block, uploaded once per frame by the renderer.
+    /// Shaders can include `shaders/common/lights.glsl` to declare it together with the
+    /// Blinn-Phong `shade` function.
+    #[derive(Debug)]
+    pub struct LightsUniforms {
+        pub ambient: Vec3,
+        pub count: u32,
+        pub lights: [LightUniform; MAX_LIGHTS],
+    }
+}
+
+impl Default for LightsUniforms {
+    fn default() -> Self {
+        Lights::new().uniforms(Vec3::zero())
+    }
+}
@@ -0,0 +1,379 @@
+use std::{collections::BTreeMap, path::Path, path::PathBuf};
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::Deserialize;
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+use wwg_math::{Vec2, Vec3, Vec4};
+
+use crate::{
+    assets::{Assets, Handle},
+    light::MAX_LIGHTS,
+    render_queue::RenderState,
+    shader::Shader,
+    texture::{Filter, SamplerDesc, Texture2D, TextureDesc, Wrap},
+};
+
+/// Vertex shader of `Material::lit`.
+pub const LIT_VERTEX_SHADER: &str = "shaders/lit/vertex.glsl";
+/// Fragment shader of `Material::lit`, Blinn-Phong shading by the `Lights` block.
+pub const LIT_FRAGMENT_SHADER: &str = "shaders/lit/fragment.glsl";
+
+/// Value of a named material parameter, written to the uniform of the same name.
+#[derive(Debug, Clone)]
+pub enum MaterialParam {
+    Int(i32),
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    /// Bound to the next free texture unit, the sampler uniform is set to that unit.
+    Texture(Handle<Texture2D>),
+}
+
+macro_rules! material_param_from {
+    ($($type:ty => $variant:ident,)+) => {$(
+        impl From<$type> for MaterialParam {
+            fn from(value: $type) -> Self {
+                MaterialParam::$variant(value)
+            }
+        }
+    )+};
+}
+
+material_param_from! {
+    i32 => Int,
+    f32 => Float,
+    Vec2 => Vec2,
+    Vec3 => Vec3,
+    Vec4 => Vec4,
+    Handle<Texture2D> => Texture,
+}
+
+/// Shader together with the uniform values and textures to draw with it.
+///
+/// Textures get consecutive texture units starting at 0, in parameter order.
+/// Parameters the shader does not use are skipped, so one material file can serve
+/// several shader variants.
+///
+/// Material files are TOML, paths in them are relative to the asset root:
+///
+/// ```toml
+/// vertex_shader = "shaders/vertex_shader.glsl"
+/// fragment_shader = "shaders/fragment_shader.glsl"
+/// defines = { MAX_LIGHTS = 4 }
+/// transparent = false
+///
+/// [params]
+/// albedo = { texture = "textures/wall.jpg", srgb = true, wrap = "mirrored_repeat" }
+/// tint = "#ff8000"
+/// roughness = 0.5
+/// offset = [0.0, 0.25]
+/// ```
+///
+/// Numbers become `Float`, or `Int` if written without a fraction and the uniform is
+/// not a `float`. Arrays of 2 to 4 numbers become vectors and `"#rrggbb"` or
+/// `"#rrggbbaa"` colours become a `Vec4`, or a `Vec3` if the uniform is a `vec3`.
+/// Colours are written in sRGB and converted to linear values, alpha is kept as is.
+#[derive(Debug, Clone)]
+pub struct Material {
+    shader: Handle<Shader>,
+    params: Vec<(String, MaterialParam)>,
+    transparent: bool,
+}
+
+impl Material {
+    pub fn new(shader: Handle<Shader>) -> Self {
+        Material {
+            shader,
+            params: Vec::new(),
+            transparent: false,
+        }
+    }
+
+    /// Blinn-Phong material lit by the `Lights` of the frame, for meshes with normals at
+    /// location 1 like those of `Mesh::from_obj`.
+    ///
+    /// `diffuse_map` and `specular_map` are multiplied with `diffuse_color` and
+    /// `specular_color`, `normal_map` holds tangent space normals. The maps default to
+    /// 1x1 textures without effect, a diffuse map should be loaded as sRGB. Every draw
+    /// evaluates at most `max_lights` lights, which is capped at `MAX_LIGHTS`.
+    ///
+    /// The shader is the instanced variant, the render queue draws items sharing mesh and
+    /// material with one call and multiplies the diffuse colour with their tint.
+    pub fn lit(assets: &mut Assets, max_lights: usize) -> Result<Material, WhirlwingError> {
+        let max_lights = max_lights.min(MAX_LIGHTS).to_string();
+        let defines = [("MAX_LIGHTS", max_lights.as_str()), ("INSTANCED", "")];
+        let shader =
+            assets.load_shader_with_defines(LIT_VERTEX_SHADER, LIT_FRAGMENT_SHADER, &defines)?;
+
+        let texture = |pixel: [u8; 4], srgb: bool| {
+            let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba(pixel)));
+            let desc = TextureDesc {
+                srgb,
+                ..TextureDesc::default()
+            };
+            Handle::new(Texture2D::from_image(&image, &desc))
+        };
+        let mut material = Material::new(shader);
+        material.set("diffuse_map", texture([255; 4], true));
+        material.set("specular_map", texture([255; 4], false));
+        material.set("normal_map", texture([128, 128, 255, 255], false));
+        material.set("diffuse_color", Vec4::one());
+        material.set("specular_color", Vec3::broadcast(0.5));
+        material.set("shininess", 32.0);
+        Ok(material)
+    }
+
+    /// Loads the shader and textures through `assets`, textures are loaded asynchronously.
+    pub fn from_toml_str(toml: &str, assets: &mut Assets) -> Result<Material, WhirlwingError> {
+        let file: MaterialFile = toml::from_str(toml).map_err(|error| {
+            WhirlwingError::new_with_source(
+                "Failed to parse material".to_string(),
+                WhirlwingErrorKind::MaterialLoadFailure,
+                Box::new(error),
+            )
+        })?;
+
+        let defines: Vec<(String, String)> = file
+            .defines
+            .into_iter()
+            .map(|(name, value)| match value {
+                toml::Value::String(value) => (name, value),
+                value => (name, value.to_string()),
+            })
+            .collect();
+        let defines: Vec<(&str, &str)> = defines
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        let shader = assets.load_shader_with_defines(
+            &file.vertex_shader,
+            &file.fragment_shader,
+            &defines,
+        )?;
+
+        let mut material = Material::new(shader);
+        material.transparent = file.transparent;
+        for (name, param) in file.params {
+            let gl_type = material
+                .shader
+                .get()
+                .and_then(|shader| shader.uniform(&name))
+                .map(|uniform| uniform.gl_type);
+            let param = param
+                .into_param(gl_type, assets)
+                .map_err(|error| material_error(format!("Parameter `{name}`: {error}")))?;
+            material.set(&name, param);
+        }
+        Ok(material)
+    }
+
+    /// See `from_toml_str`.
+    pub fn from_toml_file<P: AsRef<Path>>(
+        path: P,
+        assets: &mut Assets,
+    ) -> Result<Material, WhirlwingError> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path).map_err(|error| {
+            WhirlwingError::new_with_source(
+                format!("Failed to read from path: {}", path.display()),
+                WhirlwingErrorKind::MaterialLoadFailure,
+                Box::new(error),
+            )
+        })?;
+        Self::from_toml_str(&toml, assets)
+    }
+
+    /// Replaces the value of an existing parameter, new parameters are appended.
+    pub fn set<P: Into<MaterialParam>>(&mut self, name: &str, value: P) {
+        let value = value.into();
+        match self.params.iter_mut().find(|(other, _)| other == name) {
+            Some((_, param)) => *param = value,
+            None => self.params.push((name.to_string(), value)),
+        }
+    }
+
+    pub fn param(&self, name: &str) -> Option<&MaterialParam> {
+        self.params
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, param)| param)
+    }
+
+    pub fn params(&self) -> &[(String, MaterialParam)] {
+        &self.params
+    }
+
+    pub fn shader(&self) -> &Handle<Shader> {
+        &self.shader
+    }
+
+    /// Transparent materials are drawn after opaque ones, sorted back to front and blended
+    /// by alpha.
+    pub fn set_transparent(&mut self, transparent: bool) {
+        self.transparent = transparent;
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.transparent
+    }
+
+    /// Binds the shader and textures and writes every parameter.
+    ///
+    /// Textures which are still loading leave their unit empty.
+    /// Does nothing while the shader is not loaded.
+    pub fn bind(&self) -> Result<(), WhirlwingError> {
+        self.bind_with(&mut RenderState::default())
+    }
+
+    /// Like `bind`, skipping the program and textures `state` has bound already.
+    pub(crate) fn bind_with(&self, state: &mut RenderState) -> Result<(), WhirlwingError> {
+        let Some(shader) = self.shader.get() else {
+            return Ok(());
+        };
+        state.use_program(shader.id());
+
+        let mut unit = 0;
+        for (name, param) in &self.params {
+            if !shader.has_uniform(name) {
+                continue;
+            }
+            match param {
+                MaterialParam::Int(value) => shader.set(name, value)?,
+                MaterialParam::Float(value) => shader.set(name, value)?,
+                MaterialParam::Vec2(value) => shader.set(name, value)?,
+                MaterialParam::Vec3(value) => shader.set(name, value)?,
+                MaterialParam::Vec4(value) => shader.set(name, value)?,
+                MaterialParam::Texture(texture) => {
+                    let texture = texture.get().map_or(0, |texture| texture.id());
+                    state.bind_texture(unit, texture);
+                    shader.set(name, &(unit as i32))?;
+                    unit += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialFile {
+    vertex_shader: PathBuf,
+    fragment_shader: PathBuf,
+    #[serde(default)]
+    defines: BTreeMap<String, toml::Value>,
+    #[serde(default)]
+    transparent: bool,
+    // Sorted by name, which fixes the texture units.
+    #[serde(default)]
+    params: BTreeMap<String, ParamFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ParamFile {
+    Int(i32),
+    Float(f32),
+    Vector(Vec<f32>),
+    Color(String),
+    Texture(TextureFile),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextureFile {
+    texture: PathBuf,
+    #[serde(default)]
+    srgb: bool,
+    #[serde(default)]
+    flip_vertically: bool,
+    wrap: Option<Wrap>,
+    filter: Option<Filter>,
+    mipmaps: Option<bool>,
+    border_color: Option<[f32; 4]>,
+}
+
+impl ParamFile {
+    /// `gl_type` is the type of the uniform, `None` if the shader does not use it.
+    fn into_param(
+        self,
+        gl_type: Option<u32>,
+        assets: &mut Assets,
+    ) -> Result<MaterialParam, String> {
+        Ok(match self {
+            ParamFile::Int(value) if gl_type == Some(gl::FLOAT) => {
+                MaterialParam::Float(value as f32)
+            }
+            ParamFile::Int(value) => MaterialParam::Int(value),
+            ParamFile::Float(value) => MaterialParam::Float(value),
+            ParamFile::Vector(values) => match values[..] {
+                [x, y] => MaterialParam::Vec2(Vec2::new(x, y)),
+                [x, y, z] => MaterialParam::Vec3(Vec3::new(x, y, z)),
+                [x, y, z, w] => MaterialParam::Vec4(Vec4::new(x, y, z, w)),
+                _ => {
+                    return Err(format!(
+                        "Vectors have 2 to 4 components, not {}",
+                        values.len()
+                    ))
+                }
+            },
+            ParamFile::Color(color) => {
+                let color = parse_color(&color).ok_or(format!("Invalid colour: {color}"))?;
+                if gl_type == Some(gl::FLOAT_VEC3) {
+                    MaterialParam::Vec3(color.xyz())
+                } else {
+                    MaterialParam::Vec4(color)
+                }
+            }
+            ParamFile::Texture(file) => {
+                let defaults = SamplerDesc::default();
+                let desc = TextureDesc {
+                    sampler: SamplerDesc {
+                        wrap_s: file.wrap.unwrap_or(defaults.wrap_s),
+                        wrap_t: file.wrap.unwrap_or(defaults.wrap_t),
+                        min_filter: file.filter.unwrap_or(defaults.min_filter),
+                        mag_filter: file.filter.unwrap_or(defaults.mag_filter),
+                        mipmaps: file.mipmaps.unwrap_or(defaults.mipmaps),
+                        border_color: file.border_color.unwrap_or(defaults.border_color),
+                        anisotropy: defaults.anisotropy,
+                    },
+                    srgb: file.srgb,
+                    flip_vertically: file.flip_vertically,
+                };
+                MaterialParam::Texture(assets.load_texture_async(&file.texture, &desc))
+            }
+        })
+    }
+}
+
+/// `#rrggbb` or `#rrggbbaa`, with the colour channels decoded from sRGB.
+fn parse_color(color: &str) -> Option<Vec4> {
+    let hex = color.strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+    let mut channels = [1.0; 4];
+    for (index, digits) in hex.as_bytes().chunks(2).enumerate() {
+        let digits = std::str::from_utf8(digits).ok()?;
+        let value = u8::from_str_radix(digits, 16).ok()? as f32 / 255.0;
+        channels[index] = if index < 3 {
+            srgb_to_linear(value)
+        } else {
+            value
+        };
+    }
+    Some(Vec4::from(channels))
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn material_error(content: String) -> WhirlwingError {
+    WhirlwingError::new(content, WhirlwingErrorKind::MaterialLoadFailure)
+}
@@ -0,0 +1,404 @@
+use std::ffi::c_void;
+use std::mem::size_of_val;
+use std::path::Path;
+use std::ptr::null;
+
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+
+use crate::instancing::InstanceBuffer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeType {
+    Float,
+    HalfFloat,
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+}
+
+impl AttributeType {
+    pub fn size(self) -> usize {
+        match self {
+            AttributeType::Byte | AttributeType::UnsignedByte => 1,
+            AttributeType::HalfFloat | AttributeType::Short | AttributeType::UnsignedShort => 2,
+            AttributeType::Float | AttributeType::Int | AttributeType::UnsignedInt => 4,
+        }
+    }
+
+    fn is_integer(self) -> bool {
+        !matches!(self, AttributeType::Float | AttributeType::HalfFloat)
+    }
+
+    fn gl_type(self) -> u32 {
+        match self {
+            AttributeType::Float => gl::FLOAT,
+            AttributeType::HalfFloat => gl::HALF_FLOAT,
+            AttributeType::Byte => gl::BYTE,
+            AttributeType::UnsignedByte => gl::UNSIGNED_BYTE,
+            AttributeType::Short => gl::SHORT,
+            AttributeType::UnsignedShort => gl::UNSIGNED_SHORT,
+            AttributeType::Int => gl::INT,
+            AttributeType::UnsignedInt => gl::UNSIGNED_INT,
+        }
+    }
+}
+
+/// Single vertex attribute, `offset` is in bytes from the start of the vertex.
+///
+/// Integer attributes which are not `normalized` reach the shader as integers
+/// (`int`, `ivec*`, `uint`, `uvec*`), everything else is converted to floats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub components: u8,
+    pub kind: AttributeType,
+    pub normalized: bool,
+    pub offset: usize,
+}
+
+/// Describes how vertex data is laid out in a buffer.
+///
+/// # Examples
+///
+/// ```
+/// use whirlwing::mesh::{AttributeType, VertexLayout};
+/// // vec3 position at location 0, vec2 uv at location 2
+/// let layout = VertexLayout::new()
+///     .push(0, 3, AttributeType::Float, false)
+///     .push(2, 2, AttributeType::Float, false);
+/// assert_eq!(layout.stride(), 20);
+/// assert_eq!(layout.attributes()[1].offset, 12);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+    stride: usize,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an attribute right after the end of the previous one and grows the stride to fit it.
+    pub fn push(self, location: u32, components: u8, kind: AttributeType, normalized: bool) -> Self {
+        let offset = self
+            .attributes
+            .last()
+            .map(|last| last.offset + last.components as usize * last.kind.size())
+            .unwrap_or(0);
+        self.with_attribute(VertexAttribute {
+            location,
+            components,
+            kind,
+            normalized,
+            offset,
+        })
+    }
+
+    /// Adds an attribute at an explicit offset, the stride grows if the attribute does not fit.
+    pub fn with_attribute(mut self, attribute: VertexAttribute) -> Self {
+        let end = attribute.offset + attribute.components as usize * attribute.kind.size();
+        self.stride = self.stride.max(end);
+        self.attributes.push(attribute);
+        self
+    }
+
+    /// Appends a `mat4` as four `vec4` columns at `location` to `location + 3`.
+    pub fn push_mat4(self, location: u32) -> Self {
+        (0..4).fold(self, |layout, column| {
+            layout.push(location + column, 4, AttributeType::Float, false)
+        })
+    }
+
+    /// Overrides the stride, e.g. for vertices with padding or interleaved data not described here.
+    pub fn with_stride(mut self, stride: usize) -> Self {
+        self.stride = stride;
+        self
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Sets up attribute pointers for the currently bound vertex array and array buffer.
+    pub(crate) unsafe fn apply(&self) {
+        self.apply_at(0, 0);
+    }
+
+    /// Like `apply`, with every offset moved by `base` bytes and the attributes advancing
+    /// once per `divisor` instances, or once per vertex if it is 0.
+    pub(crate) unsafe fn apply_at(&self, base: usize, divisor: u32) {
+        for attribute in &self.attributes {
+            let offset = (base + attribute.offset) as *const c_void;
+            if attribute.kind.is_integer() && !attribute.normalized {
+                gl::VertexAttribIPointer(
+                    attribute.location,
+                    attribute.components as i32,
+                    attribute.kind.gl_type(),
+                    self.stride as i32,
+                    offset,
+                );
+            } else {
+                gl::VertexAttribPointer(
+                    attribute.location,
+                    attribute.components as i32,
+                    attribute.kind.gl_type(),
+                    attribute.normalized as u8,
+                    self.stride as i32,
+                    offset,
+                );
+            }
+            gl::VertexAttribDivisor(attribute.location, divisor);
+            gl::EnableVertexAttribArray(attribute.location);
+        }
+    }
+
+    /// Turns the attributes off again, so later draws of the vertex array do not read them.
+    pub(crate) unsafe fn disable(&self) {
+        for attribute in &self.attributes {
+            gl::DisableVertexAttribArray(attribute.location);
+            gl::VertexAttribDivisor(attribute.location, 0);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Indices<'a> {
+    None,
+    U16(&'a [u16]),
+    U32(&'a [u32]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primitive {
+    Points,
+    Lines,
+    LineStrip,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl Primitive {
+    fn gl_mode(self) -> u32 {
+        match self {
+            Primitive::Points => gl::POINTS,
+            Primitive::Lines => gl::LINES,
+            Primitive::LineStrip => gl::LINE_STRIP,
+            Primitive::Triangles => gl::TRIANGLES,
+            Primitive::TriangleStrip => gl::TRIANGLE_STRIP,
+            Primitive::TriangleFan => gl::TRIANGLE_FAN,
+        }
+    }
+}
+
+/// CPU side of an OBJ mesh, can be loaded without an OpenGL context.
+pub(crate) struct MeshData {
+    vertices: Vec<f32>,
+    indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub(crate) fn from_obj(path: &Path) -> Result<MeshData, WhirlwingError> {
+        let (models, _) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS).map_err(|error| {
+            WhirlwingError::new_with_source(
+                format!("Failed to load mesh from path: {}", path.display()),
+                WhirlwingErrorKind::MeshLoadFailure,
+                Box::new(error),
+            )
+        })?;
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for model in &models {
+            let mesh = &model.mesh;
+            let first_index = (vertices.len() / 8) as u32;
+            for i in 0..mesh.positions.len() / 3 {
+                vertices.extend_from_slice(&mesh.positions[i * 3..i * 3 + 3]);
+                match mesh.normals.get(i * 3..i * 3 + 3) {
+                    Some(normal) => vertices.extend_from_slice(normal),
+                    None => vertices.extend_from_slice(&[0.0; 3]),
+                }
+                match mesh.texcoords.get(i * 2..i * 2 + 2) {
+                    Some(texcoord) => vertices.extend_from_slice(texcoord),
+                    None => vertices.extend_from_slice(&[0.0; 2]),
+                }
+            }
+            indices.extend(mesh.indices.iter().map(|index| index + first_index));
+        }
+        Ok(MeshData { vertices, indices })
+    }
+}
+
+/// Vertex data uploaded to the GPU, drawn with a single draw call.
+///
+/// Must be created and dropped while the OpenGL context is current.
+#[derive(Debug)]
+pub struct Mesh {
+    vertex_array: u32,
+    vertex_buffer: u32,
+    index_buffer: Option<u32>,
+    index_type: u32,
+    count: i32,
+    primitive: Primitive,
+}
+
+impl Mesh {
+    /// `vertices` can be any plain data matching the `layout`, e.g. `[f32]` or `#[repr(C)]` structs.
+    pub fn new<V: Copy>(vertices: &[V], layout: &VertexLayout, indices: Indices) -> Mesh {
+        unsafe {
+            let mut vertex_array = 0;
+            gl::GenVertexArrays(1, &mut vertex_array);
+            gl::BindVertexArray(vertex_array);
+
+            let mut vertex_buffer = 0;
+            gl::GenBuffers(1, &mut vertex_buffer);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                size_of_val(vertices) as isize,
+                vertices.as_ptr() as *const c_void,
+                gl::STATIC_DRAW,
+            );
+            layout.apply();
+
+            let (index_buffer, index_type, count) = match indices {
+                Indices::None => {
+                    let count = size_of_val(vertices) / layout.stride().max(1);
+                    (None, 0, count as i32)
+                }
+                Indices::U16(indices) => (
+                    Some(Self::upload_indices(indices)),
+                    gl::UNSIGNED_SHORT,
+                    indices.len() as i32,
+                ),
+                Indices::U32(indices) => (
+                    Some(Self::upload_indices(indices)),
+                    gl::UNSIGNED_INT,
+                    indices.len() as i32,
+                ),
+            };
+
+            gl::BindVertexArray(0);
+
+            Mesh {
+                vertex_array,
+                vertex_buffer,
+                index_buffer,
+                index_type,
+                count,
+                primitive: Primitive::Triangles,
+            }
+        }
+    }
+
+    /// Loads every object of a Wavefront OBJ file into a single triangle mesh.
+    ///
+    /// Vertices are laid out as position (location 0), normal (location 1) and
+    /// texture coordinates (location 2), missing normals and coordinates are zeroed.
+    pub fn from_obj<P: AsRef<Path>>(path: P) -> Result<Mesh, WhirlwingError> {
+        Ok(Self::from_data(&MeshData::from_obj(path.as_ref())?))
+    }
+
+    pub(crate) fn from_data(data: &MeshData) -> Mesh {
+        let layout = VertexLayout::new()
+            .push(0, 3, AttributeType::Float, false)
+            .push(1, 3, AttributeType::Float, false)
+            .push(2, 2, AttributeType::Float, false);
+        Mesh::new(&data.vertices, &layout, Indices::U32(&data.indices))
+    }
+
+    pub fn with_primitive(mut self, primitive: Primitive) -> Self {
+        self.primitive = primitive;
+        self
+    }
+
+    /// Number of indices, or of vertices for meshes without indices.
+    pub fn count(&self) -> i32 {
+        self.count
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        self.index_buffer.is_some()
+    }
+
+    pub fn draw(&self) {
+        unsafe {
+            gl::BindVertexArray(self.vertex_array);
+        }
+        self.draw_bound();
+    }
+
+    /// Draws the mesh once per instance in the buffer with a single draw call.
+    pub fn draw_instanced<I: Copy>(&self, instances: &InstanceBuffer<I>) {
+        unsafe {
+            gl::BindVertexArray(self.vertex_array);
+            instances.attach(0);
+        }
+        self.draw_bound_instanced(instances.len() as i32);
+        unsafe {
+            instances.detach();
+        }
+    }
+
+    pub(crate) fn vertex_array(&self) -> u32 {
+        self.vertex_array
+    }
+
+    /// Draws with the vertex array of the mesh already bound.
+    pub(crate) fn draw_bound(&self) {
+        unsafe {
+            if self.index_buffer.is_some() {
+                gl::DrawElements(self.primitive.gl_mode(), self.count, self.index_type, null());
+            } else {
+                gl::DrawArrays(self.primitive.gl_mode(), 0, self.count);
+            }
+        }
+    }
+
+    /// Like `draw_bound`, with the instance attributes already attached.
+    pub(crate) fn draw_bound_instanced(&self, instances: i32) {
+        let mode = self.primitive.gl_mode();
+        unsafe {
+            if self.index_buffer.is_some() {
+                gl::DrawElementsInstanced(mode, self.count, self.index_type, null(), instances);
+            } else {
+                gl::DrawArraysInstanced(mode, 0, self.count, instances);
+            }
+        }
+    }
+
+    // Expects the mesh vertex array to be bound, so the element buffer binding is stored in it.
+    unsafe fn upload_indices<I: Copy>(indices: &[I]) -> u32 {
+        let mut index_buffer = 0;
+        gl::GenBuffers(1, &mut index_buffer);
+        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer);
+        gl::BufferData(
+            gl::ELEMENT_ARRAY_BUFFER,
+            size_of_val(indices) as isize,
+            indices.as_ptr() as *const c_void,
+            gl::STATIC_DRAW,
+        );
+        index_buffer
+    }
+}
+
+impl Drop for Mesh {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vertex_array);
+            gl::DeleteBuffers(1, &self.vertex_buffer);
+            if let Some(index_buffer) = &self.index_buffer {
+                gl::DeleteBuffers(1, index_buffer);
+            }
+        }
+    }
+}
@@ -0,0 +1,628 @@
+use wwg_error::WhirlwingError;
+use wwg_math::Vec2;
+
+use crate::{
+    assets::{Assets, Handle},
+    material::{Material, MaterialParam},
+    render_queue::RenderState,
+    render_target::{AttachmentStorage, RenderTarget, RenderTargetBuilder},
+    shader::Shader,
+    texture::{Texture3D, TextureFormat},
+    uniform::Uniform,
+};
+
+/// Vertex shader of custom passes, a triangle covering the screen which passes `uv` on.
+pub const FULLSCREEN_VERTEX_SHADER: &str = "shaders/post/fullscreen.glsl";
+
+/// Curve mapping HDR colours into the displayable range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tonemapper {
+    Reinhard,
+    /// Filmic curve with more contrast, the default.
+    Aces,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tonemap {
+    pub curve: Tonemapper,
+    /// Colours are multiplied with it before the curve is applied.
+    pub exposure: f32,
+}
+
+impl Default for Tonemap {
+    fn default() -> Self {
+        Tonemap {
+            curve: Tonemapper::Aces,
+            exposure: 1.0,
+        }
+    }
+}
+
+/// Glow around bright areas, blurred at half resolution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bloom {
+    /// Brightness above which colours start to glow.
+    pub threshold: f32,
+    pub intensity: f32,
+    /// Blur iterations, each one widens the glow.
+    pub iterations: u32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom {
+            threshold: 1.0,
+            intensity: 0.5,
+            iterations: 4,
+        }
+    }
+}
+
+/// Darkens the corners of the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vignette {
+    /// Darkening of the corners, 0 to 1.
+    pub intensity: f32,
+    /// Part of the way from the centre to the corners over which the darkening fades in.
+    pub smoothness: f32,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Vignette {
+            intensity: 0.5,
+            smoothness: 0.6,
+        }
+    }
+}
+
+/// Replaces colours through a lookup table, see `Texture3D::lut_from_image`.
+#[derive(Debug, Clone)]
+pub struct ColorGrading {
+    pub lut: Handle<Texture3D>,
+    /// Blend between the original colour at 0 and the graded one at 1.
+    pub strength: f32,
+}
+
+/// Full screen pass of a `PostProcess` stack.
+#[derive(Debug, Clone)]
+pub enum Effect {
+    Tonemap(Tonemap),
+    /// Encodes linear colours with the given gamma, for outputs which are not sRGB.
+    Gamma(f32),
+    Bloom(Bloom),
+    /// Fast approximate anti-aliasing, best run after tonemapping.
+    Fxaa,
+    Vignette(Vignette),
+    ColorGrading(ColorGrading),
+    /// Material drawn over the screen, usually with `FULLSCREEN_VERTEX_SHADER`.
+    ///
+    /// The output of the previous pass is bound to the `source` sampler, after the
+    /// textures of the material. `texel_size` is set to the size of one pixel in
+    /// texture coordinates, if the shader declares it.
+    Custom(Material),
+}
+
+#[derive(Debug)]
+struct Pass {
+    name: String,
+    effect: Effect,
+    enabled: bool,
+}
+
+/// Passes run over the rendered scene, in order, before it is shown.
+///
+/// While a pass is enabled the scene is drawn into an offscreen target instead of the
+/// window. Every pass reads the output of the previous one and the last enabled pass
+/// writes to the window. Passes are looked up by the name they were added with.
+///
+/// ```no_run
+/// # use whirlwing::post_process::{Bloom, Effect, PostProcess, Tonemap};
+/// let mut post_process = PostProcess::new();
+/// post_process.push("bloom", Effect::Bloom(Bloom::default()));
+/// post_process.push("tonemap", Effect::Tonemap(Tonemap::default()));
+/// post_process.push("fxaa", Effect::Fxaa);
+/// post_process.set_enabled("bloom", false);
+/// ```
+#[derive(Debug)]
+pub struct PostProcess {
+    passes: Vec<Pass>,
+    scene_format: TextureFormat,
+    samples: u32,
+    // Created on the first frame with an enabled pass, the stack may exist before the
+    // context.
+    targets: Option<Targets>,
+}
+
+impl Default for PostProcess {
+    fn default() -> Self {
+        PostProcess {
+            passes: Vec::new(),
+            scene_format: TextureFormat::Rgba8,
+            samples: 0,
+            targets: None,
+        }
+    }
+}
+
+impl PostProcess {
+    pub fn new() -> Self {
+        PostProcess::default()
+    }
+
+    /// Stack drawing the scene in `Rgba16F` with a `tonemap` pass mapping it to the
+    /// displayable range, which `Context` starts with.
+    pub fn hdr() -> Self {
+        let mut post_process = PostProcess {
+            scene_format: TextureFormat::Rgba16F,
+            ..PostProcess::default()
+        };
+        post_process.push("tonemap", Effect::Tonemap(Tonemap::default()));
+        post_process
+    }
+
+    /// Adds an enabled pass at the end, replacing the pass with the same name.
+    pub fn push(&mut self, name: &str, effect: Effect) {
+        self.remove(name);
+        self.passes.push(Pass {
+            name: name.to_string(),
+            effect,
+            enabled: true,
+        });
+    }
+
+    /// Adds an enabled pass before the pass at `index`, replacing the pass with the same name.
+    ///
+    /// Panics if `index` is past the end.
+    pub fn insert(&mut self, index: usize, name: &str, effect: Effect) {
+        self.remove(name);
+        let pass = Pass {
+            name: name.to_string(),
+            effect,
+            enabled: true,
+        };
+        self.passes.insert(index, pass);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Effect> {
+        let index = self.passes.iter().position(|pass| pass.name == name)?;
+        Some(self.passes.remove(index).effect)
+    }
+
+    /// Returns `false` if there is no pass called `name`.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        self.pass_mut(name)
+            .map(|pass| pass.enabled = enabled)
+            .is_some()
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.pass(name).is_some_and(|pass| pass.enabled)
+    }
+
+    pub fn effect(&self, name: &str) -> Option<&Effect> {
+        self.pass(name).map(|pass| &pass.effect)
+    }
+
+    /// Parameters can be changed at runtime through the returned effect.
+    pub fn effect_mut(&mut self, name: &str) -> Option<&mut Effect> {
+        self.pass_mut(name).map(|pass| &mut pass.effect)
+    }
+
+    /// Names of the passes in order, with whether they are enabled.
+    pub fn passes(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.passes
+            .iter()
+            .map(|pass| (pass.name.as_str(), pass.enabled))
+    }
+
+    /// Exposure of the first enabled tonemap pass.
+    pub fn exposure(&self) -> Option<f32> {
+        self.tonemap().map(|tonemap| tonemap.exposure)
+    }
+
+    /// Returns `false` if there is no enabled tonemap pass to apply it to.
+    pub fn set_exposure(&mut self, exposure: f32) -> bool {
+        let tonemap = self
+            .passes
+            .iter_mut()
+            .find_map(|pass| match &mut pass.effect {
+                Effect::Tonemap(tonemap) if pass.enabled => Some(tonemap),
+                _ => None,
+            });
+        tonemap.map(|tonemap| tonemap.exposure = exposure).is_some()
+    }
+
+    /// Whether the scene goes through the stack at all.
+    pub fn is_active(&self) -> bool {
+        self.passes.iter().any(|pass| pass.enabled)
+    }
+
+    /// Colour format the scene is drawn in before the passes, `Rgba8` by default and
+    /// `Rgba16F` for `hdr`.
+    /// The intermediate targets between passes use it as well.
+    pub fn set_scene_format(&mut self, format: TextureFormat) {
+        self.scene_format = format;
+        self.targets = None;
+    }
+
+    pub fn scene_format(&self) -> TextureFormat {
+        self.scene_format
+    }
+
+    /// MSAA sample count of the scene target, the window samples are not used while the
+    /// stack is active.
+    pub fn set_samples(&mut self, samples: u32) {
+        self.samples = samples;
+        self.targets = None;
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    fn tonemap(&self) -> Option<&Tonemap> {
+        self.passes.iter().find_map(|pass| match &pass.effect {
+            Effect::Tonemap(tonemap) if pass.enabled => Some(tonemap),
+            _ => None,
+        })
+    }
+
+    fn pass(&self, name: &str) -> Option<&Pass> {
+        self.passes.iter().find(|pass| pass.name == name)
+    }
+
+    fn pass_mut(&mut self, name: &str) -> Option<&mut Pass> {
+        self.passes.iter_mut().find(|pass| pass.name == name)
+    }
+
+    /// Enabled passes whose shaders are loaded, the others are skipped so they do not
+    /// break the chain.
+    fn drawable_passes<'a>(&'a self, shaders: &'a PostShaders) -> impl Iterator<Item = &'a Pass> {
+        self.passes
+            .iter()
+            .filter(|pass| pass.enabled && shaders.can_draw(&pass.effect))
+    }
+
+    /// Whether any pass would be drawn, the scene goes straight to the output otherwise.
+    pub(crate) fn can_draw(&self, shaders: &PostShaders) -> bool {
+        self.drawable_passes(shaders).next().is_some()
+    }
+
+    /// Binds the target the scene is drawn into, of the given size.
+    /// Returns `false` if it could not be created, the scene goes to the output then.
+    pub(crate) fn begin(&mut self, width: u32, height: u32) -> bool {
+        let size = (width, height);
+        let outdated = self.targets.as_ref().map_or(true, |targets| {
+            (targets.scene.width(), targets.scene.height()) != size
+        });
+        if outdated {
+            self.targets = None;
+            match Targets::new(width, height, self.scene_format, self.samples) {
+                Ok(targets) => self.targets = Some(targets),
+                Err(e) => {
+                    wwg_log::wwg_err!("{e}");
+                }
+            }
+        }
+        match &self.targets {
+            Some(targets) => {
+                targets.scene.bind();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Runs the enabled passes over the scene drawn since `begin`. The last one draws
+    /// into `output`, or the window if `None`, with a viewport of `width` by `height`.
+    pub(crate) fn run(
+        &self,
+        shaders: &PostShaders,
+        output: Option<&RenderTarget>,
+        width: u32,
+        height: u32,
+    ) {
+        let Some(targets) = &self.targets else {
+            return;
+        };
+        targets.scene.resolve();
+        let passes: Vec<&Pass> = self.drawable_passes(shaders).collect();
+
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::BindVertexArray(targets.vertex_array);
+        }
+        let mut source = targets.scene.color_texture(0).unwrap();
+        for (index, pass) in passes.iter().enumerate() {
+            let last = index + 1 == passes.len();
+            let target = &targets.intermediate[index % 2];
+            if last {
+                bind_output(output, width, height);
+            } else {
+                target.bind();
+            }
+            let draw = PassDraw {
+                source: source.id(),
+                texel_size: Vec2::new(1.0 / source.width() as f32, 1.0 / source.height() as f32),
+            };
+            if let Err(e) = draw.effect(&pass.effect, shaders, targets) {
+                wwg_log::wwg_err!("Post-processing pass `{}` failed: {e}", pass.name);
+            }
+            source = target.color_texture(0).unwrap();
+        }
+        unsafe {
+            gl::BindVertexArray(0);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+}
+
+/// Binds `output`, or the window with a viewport of `width` by `height` if `None`.
+pub(crate) fn bind_output(output: Option<&RenderTarget>, width: u32, height: u32) {
+    match output {
+        Some(output) => output.bind(),
+        None => unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, width as i32, height as i32);
+        },
+    }
+}
+
+/// Programs of the built-in effects, loaded with the renderer.
+#[derive(Debug)]
+pub(crate) struct PostShaders {
+    tonemap: Handle<Shader>,
+    gamma: Handle<Shader>,
+    bloom_threshold: Handle<Shader>,
+    blur: Handle<Shader>,
+    bloom_combine: Handle<Shader>,
+    fxaa: Handle<Shader>,
+    vignette: Handle<Shader>,
+    color_grading: Handle<Shader>,
+}
+
+impl PostShaders {
+    pub(crate) fn load(assets: &mut Assets) -> Result<PostShaders, WhirlwingError> {
+        let mut load = |name: &str| {
+            assets.load_shader(
+                FULLSCREEN_VERTEX_SHADER,
+                &format!("shaders/post/{name}.glsl"),
+            )
+        };
+        Ok(PostShaders {
+            tonemap: load("tonemap")?,
+            gamma: load("gamma")?,
+            bloom_threshold: load("bloom_threshold")?,
+            blur: load("blur")?,
+            bloom_combine: load("bloom_combine")?,
+            fxaa: load("fxaa")?,
+            vignette: load("vignette")?,
+            color_grading: load("color_grading")?,
+        })
+    }
+
+    /// Whether the programs `effect` draws with are loaded.
+    fn can_draw(&self, effect: &Effect) -> bool {
+        match effect {
+            Effect::Tonemap(_) => self.tonemap.is_loaded(),
+            Effect::Gamma(_) => self.gamma.is_loaded(),
+            Effect::Bloom(_) => [&self.bloom_threshold, &self.blur, &self.bloom_combine]
+                .iter()
+                .all(|shader| shader.is_loaded()),
+            Effect::Fxaa => self.fxaa.is_loaded(),
+            Effect::Vignette(_) => self.vignette.is_loaded(),
+            Effect::ColorGrading(_) => self.color_grading.is_loaded(),
+            Effect::Custom(material) => material.shader().is_loaded(),
+        }
+    }
+}
+
+/// Offscreen targets of the stack, all recreated when the output size changes.
+#[derive(Debug)]
+struct Targets {
+    scene: RenderTarget,
+    intermediate: [RenderTarget; 2],
+    /// Half resolution, blurred back and forth.
+    bloom: [RenderTarget; 2],
+    /// Empty, the full screen triangle is generated from `gl_VertexID`.
+    vertex_array: u32,
+}
+
+impl Targets {
+    fn new(
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        samples: u32,
+    ) -> Result<Targets, WhirlwingError> {
+        let scene = RenderTargetBuilder::new(width, height)
+            .with_color(format)
+            .with_depth(
+                TextureFormat::Depth24Stencil8,
+                AttachmentStorage::Renderbuffer,
+            )
+            .with_samples(samples)
+            .build()?;
+        let intermediate = RenderTargetBuilder::new(width, height).with_color(format);
+        let bloom = RenderTargetBuilder::new((width / 2).max(1), (height / 2).max(1))
+            .with_color(TextureFormat::Rgba16F);
+
+        let mut vertex_array = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vertex_array);
+        }
+        Ok(Targets {
+            scene,
+            intermediate: [intermediate.build()?, intermediate.build()?],
+            bloom: [bloom.build()?, bloom.build()?],
+            vertex_array,
+        })
+    }
+}
+
+impl Drop for Targets {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vertex_array);
+        }
+    }
+}
+
+/// Draws one pass into the bound framebuffer.
+struct PassDraw {
+    source: u32,
+    texel_size: Vec2,
+}
+
+impl PassDraw {
+    fn effect(
+        &self,
+        effect: &Effect,
+        shaders: &PostShaders,
+        targets: &Targets,
+    ) -> Result<(), WhirlwingError> {
+        match effect {
+            Effect::Tonemap(tonemap) => {
+                let curve = match tonemap.curve {
+                    Tonemapper::Reinhard => 0,
+                    Tonemapper::Aces => 1,
+                };
+                self.draw(
+                    &shaders.tonemap,
+                    &[("exposure", &tonemap.exposure), ("curve", &curve)],
+                )
+            }
+            Effect::Gamma(gamma) => self.draw(&shaders.gamma, &[("gamma", gamma)]),
+            Effect::Bloom(bloom) => self.bloom(bloom, shaders, targets),
+            Effect::Fxaa => self.draw(&shaders.fxaa, &[("texel_size", &self.texel_size)]),
+            Effect::Vignette(vignette) => self.draw(
+                &shaders.vignette,
+                &[
+                    ("intensity", &vignette.intensity),
+                    ("smoothness", &vignette.smoothness),
+                ],
+            ),
+            Effect::ColorGrading(grading) => {
+                let lut = grading.lut.get().map_or(0, |lut| lut.id());
+                unsafe {
+                    gl::ActiveTexture(gl::TEXTURE1);
+                    gl::BindTexture(gl::TEXTURE_3D, lut);
+                }
+                self.draw(
+                    &shaders.color_grading,
+                    &[("lut", &1), ("strength", &grading.strength)],
+                )
+            }
+            Effect::Custom(material) => self.custom(material),
+        }
+    }
+
+    /// Draws with `source` on texture unit 0 and the given uniforms.
+    fn draw(
+        &self,
+        shader: &Handle<Shader>,
+        uniforms: &[(&str, &dyn SetUniform)],
+    ) -> Result<(), WhirlwingError> {
+        let Some(shader) = shader.get() else {
+            return Ok(());
+        };
+        shader.bind();
+        bind_texture(0, self.source);
+        shader.set("source", &0)?;
+        for (name, value) in uniforms {
+            value.set_on(shader, name)?;
+        }
+        draw_triangle();
+        Ok(())
+    }
+
+    fn bloom(
+        &self,
+        bloom: &Bloom,
+        shaders: &PostShaders,
+        targets: &Targets,
+    ) -> Result<(), WhirlwingError> {
+        // Drawn into the bloom targets first, then back into the output.
+        let (mut output, mut viewport) = (0, [0; 4]);
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut output);
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+
+        let [first, second] = &targets.bloom;
+        first.bind();
+        self.draw(&shaders.bloom_threshold, &[("threshold", &bloom.threshold)])?;
+        let texel = Vec2::new(1.0 / first.width() as f32, 1.0 / first.height() as f32);
+        for _ in 0..bloom.iterations {
+            let blur = |from: &RenderTarget, to: &RenderTarget, direction: Vec2| {
+                to.bind();
+                let pass = PassDraw {
+                    source: from.color_texture(0).unwrap().id(),
+                    texel_size: texel,
+                };
+                pass.draw(&shaders.blur, &[("direction", &direction)])
+            };
+            blur(first, second, Vec2::new(texel.x, 0.0))?;
+            blur(second, first, Vec2::new(0.0, texel.y))?;
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, output as u32);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+        bind_texture(1, first.color_texture(0).unwrap().id());
+        self.draw(
+            &shaders.bloom_combine,
+            &[("bloom", &1), ("intensity", &bloom.intensity)],
+        )
+    }
+
+    fn custom(&self, material: &Material) -> Result<(), WhirlwingError> {
+        let Some(shader) = material.shader().get() else {
+            return Ok(());
+        };
+        material.bind_with(&mut RenderState::default())?;
+        // Material textures take the units from 0 on, in order.
+        let unit = material
+            .params()
+            .iter()
+            .filter(|(name, param)| {
+                matches!(param, MaterialParam::Texture(_)) && shader.has_uniform(name)
+            })
+            .count();
+        bind_texture(unit as u32, self.source);
+        if shader.has_uniform("source") {
+            shader.set("source", &(unit as i32))?;
+        }
+        if shader.has_uniform("texel_size") {
+            shader.set("texel_size", &self.texel_size)?;
+        }
+        draw_triangle();
+        Ok(())
+    }
+}
+
+/// Object safe `Shader::set`, to pass uniforms of different types in one list.
+trait SetUniform {
+    fn set_on(&self, shader: &Shader, name: &str) -> Result<(), WhirlwingError>;
+}
+
+impl<T: Uniform> SetUniform for T {
+    fn set_on(&self, shader: &Shader, name: &str) -> Result<(), WhirlwingError> {
+        shader.set(name, self)
+    }
+}
+
+fn bind_texture(unit: u32, texture: u32) {
+    unsafe {
+        gl::ActiveTexture(gl::TEXTURE0 + unit);
+        gl::BindTexture(gl::TEXTURE_2D, texture);
+    }
+}
+
+fn draw_triangle() {
+    unsafe {
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+    }
+}
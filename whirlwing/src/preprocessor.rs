@@ -0,0 +1,289 @@
+use std::{
+    collections::HashSet,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+
+/// Expands `#include "path"` directives and injects `#define`s into GLSL source.
+///
+/// Include paths are relative to the include root, usually the asset root.
+/// Every file is included at most once per shader, so include guards are optional.
+/// Defines are inserted right after the `#version` directive, which may follow blank lines
+/// and comments.
+///
+/// ```
+/// use whirlwing::preprocessor::Preprocessor;
+///
+/// let preprocessor = Preprocessor::new("res").define("MAX_LIGHTS", "8");
+/// let processed = preprocessor
+///     .process_str("#version 330 core\nvoid main() {}\n", "example")
+///     .unwrap();
+/// assert_eq!(
+///     processed.source(),
+///     "#version 330 core\n#define MAX_LIGHTS 8\nvoid main() {}\n"
+/// );
+/// assert_eq!(processed.original_line(3), Some((std::path::Path::new("example"), 2)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Preprocessor {
+    include_root: PathBuf,
+    defines: Vec<(String, String)>,
+}
+
+impl Preprocessor {
+    pub fn new<P: Into<PathBuf>>(include_root: P) -> Self {
+        Preprocessor {
+            include_root: include_root.into(),
+            defines: Vec::new(),
+        }
+    }
+
+    /// Adds `#define name value`, an empty value defines a flag.
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.defines.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn include_root(&self) -> &Path {
+        &self.include_root
+    }
+
+    pub fn defines(&self) -> &[(String, String)] {
+        &self.defines
+    }
+
+    pub fn process_file<P: AsRef<Path>>(&self, path: P) -> Result<ProcessedSource, WhirlwingError> {
+        let path = path.as_ref();
+        let source = read_source(path, None)?;
+        self.process(&source, path.to_path_buf())
+    }
+
+    /// `name` identifies the source in error messages.
+    pub fn process_str(&self, source: &str, name: &str) -> Result<ProcessedSource, WhirlwingError> {
+        self.process(source, PathBuf::from(name))
+    }
+
+    fn process(&self, source: &str, name: PathBuf) -> Result<ProcessedSource, WhirlwingError> {
+        let mut output = ProcessedSource {
+            source: String::new(),
+            lines: Vec::new(),
+            files: vec![name.clone()],
+        };
+
+        let mut lines = source.lines().enumerate();
+        if let Some(version) = version_line(source) {
+            for (index, line) in lines.by_ref().take(version + 1) {
+                output.push_line(line, Some((0, index as u32 + 1)));
+            }
+        }
+        for (name, value) in &self.defines {
+            output.push_line(format!("#define {name} {value}").trim_end(), None);
+        }
+
+        let key = std::fs::canonicalize(&name).unwrap_or(name);
+        let mut expansion = Expansion {
+            preprocessor: self,
+            output,
+            stack: vec![(key, 0)],
+            included: HashSet::new(),
+        };
+        expansion.expand(lines, 0)?;
+        Ok(expansion.output)
+    }
+}
+
+/// GLSL source with includes expanded, remembering where each line came from.
+#[derive(Debug, Clone)]
+pub struct ProcessedSource {
+    source: String,
+    // File index and line for every output line, `None` for injected defines.
+    lines: Vec<Option<(usize, u32)>>,
+    // The processed file or source name first, then every included file.
+    files: Vec<PathBuf>,
+}
+
+impl ProcessedSource {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Path of the processed file, or the name given to `process_str`.
+    pub fn name(&self) -> &Path {
+        &self.files[0]
+    }
+
+    /// Files pulled in through `#include`, in the order they were first included.
+    pub fn includes(&self) -> &[PathBuf] {
+        &self.files[1..]
+    }
+
+    /// Maps a 1-based line of `source` to the file and line it came from.
+    pub fn original_line(&self, line: u32) -> Option<(&Path, u32)> {
+        let (file, line) = (*self.lines.get(line.checked_sub(1)? as usize)?)?;
+        Some((&self.files[file], line))
+    }
+
+    /// Rewrites the line numbers in a driver info log, e.g. `0:12(5): error` or
+    /// `0(12) : error`, to `file:line` of the original source.
+    pub fn map_log(&self, log: &str) -> String {
+        let mut mapped = String::with_capacity(log.len());
+        for line in log.lines() {
+            match self.map_log_line(line) {
+                Some(line) => mapped.push_str(&line),
+                None => mapped.push_str(line),
+            }
+            mapped.push('\n');
+        }
+        mapped
+    }
+
+    fn map_log_line(&self, line: &str) -> Option<String> {
+        let prefix_len = ["ERROR: ", "WARNING: "]
+            .iter()
+            .find(|prefix| line.starts_with(*prefix))
+            .map_or(0, |prefix| prefix.len());
+        let (prefix, rest) = line.split_at(prefix_len);
+
+        // Source string number, always 0 since the source is passed as one string.
+        let rest = rest.strip_prefix('0')?;
+        let (rest, closing) = match rest.strip_prefix(':') {
+            Some(rest) => (rest, ""),
+            None => (rest.strip_prefix('(')?, ")"),
+        };
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        let line_number = rest[..digits].parse().ok()?;
+        let rest = rest[digits..].strip_prefix(closing)?;
+
+        let (file, original) = self.original_line(line_number)?;
+        Some(format!("{prefix}{}:{original}{rest}", file.display()))
+    }
+
+    fn push_line(&mut self, line: &str, origin: Option<(usize, u32)>) {
+        self.source.push_str(line);
+        self.source.push('\n');
+        self.lines.push(origin);
+    }
+}
+
+struct Expansion<'a> {
+    preprocessor: &'a Preprocessor,
+    output: ProcessedSource,
+    // Canonical paths and file indices of the files currently being expanded.
+    stack: Vec<(PathBuf, usize)>,
+    included: HashSet<PathBuf>,
+}
+
+impl Expansion<'_> {
+    fn expand<'s>(
+        &mut self,
+        lines: impl Iterator<Item = (usize, &'s str)>,
+        file: usize,
+    ) -> Result<(), WhirlwingError> {
+        for (index, line) in lines {
+            let line_number = index as u32 + 1;
+            let Some(directive) = line.trim_start().strip_prefix("#include") else {
+                self.output.push_line(line, Some((file, line_number)));
+                continue;
+            };
+
+            let include = parse_include(directive).ok_or_else(|| {
+                self.error(
+                    file,
+                    line_number,
+                    format!("Malformed include: {}", line.trim()),
+                )
+            })?;
+            let path = self.preprocessor.include_root.join(include);
+            let key = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+
+            if self.stack.iter().any(|(open, _)| *open == key) {
+                let mut cycle = String::new();
+                for (_, open) in self.stack.iter().skip_while(|(open, _)| *open != key) {
+                    let _ = write!(cycle, "{} -> ", self.output.files[*open].display());
+                }
+                let _ = write!(cycle, "{}", path.display());
+                return Err(self.error(file, line_number, format!("Include cycle: {cycle}")));
+            }
+            if !self.included.insert(key.clone()) {
+                continue;
+            }
+
+            let included_from = self.location(file, line_number);
+            let source = read_source(&path, Some(&included_from))?;
+
+            self.output.files.push(path);
+            let included_file = self.output.files.len() - 1;
+            self.stack.push((key, included_file));
+            self.expand(source.lines().enumerate(), included_file)?;
+            self.stack.pop();
+        }
+        Ok(())
+    }
+
+    fn location(&self, file: usize, line: u32) -> String {
+        format!("{}:{line}", self.output.files[file].display())
+    }
+
+    fn error(&self, file: usize, line: u32, message: String) -> WhirlwingError {
+        WhirlwingError::new(
+            format!("{}: {message}", self.location(file, line)),
+            WhirlwingErrorKind::ShaderCompilationFailure,
+        )
+    }
+}
+
+/// Index of the `#version` line, if only blank lines and comments come before it.
+fn version_line(source: &str) -> Option<usize> {
+    let mut in_comment = false;
+    for (index, line) in source.lines().enumerate() {
+        let mut rest = line.trim();
+        // Skips the block comments the line starts with.
+        loop {
+            if in_comment {
+                match rest.find("*/") {
+                    Some(end) => {
+                        rest = rest[end + 2..].trim_start();
+                        in_comment = false;
+                    }
+                    None => break,
+                }
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                rest = comment;
+                in_comment = true;
+            } else {
+                break;
+            }
+        }
+        if in_comment || rest.is_empty() || rest.starts_with("//") {
+            continue;
+        }
+        return rest.starts_with("#version").then_some(index);
+    }
+    None
+}
+
+/// Accepts `"path"` and `<path>`.
+fn parse_include(directive: &str) -> Option<&str> {
+    let directive = directive.trim();
+    let path = directive
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .or_else(|| directive.strip_prefix('<')?.strip_suffix('>'))?;
+    (!path.is_empty()).then_some(path)
+}
+
+fn read_source(path: &Path, included_from: Option<&str>) -> Result<String, WhirlwingError> {
+    std::fs::read_to_string(path).map_err(|error| {
+        let content = match included_from {
+            Some(location) => format!("{location}: Failed to read include: {}", path.display()),
+            None => format!("Failed to read from path: {}", path.display()),
+        };
+        WhirlwingError::new_with_source(
+            content,
+            WhirlwingErrorKind::ShaderCompilationFailure,
+            Box::new(error),
+        )
+    })
+}
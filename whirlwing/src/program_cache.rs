@@ -0,0 +1,165 @@
+use std::{
+    ffi::{c_void, CStr},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// On-disk cache of linked program binaries, skipping GLSL compilation on later launches.
+///
+/// Entries are keyed by a hash of the preprocessed sources and the OpenGL vendor, renderer
+/// and version strings, so a driver update starts over. Binaries the driver rejects are
+/// deleted and the program is compiled from source instead.
+/// Does nothing if the driver supports no binary formats.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramCache {
+    directory: PathBuf,
+}
+
+impl ProgramCache {
+    /// The directory is created when the first binary is stored.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        ProgramCache {
+            directory: directory.into(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Returns false without a current context or if the driver has no binary formats.
+    pub fn is_supported() -> bool {
+        if !gl::GetProgramBinary::is_loaded() || !gl::ProgramBinary::is_loaded() {
+            return false;
+        }
+        let mut formats = 0;
+        unsafe {
+            gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+        }
+        formats > 0
+    }
+
+    /// Cache key of a program built from `(shader type, source)` pairs in a fixed order.
+    pub(crate) fn key(&self, sources: &[(u32, &str)]) -> u64 {
+        let mut hash = Fnv1a::new();
+        for name in [gl::VENDOR, gl::RENDERER, gl::VERSION] {
+            unsafe {
+                let string = gl::GetString(name);
+                if !string.is_null() {
+                    hash.write(CStr::from_ptr(string as *const _).to_bytes());
+                }
+            }
+            hash.write(&[0]);
+        }
+        for (shader_type, source) in sources {
+            hash.write(&shader_type.to_le_bytes());
+            // The length keeps ("ab", "c") and ("a", "bc") apart.
+            hash.write(&(source.len() as u64).to_le_bytes());
+            hash.write(source.as_bytes());
+        }
+        hash.finish()
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.directory.join(format!("{key:016x}.bin"))
+    }
+
+    /// Creates a program from the cached binary, or returns `None` if there is no usable one.
+    pub(crate) fn load(&self, key: u64) -> Option<u32> {
+        let path = self.path(key);
+        let data = fs::read(&path).ok()?;
+        if data.len() < 4 {
+            return None;
+        }
+        let (format, binary) = data.split_at(4);
+
+        unsafe {
+            let program_id = gl::CreateProgram();
+            gl::ProgramBinary(
+                program_id,
+                u32::from_le_bytes(format.try_into().unwrap()),
+                binary.as_ptr() as *const c_void,
+                binary.len() as i32,
+            );
+
+            let mut success = 0;
+            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
+            if success == 0 {
+                gl::DeleteProgram(program_id);
+                wwg_log::wwg_info!("Driver rejected cached program: {}", path.display());
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+            Some(program_id)
+        }
+    }
+
+    /// Must be called before linking for the driver to keep a retrievable binary.
+    pub(crate) fn prepare(program_id: u32) {
+        unsafe {
+            gl::ProgramParameteri(
+                program_id,
+                gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                gl::TRUE as i32,
+            );
+        }
+    }
+
+    /// Stores the binary of a successfully linked program, failures are only logged.
+    pub(crate) fn store(&self, key: u64, program_id: u32) {
+        let binary = unsafe {
+            let mut success = 0;
+            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
+            if success == 0 {
+                return;
+            }
+
+            let mut length = 0;
+            gl::GetProgramiv(program_id, gl::PROGRAM_BINARY_LENGTH, &mut length);
+            if length <= 0 {
+                return;
+            }
+
+            let mut binary = vec![0u8; length as usize];
+            let mut format = 0;
+            gl::GetProgramBinary(
+                program_id,
+                length,
+                &mut length,
+                &mut format,
+                binary.as_mut_ptr() as *mut c_void,
+            );
+            binary.truncate(length as usize);
+
+            let mut data = format.to_le_bytes().to_vec();
+            data.append(&mut binary);
+            data
+        };
+
+        let path = self.path(key);
+        let result = fs::create_dir_all(&self.directory).and_then(|_| fs::write(&path, binary));
+        if let Err(error) = result {
+            wwg_log::wwg_warn!("Failed to store program binary {}: {error}", path.display());
+        }
+    }
+}
+
+/// 64-bit FNV-1a, stable across Rust versions unlike `DefaultHasher`.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
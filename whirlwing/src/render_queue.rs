@@ -0,0 +1,289 @@
+use wwg_math::{Mat4, Vec4};
+
+use crate::{
+    assets::Handle,
+    instancing::{InstanceBuffer, InstanceData},
+    material::Material,
+    mesh::Mesh,
+};
+
+/// Mesh drawn with a material, see `RenderQueue::submit`.
+#[derive(Debug, Clone)]
+pub struct RenderItem {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<Material>,
+    pub instance: InstanceData,
+}
+
+/// Counts of the last frame drawn from a `RenderQueue`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Submitted items, including those skipped because an asset was not loaded.
+    pub items: u32,
+    pub draw_calls: u32,
+    /// Items drawn through instanced draw calls.
+    pub instances: u32,
+    pub program_changes: u32,
+    /// Materials whose parameters were uploaded, once per run of items sharing a material.
+    pub material_changes: u32,
+    pub texture_changes: u32,
+    pub vertex_array_changes: u32,
+}
+
+/// Items to draw this frame, sorted to change as little OpenGL state as possible.
+///
+/// Opaque items are drawn first, grouped by shader, material and mesh and front to back
+/// within a group. Transparent materials are drawn afterwards, back to front with
+/// blending on and depth writes off. The queue is emptied every frame after drawing.
+///
+/// Materials whose shader declares the `instance_model` attribute of `InstanceData` are
+/// drawn instanced: opaque items sharing mesh and material become one draw call.
+/// Other shaders get the model matrix through the `model` uniform, one draw per item.
+/// The lit and default shaders of the engine declare it with `INSTANCED` defined.
+#[derive(Debug, Default)]
+pub struct RenderQueue {
+    items: Vec<RenderItem>,
+    stats: RenderStats,
+    // Created on the first instanced draw, the queue may exist before the context.
+    instances: Option<InstanceBuffer<InstanceData>>,
+}
+
+impl RenderQueue {
+    pub fn new() -> Self {
+        RenderQueue::default()
+    }
+
+    /// `transform` is the model matrix of the item.
+    pub fn submit(&mut self, mesh: Handle<Mesh>, material: Handle<Material>, transform: Mat4) {
+        self.submit_instance(mesh, material, InstanceData::new(transform));
+    }
+
+    /// Like `submit`, with the tint and data read by instanced shaders.
+    pub fn submit_instance(
+        &mut self,
+        mesh: Handle<Mesh>,
+        material: Handle<Material>,
+        instance: InstanceData,
+    ) {
+        self.items.push(RenderItem {
+            mesh,
+            material,
+            instance,
+        });
+    }
+
+    pub fn items(&self) -> &[RenderItem] {
+        &self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Counts of the last `flush`.
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    /// Draws and clears the queue, `view` is used to sort by depth.
+    pub(crate) fn flush(&mut self, view: &Mat4) {
+        let mut batches: Vec<Batch> = self
+            .items
+            .iter()
+            .filter_map(|item| Batch::new(item, view))
+            .collect();
+        batches.sort_by(|a, b| a.order(b));
+
+        // Runs of batches drawn with one call, instance data of all runs in one upload.
+        let mut runs = Vec::new();
+        let mut instances = Vec::new();
+        let mut start = 0;
+        while start < batches.len() {
+            let first = &batches[start];
+            let mut end = start + 1;
+            if first.instanced {
+                while end < batches.len() && !first.transparent && first.merges(&batches[end]) {
+                    end += 1;
+                }
+                runs.push((start..end, instances.len()));
+                instances.extend(batches[start..end].iter().map(|batch| batch.item.instance));
+            } else {
+                runs.push((start..end, 0));
+            }
+            start = end;
+        }
+        if !instances.is_empty() {
+            self.instances
+                .get_or_insert_with(|| InstanceBuffer::new(InstanceData::layout()))
+                .upload(&instances);
+        }
+
+        let mut state = RenderState::default();
+        let mut transparent = false;
+        let mut previous: Option<&Material> = None;
+        for (range, first_instance) in runs {
+            let batch = &batches[range.start];
+            if batch.transparent != transparent {
+                transparent = batch.transparent;
+                set_blending(transparent);
+            }
+            let shader = batch.material.shader().get().unwrap();
+            let changed = previous.map_or(true, |previous| !std::ptr::eq(previous, batch.material));
+            if changed || state.program != Some(shader.id()) {
+                if let Err(e) = batch.material.bind_with(&mut state) {
+                    wwg_log::wwg_err!("{e}");
+                }
+                state.stats.material_changes += 1;
+                previous = Some(batch.material);
+            }
+            state.bind_vertex_array(batch.mesh.vertex_array());
+            if batch.instanced {
+                let buffer = self.instances.as_ref().unwrap();
+                unsafe {
+                    buffer.attach(first_instance);
+                }
+                batch.mesh.draw_bound_instanced(range.len() as i32);
+                unsafe {
+                    buffer.detach();
+                }
+                state.stats.instances += range.len() as u32;
+            } else {
+                if shader.has_uniform("model") {
+                    if let Err(e) = shader.set_mat4("model", &batch.item.instance.model) {
+                        wwg_log::wwg_err!("{e}");
+                    }
+                }
+                batch.mesh.draw_bound();
+            }
+            state.stats.draw_calls += 1;
+        }
+        if transparent {
+            set_blending(false);
+        }
+
+        self.stats = RenderStats {
+            items: self.items.len() as u32,
+            ..state.stats
+        };
+        self.items.clear();
+    }
+}
+
+/// Loaded item with its sort key.
+struct Batch<'a> {
+    item: &'a RenderItem,
+    mesh: &'a Mesh,
+    material: &'a Material,
+    transparent: bool,
+    instanced: bool,
+    program: u32,
+    /// View space distance in front of the camera.
+    depth: f32,
+}
+
+impl<'a> Batch<'a> {
+    fn new(item: &'a RenderItem, view: &Mat4) -> Option<Batch<'a>> {
+        let mesh = item.mesh.get()?;
+        let material = item.material.get()?;
+        let shader = material.shader().get()?;
+        let position = *view * item.instance.model * Vec4::new(0.0, 0.0, 0.0, 1.0);
+        Some(Batch {
+            item,
+            mesh,
+            material,
+            transparent: material.is_transparent(),
+            instanced: shader.has_attribute("instance_model"),
+            program: shader.id(),
+            depth: -position.z,
+        })
+    }
+
+    /// Same mesh and material, so both can be drawn by one instanced call.
+    fn merges(&self, other: &Batch) -> bool {
+        std::ptr::eq(self.material, other.material) && std::ptr::eq(self.mesh, other.mesh)
+    }
+
+    fn order(&self, other: &Batch) -> std::cmp::Ordering {
+        let key = |batch: &Batch| {
+            (
+                batch.program,
+                batch.material as *const Material as usize,
+                batch.mesh.vertex_array(),
+            )
+        };
+        self.transparent
+            .cmp(&other.transparent)
+            .then_with(|| match self.transparent {
+                // Blending needs the farthest first, whatever the state changes cost.
+                true => other.depth.total_cmp(&self.depth),
+                false => key(self)
+                    .cmp(&key(other))
+                    .then_with(|| self.depth.total_cmp(&other.depth)),
+            })
+    }
+}
+
+fn set_blending(enabled: bool) {
+    unsafe {
+        if enabled {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+        } else {
+            gl::Disable(gl::BLEND);
+            gl::DepthMask(gl::TRUE);
+        }
+    }
+}
+
+/// OpenGL bindings made so far, to skip binding the same object twice.
+///
+/// Starts out knowing nothing, so the first bind of each kind always happens.
+#[derive(Debug, Default)]
+pub(crate) struct RenderState {
+    program: Option<u32>,
+    vertex_array: Option<u32>,
+    textures: Vec<Option<u32>>,
+    stats: RenderStats,
+}
+
+impl RenderState {
+    pub(crate) fn use_program(&mut self, program: u32) {
+        if self.program != Some(program) {
+            unsafe { gl::UseProgram(program) };
+            self.program = Some(program);
+            self.stats.program_changes += 1;
+        }
+    }
+
+    pub(crate) fn bind_texture(&mut self, unit: u32, texture: u32) {
+        let unit = unit as usize;
+        if self.textures.len() <= unit {
+            self.textures.resize(unit + 1, None);
+        }
+        if self.textures[unit] != Some(texture) {
+            unsafe {
+                gl::ActiveTexture(gl::TEXTURE0 + unit as u32);
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+            }
+            self.textures[unit] = Some(texture);
+            self.stats.texture_changes += 1;
+        }
+    }
+
+    pub(crate) fn bind_vertex_array(&mut self, vertex_array: u32) {
+        if self.vertex_array != Some(vertex_array) {
+            unsafe { gl::BindVertexArray(vertex_array) };
+            self.vertex_array = Some(vertex_array);
+            self.stats.vertex_array_changes += 1;
+        }
+    }
+}
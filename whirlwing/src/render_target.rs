@@ -0,0 +1,547 @@
+use std::{collections::HashMap, ffi::c_void};
+
+use image::RgbaImage;
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+
+use crate::texture::{Filter, SamplerDesc, Texture2D, TextureFormat, Wrap};
+
+/// How an attachment of a `RenderTarget` is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentStorage {
+    /// Can be sampled after rendering, e.g. a depth texture for shadow mapping.
+    Texture,
+    /// Only used while rendering into the target, which lets the driver skip storing it.
+    Renderbuffer,
+}
+
+/// Describes a `RenderTarget`, also used to recreate it when resized.
+#[derive(Debug, Clone)]
+pub struct RenderTargetBuilder {
+    width: u32,
+    height: u32,
+    colors: Vec<TextureFormat>,
+    depth: Option<(TextureFormat, AttachmentStorage)>,
+    samples: u32,
+    sampler: SamplerDesc,
+    window_scale: Option<f32>,
+}
+
+impl RenderTargetBuilder {
+    pub fn new(width: u32, height: u32) -> Self {
+        RenderTargetBuilder {
+            width,
+            height,
+            colors: Vec::new(),
+            depth: None,
+            samples: 0,
+            sampler: SamplerDesc {
+                wrap_s: Wrap::ClampToEdge,
+                wrap_t: Wrap::ClampToEdge,
+                min_filter: Filter::Linear,
+                mag_filter: Filter::Linear,
+                mipmaps: false,
+                ..SamplerDesc::default()
+            },
+            window_scale: None,
+        }
+    }
+
+    /// Adds a colour attachment, written by fragment shader output `location = n` for the
+    /// n-th call.
+    pub fn with_color(mut self, format: TextureFormat) -> Self {
+        self.colors.push(format);
+        self
+    }
+
+    pub fn with_depth(mut self, format: TextureFormat, storage: AttachmentStorage) -> Self {
+        self.depth = Some((format, storage));
+        self
+    }
+
+    /// MSAA sample count, 0 and 1 disable multisampling.
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Sampling of the texture attachments, linear without mipmaps by default.
+    pub fn with_sampler(mut self, sampler: SamplerDesc) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Keeps the target at the window size multiplied by `scale` once added to
+    /// `RenderTargets`.
+    pub fn with_window_scale(mut self, scale: f32) -> Self {
+        self.window_scale = Some(scale);
+        self
+    }
+
+    pub fn build(&self) -> Result<RenderTarget, WhirlwingError> {
+        self.validate()?;
+        let multisampled = self.samples > 1;
+
+        unsafe {
+            let previous = bound_framebuffer();
+            let mut framebuffer = 0;
+            gl::GenFramebuffers(1, &mut framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+
+            let mut target = RenderTarget {
+                desc: self.clone(),
+                framebuffer,
+                colors: Vec::new(),
+                depth: None,
+                resolve: None,
+            };
+            for (index, format) in self.colors.iter().enumerate() {
+                let attachment = gl::COLOR_ATTACHMENT0 + index as u32;
+                let storage = match multisampled {
+                    true => AttachmentStorage::Renderbuffer,
+                    false => AttachmentStorage::Texture,
+                };
+                target
+                    .colors
+                    .push(self.attach(attachment, *format, storage));
+            }
+            if let Some((format, storage)) = self.depth {
+                let attachment = match format.has_stencil() {
+                    true => gl::DEPTH_STENCIL_ATTACHMENT,
+                    false => gl::DEPTH_ATTACHMENT,
+                };
+                // Multisampled textures cannot be sampled as `sampler2D`, the resolved copy
+                // holds the texture instead.
+                let storage = match multisampled {
+                    true => AttachmentStorage::Renderbuffer,
+                    false => storage,
+                };
+                target.depth = Some(self.attach(attachment, format, storage));
+            }
+            select_buffers(self.colors.len());
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(WhirlwingError::new(
+                    format!(
+                        "Render target is incomplete: {} ({status:#x})",
+                        status_name(status)
+                    ),
+                    WhirlwingErrorKind::FramebufferFailure,
+                ));
+            }
+
+            if multisampled {
+                let mut resolve = self.clone().with_samples(0);
+                resolve.window_scale = None;
+                if let Some((_, AttachmentStorage::Renderbuffer)) = resolve.depth {
+                    resolve.depth = None;
+                }
+                target.resolve = Some(Box::new(resolve.build()?));
+            }
+            Ok(target)
+        }
+    }
+
+    fn validate(&self) -> Result<(), WhirlwingError> {
+        let error = |content: String, kind| Err(WhirlwingError::new(content, kind));
+        if self.width == 0 || self.height == 0 {
+            return error(
+                format!("Render target size {}x{} is empty", self.width, self.height),
+                WhirlwingErrorKind::FramebufferFailure,
+            );
+        }
+        if let Some(format) = self.colors.iter().find(|format| format.is_depth()) {
+            return error(
+                format!("{format:?} cannot be used as colour attachment"),
+                WhirlwingErrorKind::FramebufferFailure,
+            );
+        }
+        if let Some((format, _)) = self.depth.filter(|(format, _)| !format.is_depth()) {
+            return error(
+                format!("{format:?} cannot be used as depth attachment"),
+                WhirlwingErrorKind::FramebufferFailure,
+            );
+        }
+
+        let (mut max_colors, mut max_samples) = (0, 0);
+        unsafe {
+            gl::GetIntegerv(gl::MAX_COLOR_ATTACHMENTS, &mut max_colors);
+            gl::GetIntegerv(gl::MAX_SAMPLES, &mut max_samples);
+        }
+        if self.colors.len() > max_colors as usize {
+            return error(
+                format!(
+                    "{} colour attachments requested, the driver supports {max_colors}",
+                    self.colors.len()
+                ),
+                WhirlwingErrorKind::UnsupportedFeature,
+            );
+        }
+        if self.samples > max_samples as u32 {
+            return error(
+                format!(
+                    "{} samples requested, the driver supports {max_samples}",
+                    self.samples
+                ),
+                WhirlwingErrorKind::UnsupportedFeature,
+            );
+        }
+        Ok(())
+    }
+
+    /// Attaches new storage to the bound framebuffer.
+    unsafe fn attach(
+        &self,
+        attachment: u32,
+        format: TextureFormat,
+        storage: AttachmentStorage,
+    ) -> Buffer {
+        match storage {
+            AttachmentStorage::Texture => {
+                let texture = Texture2D::empty(self.width, self.height, format, &self.sampler);
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    attachment,
+                    gl::TEXTURE_2D,
+                    texture.id(),
+                    0,
+                );
+                Buffer::Texture(texture)
+            }
+            AttachmentStorage::Renderbuffer => {
+                let mut renderbuffer = 0;
+                gl::GenRenderbuffers(1, &mut renderbuffer);
+                gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+                gl::RenderbufferStorageMultisample(
+                    gl::RENDERBUFFER,
+                    if self.samples > 1 {
+                        self.samples as i32
+                    } else {
+                        0
+                    },
+                    format.gl_internal_format(),
+                    self.width as i32,
+                    self.height as i32,
+                );
+                gl::FramebufferRenderbuffer(
+                    gl::FRAMEBUFFER,
+                    attachment,
+                    gl::RENDERBUFFER,
+                    renderbuffer,
+                );
+                Buffer::Renderbuffer(renderbuffer)
+            }
+        }
+    }
+}
+
+/// Framebuffer object to render into instead of the window.
+///
+/// Colour attachments are textures which can be sampled once rendering is done. Multisampled
+/// targets render into renderbuffers and keep a single-sampled copy with the textures,
+/// updated by `resolve`. Must be created and dropped while the OpenGL context is current.
+#[derive(Debug)]
+pub struct RenderTarget {
+    desc: RenderTargetBuilder,
+    framebuffer: u32,
+    colors: Vec<Buffer>,
+    depth: Option<Buffer>,
+    resolve: Option<Box<RenderTarget>>,
+}
+
+impl RenderTarget {
+    pub fn width(&self) -> u32 {
+        self.desc.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.desc.height
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.desc.samples
+    }
+
+    pub fn color_formats(&self) -> &[TextureFormat] {
+        &self.desc.colors
+    }
+
+    pub fn window_scale(&self) -> Option<f32> {
+        self.desc.window_scale
+    }
+
+    pub fn id(&self) -> u32 {
+        self.framebuffer
+    }
+
+    /// Renders into the target from now on, the viewport is set to cover it.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::Viewport(0, 0, self.width() as i32, self.height() as i32);
+        }
+    }
+
+    /// Colour attachment `index`, `None` if out of range.
+    /// For multisampled targets this is the resolved copy.
+    pub fn color_texture(&self, index: usize) -> Option<&Texture2D> {
+        match &self.resolve {
+            Some(resolve) => resolve.color_texture(index),
+            None => self.colors.get(index).and_then(Buffer::texture),
+        }
+    }
+
+    /// Depth attachment, if it is stored as texture.
+    pub fn depth_texture(&self) -> Option<&Texture2D> {
+        match &self.resolve {
+            Some(resolve) => resolve.depth_texture(),
+            None => self.depth.as_ref().and_then(Buffer::texture),
+        }
+    }
+
+    /// Copies the samples of a multisampled target into its textures, does nothing for
+    /// other targets. Depth is only resolved if it is stored as texture.
+    pub fn resolve(&self) {
+        let Some(resolve) = &self.resolve else {
+            return;
+        };
+        let (width, height) = (self.width() as i32, self.height() as i32);
+        unsafe {
+            let previous = bound_framebuffer();
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.framebuffer);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, resolve.framebuffer);
+            for index in 0..self.colors.len() {
+                let attachment = gl::COLOR_ATTACHMENT0 + index as u32;
+                let mut draw_buffers = vec![gl::NONE; index + 1];
+                draw_buffers[index] = attachment;
+                gl::ReadBuffer(attachment);
+                gl::DrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr());
+                gl::BlitFramebuffer(
+                    0,
+                    0,
+                    width,
+                    height,
+                    0,
+                    0,
+                    width,
+                    height,
+                    gl::COLOR_BUFFER_BIT,
+                    gl::NEAREST,
+                );
+            }
+            if let Some((format, _)) = resolve.desc.depth {
+                let mut mask = gl::DEPTH_BUFFER_BIT;
+                if format.has_stencil() {
+                    mask |= gl::STENCIL_BUFFER_BIT;
+                }
+                gl::BlitFramebuffer(0, 0, width, height, 0, 0, width, height, mask, gl::NEAREST);
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, resolve.framebuffer);
+            select_buffers(self.colors.len());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            select_buffers(self.colors.len());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+        }
+    }
+
+    /// Recreates the attachments at the new size, their contents are lost.
+    /// The target is left unchanged if this fails.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), WhirlwingError> {
+        if (width, height) == (self.width(), self.height()) {
+            return Ok(());
+        }
+        let mut desc = self.desc.clone();
+        desc.width = width;
+        desc.height = height;
+        *self = desc.build()?;
+        Ok(())
+    }
+
+    /// Reads back colour attachment `index` as 8-bit RGBA, with the first row being the top
+    /// of the image. Multisampled targets are read from the copy made by `resolve`.
+    pub fn read_pixels(&self, index: usize) -> Option<RgbaImage> {
+        if let Some(resolve) = &self.resolve {
+            return resolve.read_pixels(index);
+        }
+        if index >= self.colors.len() {
+            return None;
+        }
+
+        let (width, height) = (self.width(), self.height());
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        unsafe {
+            let mut previous = 0;
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut previous);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.framebuffer);
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0 + index as u32);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut c_void,
+            );
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, previous as u32);
+        }
+        let image = RgbaImage::from_raw(width, height, pixels).unwrap();
+        Some(image::imageops::flip_vertical(&image))
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Buffer {
+    Texture(Texture2D),
+    Renderbuffer(u32),
+}
+
+impl Buffer {
+    fn texture(&self) -> Option<&Texture2D> {
+        match self {
+            Buffer::Texture(texture) => Some(texture),
+            Buffer::Renderbuffer(_) => None,
+        }
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if let Buffer::Renderbuffer(renderbuffer) = self {
+            unsafe {
+                gl::DeleteRenderbuffers(1, renderbuffer);
+            }
+        }
+    }
+}
+
+/// Named render targets, those built `with_window_scale` follow the window size.
+///
+/// The app resizes them together with the window, see `Context::render_targets_mut`.
+#[derive(Debug, Default)]
+pub struct RenderTargets {
+    window_size: Option<[u32; 2]>,
+    targets: HashMap<String, RenderTarget>,
+}
+
+impl RenderTargets {
+    pub fn new() -> Self {
+        RenderTargets::default()
+    }
+
+    /// Size of the last `resize`, `None` before the window exists.
+    pub fn window_size(&self) -> Option<[u32; 2]> {
+        self.window_size
+    }
+
+    /// Adds `target`, resizing it to the window first if it follows it.
+    /// Returns the target previously stored under `name`.
+    pub fn insert(
+        &mut self,
+        name: &str,
+        mut target: RenderTarget,
+    ) -> Result<Option<RenderTarget>, WhirlwingError> {
+        if let (Some(scale), Some(size)) = (target.window_scale(), self.window_size) {
+            let [width, height] = scaled(size, scale);
+            target.resize(width, height)?;
+        }
+        Ok(self.targets.insert(name.to_string(), target))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RenderTarget> {
+        self.targets.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut RenderTarget> {
+        self.targets.get_mut(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<RenderTarget> {
+        self.targets.remove(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RenderTarget)> {
+        self.targets
+            .iter()
+            .map(|(name, target)| (name.as_str(), target))
+    }
+
+    /// Resizes the targets following the window. All of them are attempted, the last
+    /// error is returned.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), WhirlwingError> {
+        self.window_size = Some([width, height]);
+        let mut result = Ok(());
+        for target in self.targets.values_mut() {
+            if let Some(scale) = target.window_scale() {
+                let [width, height] = scaled([width, height], scale);
+                if let Err(e) = target.resize(width, height) {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    /// Renders into the window again after using a target, with a viewport covering it.
+    pub fn bind_window(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            if let Some([width, height]) = self.window_size {
+                gl::Viewport(0, 0, width as i32, height as i32);
+            }
+        }
+    }
+}
+
+fn scaled([width, height]: [u32; 2], scale: f32) -> [u32; 2] {
+    let scale = |size: u32| ((size as f32 * scale).round() as u32).max(1);
+    [scale(width), scale(height)]
+}
+
+unsafe fn bound_framebuffer() -> u32 {
+    let mut framebuffer = 0;
+    gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut framebuffer);
+    framebuffer as u32
+}
+
+/// Draws into all `colors` attachments of the bound framebuffer and reads from the first.
+unsafe fn select_buffers(colors: usize) {
+    if colors == 0 {
+        // `glDrawBuffer` does not exist in OpenGL ES.
+        gl::DrawBuffers(1, &gl::NONE);
+        gl::ReadBuffer(gl::NONE);
+    } else {
+        let buffers: Vec<u32> = (0..colors as u32)
+            .map(|index| gl::COLOR_ATTACHMENT0 + index)
+            .collect();
+        gl::DrawBuffers(buffers.len() as i32, buffers.as_ptr());
+        gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+    }
+}
+
+fn status_name(status: u32) -> &'static str {
+    match status {
+        gl::FRAMEBUFFER_UNDEFINED => "undefined",
+        gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "incomplete attachment",
+        gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "missing attachment",
+        gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => "incomplete draw buffer",
+        gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => "incomplete read buffer",
+        gl::FRAMEBUFFER_UNSUPPORTED => "unsupported format combination",
+        gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "mismatched sample counts",
+        gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => "incomplete layer targets",
+        _ => "unknown status",
+    }
+}
@@ -1,239 +1,151 @@
 use std::ffi::*;
-use std::mem::{size_of, size_of_val};
-use std::ptr::null;
 
-use crate::{shader::Shader, time::Time, camera::Camera};
+use crate::{
+    assets::Assets,
+    camera::{Camera, CameraUniforms},
+    light::{Lights, LightsUniforms},
+    post_process::{bind_output, PostProcess, PostShaders},
+    render_queue::RenderQueue,
+    render_target::RenderTarget,
+    scene::{Projection, Scene},
+    shader::context_version,
+    time::Time,
+    uniform_buffer::{reserved_binding, UniformBuffer},
+};
 use glutin::display::{Display, GlDisplay};
+use wwg_error::WhirlwingError;
 
 pub(crate) struct Renderer {
-    vertex_array: u32,
-    element_buffer: u32,
-    texture1: u32,
-    texture2: u32,
-    shader: Shader,
+    camera_buffer: UniformBuffer<CameraUniforms>,
+    lights_buffer: UniformBuffer<LightsUniforms>,
+    /// `None` if they failed to load, post-processing is skipped then.
+    post_shaders: Option<PostShaders>,
+    width: u32,
+    height: u32,
 }
 
-impl Renderer {
-    pub(crate) fn new(display: &Display) -> Self {
-        gl::load_with(|symbol| {
-            let symbol = CString::new(symbol).unwrap();
-            display.get_proc_address(&symbol)
-        });
+/// Loads OpenGL function pointers, must be called with a current context before `Renderer::new`.
+pub(crate) fn load_gl(display: &Display) {
+    gl::load_with(|symbol| {
+        let symbol = CString::new(symbol).unwrap();
+        display.get_proc_address(&symbol)
+    });
+}
 
+impl Renderer {
+    /// `srgb` enables the encoding of linear colours written to sRGB framebuffers.
+    pub(crate) fn new(
+        width: u32,
+        height: u32,
+        srgb: bool,
+        assets: &mut Assets,
+    ) -> Result<Self, WhirlwingError> {
+        let (.., gles) = context_version();
         unsafe {
-            gl::Viewport(0, 0, 800, 600);
+            gl::Viewport(0, 0, width as i32, height as i32);
             gl::Enable(gl::DEPTH_TEST);
+            // OpenGL ES always encodes for sRGB framebuffers and has no switch for it.
+            if srgb && !gles {
+                gl::Enable(gl::FRAMEBUFFER_SRGB);
+            }
         }
 
-        unsafe {
-            let mut vbo = 0;
-            gl::GenBuffers(1, &mut vbo);
-
-            let vertex_shader = include_str!("../../res/shaders/vertex_shader.glsl");
-            let fragment_shader = include_str!("../../res/shaders/fragment_shader.glsl");
-            let shader = Shader::from_str(vertex_shader, fragment_shader);
-            if let Err(e) = &shader {
+        let camera_buffer = UniformBuffer::new(
+            reserved_binding("Camera").unwrap(),
+            &CameraUniforms::default(),
+        )?;
+        let lights_buffer = UniformBuffer::new(
+            reserved_binding("Lights").unwrap(),
+            &LightsUniforms::default(),
+        )?;
+
+        let post_shaders = match PostShaders::load(assets) {
+            Ok(shaders) => Some(shaders),
+            Err(e) => {
                 wwg_log::wwg_err!("{e}");
-                panic!();
-            }
-            let shader = shader.unwrap();
-
-            let mut vao = 0;
-            gl::GenVertexArrays(1, &mut vao);
-            gl::BindVertexArray(vao);
-
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                size_of_val(&VERTICES) as isize,
-                VERTICES.as_ptr() as *const c_void,
-                gl::STATIC_DRAW,
-            );
-
-            gl::VertexAttribPointer(
-                0,
-                3,
-                gl::FLOAT,
-                gl::FALSE,
-                5 * size_of::<f32>() as i32,
-                std::ptr::null(),
-            );
-            gl::EnableVertexAttribArray(0);
-
-            // gl::VertexAttribPointer(
-            //     1,
-            //     3,
-            //     gl::FLOAT,
-            //     gl::FALSE,
-            //     8 * size_of::<f32>() as i32,
-            //     (3 * size_of::<f32>()) as *const c_void,
-            // );
-            // gl::EnableVertexAttribArray(1);
-
-            gl::VertexAttribPointer(
-                2,
-                2,
-                gl::FLOAT,
-                gl::FALSE,
-                5 * size_of::<f32>() as i32,
-                (3 * size_of::<f32>()) as *const c_void,
-            );
-            gl::EnableVertexAttribArray(2);
-
-            // TEXTURES CODE STARTS HERE
-            let path = std::env::current_dir().unwrap();
-
-            let mut container_texture_path = path.clone();
-            container_texture_path.push("res/textures/container.jpg");
-            let img = image::open(container_texture_path).unwrap();
-            let width = img.width();
-            let height = img.height();
-            let pixels = img.into_rgb8().into_raw();
-
-            let mut texture1 = 0;
-            gl::GenTextures(1, &mut texture1);
-            gl::BindTexture(gl::TEXTURE_2D, texture1);
-            let border_color: [f32; 4] = [0.3, 0.2, 0.5, 1.0];
-            gl::TexParameterfv(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_BORDER_COLOR,
-                border_color.as_ptr(),
-            );
-
-            gl::TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_WRAP_S,
-                gl::CLAMP_TO_BORDER as i32,
-            );
-            gl::TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_WRAP_T,
-                gl::CLAMP_TO_BORDER as i32,
-            );
-
-            gl::TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_MIN_FILTER,
-                gl::LINEAR_MIPMAP_LINEAR as i32,
-            );
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGB as i32,
-                width as i32,
-                height as i32,
-                0,
-                gl::RGB,
-                gl::UNSIGNED_BYTE,
-                pixels.as_ptr() as *const c_void,
-            );
-            gl::GenerateMipmap(gl::TEXTURE_2D);
-
-            let mut awesomeface_path = path.clone();
-            awesomeface_path.push("res/textures/awesomeface.png");
-            let img = image::open(awesomeface_path).unwrap().flipv();
-            let width = img.width();
-            let height = img.height();
-            let pixels = img.into_rgba8().into_raw();
-
-            let mut texture2 = 0;
-            gl::GenTextures(1, &mut texture2);
-            gl::BindTexture(gl::TEXTURE_2D, texture2);
-
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-
-            gl::TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_MIN_FILTER,
-                gl::LINEAR_MIPMAP_LINEAR as i32,
-            );
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGB as i32,
-                width as i32,
-                height as i32,
-                0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                pixels.as_ptr() as *const c_void,
-            );
-
-            gl::GenerateMipmap(gl::TEXTURE_2D);
-
-            shader.bind();
-            shader.set_int("texture1", 0);
-            shader.set_int("texture2", 1);
-
-            // Element buffer
-            let mut ebo = 0;
-            gl::GenBuffers(1, &mut ebo);
-
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                size_of_val(&INDICES) as isize,
-                INDICES.as_ptr() as *const c_void,
-                gl::STATIC_DRAW,
-            );
-
-            Renderer {
-                vertex_array: vao,
-                element_buffer: ebo,
-                texture1,
-                texture2,
-                shader,
+                None
             }
-        }
+        };
+
+        Ok(Renderer {
+            camera_buffer,
+            lights_buffer,
+            post_shaders,
+            width,
+            height,
+        })
     }
 
-    pub(crate) fn resize(&self, width: i32, height: i32) {
+    pub(crate) fn resize(&mut self, width: i32, height: i32) {
+        self.width = width as u32;
+        self.height = height as u32;
         unsafe {
             gl::Viewport(0, 0, width, height);
         }
     }
 
-    pub(crate) fn redraw(&self, camera: &Camera, time: &Time) {
+    /// Draws the models of `scene` together with the items submitted to `queue`, lit by the
+    /// scene lights and `lights`, through the enabled passes of `post_process` into `target`
+    /// or the window. The active camera of `scene` places the view, `camera` does without one
+    /// and always sets the clear colour. `queue` and `lights` are emptied.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn redraw(
+        &self,
+        target: Option<&RenderTarget>,
+        scene: &Scene,
+        camera: &Camera,
+        time: &Time,
+        queue: &mut RenderQueue,
+        lights: &mut Lights,
+        post_process: &mut PostProcess,
+    ) {
+        let (width, height) = match target {
+            Some(target) => (target.width(), target.height()),
+            None => (self.width, self.height),
+        };
+        let post_shaders = self
+            .post_shaders
+            .as_ref()
+            .filter(|shaders| post_process.can_draw(shaders) && post_process.begin(width, height));
+        if post_shaders.is_none() {
+            // The game may have left another target bound while rendering the last frame.
+            bind_output(target, width, height);
+        }
+
+        let scene_view = scene.active_camera().and_then(|id| scene.camera(id));
+        let (view, projection) = match &scene_view {
+            Some((view, projection)) => (view, *projection),
+            None => (camera, Projection::default()),
+        };
         unsafe {
-            gl::ClearColor(0.2, 0.3, 0.3, 1.0);
+            let clear = camera.clear_color();
+            gl::ClearColor(clear.x, clear.y, clear.z, clear.w);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
 
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, self.texture1);
-            gl::ActiveTexture(gl::TEXTURE1);
-            gl::BindTexture(gl::TEXTURE_2D, self.texture2);
-
-            self.shader.bind();
-            use wwg_math::*;
-
             let _angle = time.now().as_secs_f32() * 1.5f32;
-            // let rot = Rotor3::from_rotation_xz(f32::to_radians(60.0)) * Rotor3::from_rotation_yz(f32::to_radians(30.0));
-            let rot = Rotor3::from_rotation_xz(f32::to_radians(30.0));
-            let model = Similarity3::new(Vec3::zero(), rot, 1.0);
 
-            // let eye = Vec3::new(0.0, 2.0, 5.0);
-            // let camera = Isometry3::new(eye, Rotor3::from_rotation_yz(f32::to_radians(-20.0)));
-            // let view = camera.into_homogeneous_matrix().inversed();
+            let projection = projection.matrix(width as f32 / height as f32);
 
-            // Projection
-            let near = 0.1f32;
-            let far = 100.0f32;
-            let angle_rad = 0.7f32;
-            let aspect = 800.0f32 / 600.0f32;
-            let projection = perspective_gl(angle_rad, aspect, near, far);
+            // Shared by every program through the `Camera` uniform block.
+            self.camera_buffer
+                .update(&CameraUniforms::new(view, projection));
 
-            self.shader.set_mat4("model", &model.into_homogeneous_matrix());
-            self.shader.set_mat4("view", &camera.view_matrix());
-            self.shader.set_mat4("projection", &projection);
-
-            gl::BindVertexArray(self.vertex_array);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.element_buffer);
-            gl::DrawElements(gl::TRIANGLES, 36, gl::UNSIGNED_INT, null());
+            for (mesh, material, model) in scene.models() {
+                queue.submit(mesh.clone(), material.clone(), model);
+            }
+        }
+        for (light, position, direction) in scene.lights() {
+            lights.submit(*light, position, direction);
+        }
+        // Shared by every program through the `Lights` uniform block.
+        self.lights_buffer.update(&lights.uniforms(view.position()));
+        lights.clear();
+        // Materials are bound every frame, a hot reload replaces the program and resets
+        // its uniforms.
+        queue.flush(&view.view_matrix());
+
+        if let Some(shaders) = post_shaders {
+            post_process.run(shaders, target, width, height);
         }
     }
 }
@@ -241,45 +153,3 @@ impl Renderer {
 impl Drop for Renderer {
     fn drop(&mut self) {}
 }
-
-#[rustfmt::skip]
-const VERTICES: [f32; 120] = [
-    -0.5, -0.5, -0.5,   0.0, 0.0,
-    -0.5,  0.5, -0.5,   0.0, 1.0,
-     0.5, -0.5, -0.5,   1.0, 0.0,
-     0.5,  0.5, -0.5,   1.0, 1.0,
-
-    -0.5, -0.5,  0.5,   0.0, 0.0,
-    -0.5,  0.5,  0.5,   0.0, 1.0,
-     0.5, -0.5,  0.5,   1.0, 0.0,
-     0.5,  0.5,  0.5,   1.0, 1.0,
-
-    -0.5, -0.5, -0.5,   0.0, 0.0,
-    -0.5,  0.5, -0.5,   0.0, 1.0,
-    -0.5, -0.5,  0.5,   1.0, 0.0,
-    -0.5,  0.5,  0.5,   1.0, 1.0,
-
-     0.5, -0.5, -0.5,   0.0, 0.0,
-     0.5,  0.5, -0.5,   0.0, 1.0,
-     0.5, -0.5,  0.5,   1.0, 0.0,
-     0.5,  0.5,  0.5,   1.0, 1.0,
-
-    -0.5, -0.5, -0.5,   0.0, 0.0,
-    -0.5, -0.5,  0.5,   0.0, 1.0,
-     0.5, -0.5, -0.5,   1.0, 0.0,
-     0.5, -0.5,  0.5,   1.0, 1.0,
-
-    -0.5, 0.5, -0.5,   0.0, 0.0,
-    -0.5, 0.5,  0.5,   0.0, 1.0,
-     0.5, 0.5, -0.5,   1.0, 0.0,
-     0.5, 0.5,  0.5,   1.0, 1.0,
-];
-
-const INDICES: [u32; 36] = [
-    0, 1, 2, 1, 2, 3,
-    4, 5, 6, 5, 6, 7,
-    8, 9, 10, 9, 10, 11,
-    12, 13, 14, 13, 14, 15,
-    16, 17, 18, 17, 18, 19,
-    20, 21, 22, 21, 22, 23,
-];
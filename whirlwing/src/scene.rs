@@ -0,0 +1,404 @@
+use std::cell::Cell;
+
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+use wwg_math::{perspective_gl, Isometry3, Mat4, Similarity3, Vec3};
+
+use crate::{
+    assets::Handle,
+    camera::{Camera, CameraUniforms},
+    light::Light,
+    material::Material,
+    mesh::Mesh,
+};
+
+/// Identifies a node of a `Scene`. Ids of removed nodes are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+/// Something drawn or used for drawing, placed by the node it is attached to.
+#[derive(Debug, Clone)]
+pub enum Attachment {
+    Model {
+        mesh: Handle<Mesh>,
+        material: Handle<Material>,
+    },
+    Camera(Projection),
+    Light(Light),
+}
+
+/// Perspective projection of a camera node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection {
+            fov_y: 0.7,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect_ratio: f32) -> Mat4 {
+        perspective_gl(self.fov_y, aspect_ratio, self.near, self.far)
+    }
+}
+
+#[derive(Debug)]
+pub struct Node {
+    name: String,
+    local: Similarity3,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    attachment: Option<Attachment>,
+    // Recomputed on read. A dirty node always has dirty descendants.
+    world: Cell<Similarity3>,
+    world_matrix: Cell<Mat4>,
+    dirty: Cell<bool>,
+}
+
+impl Node {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Transform relative to the parent, or to the world for root nodes.
+    pub fn transform(&self) -> Similarity3 {
+        self.local
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    pub fn attachment(&self) -> Option<&Attachment> {
+        self.attachment.as_ref()
+    }
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<Node>,
+}
+
+/// Tree of nodes with local transforms, the world transform of a node is the world
+/// transform of its parent followed by its own.
+///
+/// World transforms are cached. Changing a transform or a parent marks the subtree
+/// below the node dirty, and dirty nodes are recomputed the next time they are read.
+///
+/// The renderer draws the models and lights of the scene in the `Context` every frame,
+/// seen from its active camera.
+#[derive(Default)]
+pub struct Scene {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    roots: Vec<NodeId>,
+    active_camera: Option<NodeId>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Scene::default()
+    }
+
+    /// Adds a root node.
+    pub fn add_node(&mut self, name: &str, transform: Similarity3) -> NodeId {
+        let id = self.insert(name, transform, None);
+        self.roots.push(id);
+        id
+    }
+
+    pub fn add_child(
+        &mut self,
+        parent: NodeId,
+        name: &str,
+        transform: Similarity3,
+    ) -> Result<NodeId, WhirlwingError> {
+        self.get(parent)?;
+        let id = self.insert(name, transform, Some(parent));
+        self.node_mut(parent).children.push(id);
+        Ok(id)
+    }
+
+    fn insert(&mut self, name: &str, transform: Similarity3, parent: Option<NodeId>) -> NodeId {
+        let node = Node {
+            name: name.to_string(),
+            local: transform,
+            parent,
+            children: Vec::new(),
+            attachment: None,
+            world: Cell::new(Similarity3::identity()),
+            world_matrix: Cell::new(Mat4::identity()),
+            dirty: Cell::new(true),
+        };
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.node = Some(node);
+                NodeId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    node: Some(node),
+                });
+                NodeId {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    /// Removes the node together with all its descendants.
+    pub fn remove(&mut self, id: NodeId) -> Result<(), WhirlwingError> {
+        self.get(id)?;
+        self.unlink(id);
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let slot = &mut self.slots[id.index as usize];
+            let node = slot.node.take().unwrap();
+            slot.generation += 1;
+            self.free.push(id.index);
+            stack.extend(node.children);
+        }
+        Ok(())
+    }
+
+    /// Moves the node and its subtree under `parent`, or to the root with `None`.
+    /// The local transform is kept, so the node moves along with its new parent.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> Result<(), WhirlwingError> {
+        self.get(id)?;
+        if let Some(parent) = parent {
+            self.get(parent)?;
+            let mut ancestor = Some(parent);
+            while let Some(node) = ancestor {
+                if node == id {
+                    return Err(scene_error(format!(
+                        "Node `{}` can not become a child of its own descendant",
+                        self.node(id).unwrap().name
+                    )));
+                }
+                ancestor = self.node(node).unwrap().parent;
+            }
+        }
+
+        self.unlink(id);
+        self.node_mut(id).parent = parent;
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+        self.mark_dirty(id);
+        Ok(())
+    }
+
+    /// Detaches the node from its parent or from the roots.
+    fn unlink(&mut self, id: NodeId) {
+        match self.node(id).unwrap().parent {
+            Some(parent) => self.node_mut(parent).children.retain(|child| *child != id),
+            None => self.roots.retain(|root| *root != id),
+        }
+    }
+
+    pub fn set_transform(
+        &mut self,
+        id: NodeId,
+        transform: Similarity3,
+    ) -> Result<(), WhirlwingError> {
+        self.get(id)?;
+        self.node_mut(id).local = transform;
+        self.mark_dirty(id);
+        Ok(())
+    }
+
+    /// Sets a transform without scaling.
+    pub fn set_isometry(&mut self, id: NodeId, isometry: Isometry3) -> Result<(), WhirlwingError> {
+        self.set_transform(
+            id,
+            Similarity3::new(isometry.translation, isometry.rotation, 1.0),
+        )
+    }
+
+    fn mark_dirty(&self, id: NodeId) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = self.node(id).unwrap();
+            // Its descendants are dirty already.
+            if node.dirty.replace(true) {
+                continue;
+            }
+            stack.extend_from_slice(&node.children);
+        }
+    }
+
+    /// Replaces the attachment of the node, returns the previous one.
+    pub fn attach(
+        &mut self,
+        id: NodeId,
+        attachment: Attachment,
+    ) -> Result<Option<Attachment>, WhirlwingError> {
+        self.get(id)?;
+        Ok(self.node_mut(id).attachment.replace(attachment))
+    }
+
+    pub fn detach(&mut self, id: NodeId) -> Option<Attachment> {
+        self.get(id).ok()?;
+        self.node_mut(id).attachment.take()
+    }
+
+    /// `None` if the node was removed.
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        let slot = self.slots.get(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        slot.node.as_ref()
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.node(id).is_some()
+    }
+
+    /// First node with the name, in no particular order.
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.iter()
+            .find(|(_, node)| node.name == name)
+            .map(|(id, _)| id)
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let id = NodeId {
+                index: index as u32,
+                generation: slot.generation,
+            };
+            slot.node.as_ref().map(|node| (id, node))
+        })
+    }
+
+    /// Transform from the node's space to world space.
+    pub fn world_transform(&self, id: NodeId) -> Option<Similarity3> {
+        let node = self.node(id)?;
+        if node.dirty.get() {
+            let world = match node.parent {
+                Some(parent) => self.world_transform(parent).unwrap() * node.local,
+                None => node.local,
+            };
+            node.world.set(world);
+            node.world_matrix.set(world.into_homogeneous_matrix());
+            node.dirty.set(false);
+        }
+        Some(node.world.get())
+    }
+
+    pub fn world_matrix(&self, id: NodeId) -> Option<Mat4> {
+        self.world_transform(id)?;
+        Some(self.node(id)?.world_matrix.get())
+    }
+
+    /// View of a node with a camera attachment, the scale of the node is ignored.
+    pub fn camera(&self, id: NodeId) -> Option<(Camera, Projection)> {
+        let Some(Attachment::Camera(projection)) = self.node(id)?.attachment else {
+            return None;
+        };
+        let world = self.world_transform(id)?;
+        Some((Camera::new(world.translation, world.rotation), projection))
+    }
+
+    /// Camera node the scene is drawn from, `None` if it is unset or was removed.
+    pub fn active_camera(&self) -> Option<NodeId> {
+        self.active_camera.filter(|id| self.contains(*id))
+    }
+
+    /// The node needs a camera attachment while it is active. Without an active camera the
+    /// `Camera` resource places the view, with the default `Projection`.
+    pub fn set_active_camera(&mut self, id: Option<NodeId>) -> Result<(), WhirlwingError> {
+        if let Some(id) = id {
+            if !matches!(self.get(id)?.attachment, Some(Attachment::Camera(_))) {
+                return Err(scene_error(format!(
+                    "Node `{}` has no camera attachment",
+                    self.node(id).unwrap().name
+                )));
+            }
+        }
+        self.active_camera = id;
+        Ok(())
+    }
+
+    /// Contents of the `Camera` uniform block for a camera node.
+    pub fn camera_uniforms(&self, id: NodeId, aspect_ratio: f32) -> Option<CameraUniforms> {
+        let (camera, projection) = self.camera(id)?;
+        Some(CameraUniforms::new(
+            &camera,
+            projection.matrix(aspect_ratio),
+        ))
+    }
+
+    /// Every model attachment with its world matrix.
+    pub fn models(&self) -> impl Iterator<Item = (&Handle<Mesh>, &Handle<Material>, Mat4)> {
+        self.iter().filter_map(|(id, node)| match &node.attachment {
+            Some(Attachment::Model { mesh, material }) => {
+                Some((mesh, material, self.world_matrix(id).unwrap()))
+            }
+            _ => None,
+        })
+    }
+
+    /// Every light with its world position and the direction it shines in.
+    pub fn lights(&self) -> impl Iterator<Item = (&Light, Vec3, Vec3)> {
+        self.iter().filter_map(|(id, node)| match &node.attachment {
+            Some(Attachment::Light(light)) => {
+                let world = self.world_transform(id).unwrap();
+                let mut direction = Vec3::new(0.0, 0.0, -1.0);
+                world.rotation.rotate_vec(&mut direction);
+                Some((light, world.translation, direction))
+            }
+            _ => None,
+        })
+    }
+
+    fn get(&self, id: NodeId) -> Result<&Node, WhirlwingError> {
+        self.node(id)
+            .ok_or_else(|| scene_error(format!("Node {id:?} was removed")))
+    }
+
+    // Only called with ids checked by `get`.
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.slots[id.index as usize].node.as_mut().unwrap()
+    }
+}
+
+fn scene_error(content: String) -> WhirlwingError {
+    WhirlwingError::new(content, WhirlwingErrorKind::SceneFailure)
+}
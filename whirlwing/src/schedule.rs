@@ -0,0 +1,124 @@
+use crate::ecs::{Bundle, Component, Entity, World};
+
+/// Part of a frame systems run in, in declaration order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Stage {
+    /// Before `Game::update`, e.g. to turn input into movement.
+    PreUpdate,
+    /// After `Game::update`.
+    Update,
+    /// After `Update`, e.g. to copy transforms into the scene or camera.
+    PostUpdate,
+    /// After the scene is drawn and before `Game::render`.
+    Render,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 4] = [
+        Stage::PreUpdate,
+        Stage::Update,
+        Stage::PostUpdate,
+        Stage::Render,
+    ];
+}
+
+type System = Box<dyn FnMut(&World, &mut Commands)>;
+
+/// Systems sorted into stages.
+///
+/// Systems read and write components and resources through `&World`, and spawn,
+/// despawn or insert through `Commands`. Commands are applied after the last system of
+/// each stage, so later stages see their results.
+#[derive(Default)]
+pub struct Schedule {
+    stages: [Vec<System>; 4],
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Schedule::default()
+    }
+
+    /// Systems of a stage run in the order they were added.
+    pub fn add_system<F>(&mut self, stage: Stage, system: F)
+    where
+        F: FnMut(&World, &mut Commands) + 'static,
+    {
+        self.stages[stage as usize].push(Box::new(system));
+    }
+
+    pub fn run(&mut self, world: &mut World) {
+        for stage in Stage::ALL {
+            self.run_stage(stage, world);
+        }
+    }
+
+    pub fn run_stage(&mut self, stage: Stage, world: &mut World) {
+        let mut queue = Vec::new();
+        for system in &mut self.stages[stage as usize] {
+            let mut commands = Commands::new(world);
+            system(world, &mut commands);
+            queue.append(&mut commands.queue);
+        }
+        for command in queue {
+            command(world);
+        }
+    }
+}
+
+type Command = Box<dyn FnOnce(&mut World)>;
+
+/// Changes to the world deferred until the end of the stage.
+pub struct Commands<'w> {
+    world: &'w World,
+    queue: Vec<Command>,
+}
+
+impl<'w> Commands<'w> {
+    fn new(world: &'w World) -> Self {
+        Commands {
+            world,
+            queue: Vec::new(),
+        }
+    }
+
+    /// The entity exists right away, its components are inserted at the end of the stage.
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let entity = self.world.reserve();
+        self.insert(entity, bundle);
+        entity
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        self.add(move |world| {
+            if let Err(e) = world.despawn(entity) {
+                wwg_log::wwg_warn!("{e}");
+            }
+        });
+    }
+
+    pub fn insert<B: Bundle>(&mut self, entity: Entity, bundle: B) {
+        self.add(move |world| {
+            if let Err(e) = world.insert(entity, bundle) {
+                wwg_log::wwg_warn!("{e}");
+            }
+        });
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: Entity) {
+        self.add(move |world| {
+            world.remove::<T>(entity);
+        });
+    }
+
+    pub fn insert_resource<R: 'static>(&mut self, resource: R) {
+        self.add(move |world| {
+            world.insert_resource(resource);
+        });
+    }
+
+    /// Runs any change to the world at the end of the stage.
+    pub fn add<F: FnOnce(&mut World) + 'static>(&mut self, command: F) {
+        self.queue.push(Box::new(command));
+    }
+}
@@ -1,333 +1,867 @@
-#[allow(unused_imports)]
-use std::ptr::{null, null_mut};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::path::{Path, PathBuf};
 
 use wwg_error::{WhirlwingError, WhirlwingErrorKind};
 use wwg_math::Mat4;
 
+use crate::preprocessor::{Preprocessor, ProcessedSource};
+use crate::program_cache::ProgramCache;
+use crate::shader_error::ShaderError;
 #[cfg(debug_assertions)]
-use std::str::from_utf8_unchecked;
+use crate::uniform::glsl_type_name;
+use crate::uniform::{Uniform, UniformElement};
+use crate::uniform_buffer::reserved_binding;
+
+/// Linked shader program, see `ShaderProgramBuilder` for stages besides vertex and fragment.
+///
+/// Sources go through the `Preprocessor` first, so they may use `#include`.
+#[derive(Debug)]
+pub struct Shader {
+    program_id: Cell<u32>,
+    // Replaced together with `program_id` on reload.
+    reflection: RefCell<Reflection>,
+    // Set through `bind_uniform_block`, applied again on reload.
+    block_bindings: RefCell<HashMap<String, u32>>,
+    // Set for shaders created from files, used to reload them.
+    files: Option<ShaderFiles>,
+}
 
-pub(crate) struct Shader {
-    program_id: u32,
+/// Active uniform or vertex attribute of a linked program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveVariable {
+    /// Arrays are named without the `[0]` suffix OpenGL reports for them.
+    pub name: String,
+    pub location: i32,
+    /// GLSL type as an OpenGL enum, e.g. `gl::FLOAT_VEC3`.
+    pub gl_type: u32,
+    /// Number of array elements, 1 for variables which are not arrays.
+    pub size: i32,
 }
 
-#[allow(dead_code)]
-impl Shader {
-    pub fn new(
-        vertex_shader_path: &str,
-        fragment_shader_path: &str,
-    ) -> Result<Shader, WhirlwingError> {
-        unsafe {
-            let mut vertex_shader_vector;
-            match std::fs::read(vertex_shader_path) {
-                Ok(vec) => {
-                    vertex_shader_vector = vec;
-                    vertex_shader_vector.push(b'\0');
-                }
-                Err(error) => {
-                    let error_content = format!("Failed to read from path: {vertex_shader_path}");
-                    return Err(WhirlwingError::new_with_source(
-                        error_content,
-                        WhirlwingErrorKind::ShaderCompilationFailure,
-                        Box::new(error),
-                    ));
+/// Active uniform block of a linked program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniformBlock {
+    pub name: String,
+    pub index: u32,
+    pub binding: u32,
+    /// Minimum size of a buffer backing the block, in bytes.
+    pub data_size: usize,
+}
+
+#[derive(Debug, Default)]
+struct Reflection {
+    uniforms: HashMap<String, ActiveVariable>,
+    attributes: Vec<ActiveVariable>,
+    blocks: Vec<UniformBlock>,
+}
+
+impl Reflection {
+    /// Uniforms inside uniform blocks have no location and are left out.
+    unsafe fn of(program_id: u32) -> Reflection {
+        let uniforms = Reflection::active_variables(
+            program_id,
+            gl::ACTIVE_UNIFORMS,
+            gl::ACTIVE_UNIFORM_MAX_LENGTH,
+            gl::GetActiveUniform,
+            gl::GetUniformLocation,
+        )
+        .into_iter()
+        .filter(|uniform| uniform.location >= 0)
+        .map(|uniform| (uniform.name.clone(), uniform))
+        .collect();
+
+        let mut attributes = Reflection::active_variables(
+            program_id,
+            gl::ACTIVE_ATTRIBUTES,
+            gl::ACTIVE_ATTRIBUTE_MAX_LENGTH,
+            gl::GetActiveAttrib,
+            gl::GetAttribLocation,
+        );
+        attributes.sort_by_key(|attribute| attribute.location);
+
+        Reflection {
+            uniforms,
+            attributes,
+            blocks: Reflection::uniform_blocks(program_id),
+        }
+    }
+
+    unsafe fn uniform_blocks(program_id: u32) -> Vec<UniformBlock> {
+        let mut count = 0;
+        let mut max_length = 0;
+        gl::GetProgramiv(program_id, gl::ACTIVE_UNIFORM_BLOCKS, &mut count);
+        gl::GetProgramiv(
+            program_id,
+            gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH,
+            &mut max_length,
+        );
+
+        let mut buffer = vec![0u8; max_length.max(1) as usize];
+        (0..count as u32)
+            .map(|index| {
+                let mut length = 0;
+                gl::GetActiveUniformBlockName(
+                    program_id,
+                    index,
+                    buffer.len() as i32,
+                    &mut length,
+                    buffer.as_mut_ptr() as *mut i8,
+                );
+                let mut binding = 0;
+                let mut data_size = 0;
+                gl::GetActiveUniformBlockiv(
+                    program_id,
+                    index,
+                    gl::UNIFORM_BLOCK_BINDING,
+                    &mut binding,
+                );
+                gl::GetActiveUniformBlockiv(
+                    program_id,
+                    index,
+                    gl::UNIFORM_BLOCK_DATA_SIZE,
+                    &mut data_size,
+                );
+                UniformBlock {
+                    name: String::from_utf8_lossy(&buffer[..length as usize]).into_owned(),
+                    index,
+                    binding: binding as u32,
+                    data_size: data_size as usize,
                 }
+            })
+            .collect()
+    }
+
+    /// Applies the reserved bindings and then `custom_bindings` by block name.
+    unsafe fn bind_blocks(&mut self, program_id: u32, custom_bindings: &HashMap<String, u32>) {
+        for block in &mut self.blocks {
+            let binding = custom_bindings
+                .get(&block.name)
+                .copied()
+                .or_else(|| reserved_binding(&block.name));
+            if let Some(binding) = binding {
+                gl::UniformBlockBinding(program_id, block.index, binding);
+                block.binding = binding;
             }
-            let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
-            gl::ShaderSource(
-                vertex_shader,
-                1,
-                &(vertex_shader_vector.as_ptr() as *const i8),
-                null(),
-            );
-            gl::CompileShader(vertex_shader);
-
-            #[cfg(debug_assertions)]
-            {
-                let error_message =
-                    format!("Vertex Shader Compilation Error: {vertex_shader_path}");
-                Shader::check_shader_compilation(vertex_shader, &error_message)?;
-            }
+        }
+    }
 
-            let mut fragment_shader_vector;
-            match std::fs::read(fragment_shader_path) {
-                Ok(vec) => {
-                    fragment_shader_vector = vec;
-                    fragment_shader_vector.push(b'\0');
+    unsafe fn active_variables(
+        program_id: u32,
+        count_name: u32,
+        max_length_name: u32,
+        get_active: unsafe fn(u32, u32, i32, *mut i32, *mut i32, *mut u32, *mut i8),
+        get_location: unsafe fn(u32, *const i8) -> i32,
+    ) -> Vec<ActiveVariable> {
+        let mut count = 0;
+        let mut max_length = 0;
+        gl::GetProgramiv(program_id, count_name, &mut count);
+        gl::GetProgramiv(program_id, max_length_name, &mut max_length);
+
+        let mut buffer = vec![0u8; max_length.max(1) as usize];
+        (0..count as u32)
+            .map(|index| {
+                let mut length = 0;
+                let mut size = 0;
+                let mut gl_type = 0;
+                get_active(
+                    program_id,
+                    index,
+                    buffer.len() as i32,
+                    &mut length,
+                    &mut size,
+                    &mut gl_type,
+                    buffer.as_mut_ptr() as *mut i8,
+                );
+                // Still NUL terminated for the location query.
+                let location = get_location(program_id, buffer.as_ptr() as *const i8);
+                let name = String::from_utf8_lossy(&buffer[..length as usize]);
+                ActiveVariable {
+                    name: name.strip_suffix("[0]").unwrap_or(&name).to_string(),
+                    location,
+                    gl_type,
+                    size,
                 }
-                Err(error) => {
-                    let error_content = format!("Failed to read from path: {fragment_shader_path}");
-                    return Err(WhirlwingError::new_with_source(
-                        error_content,
-                        WhirlwingErrorKind::ShaderCompilationFailure,
-                        Box::new(error),
-                    ));
-                }
-            }
-            let fragment_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
-            gl::ShaderSource(
-                fragment_shader,
-                1,
-                &(fragment_shader_vector.as_ptr() as *const i8),
-                null(),
-            );
-            gl::CompileShader(fragment_shader);
-
-            #[cfg(debug_assertions)]
-            {
-                let error_message =
-                    format!("Fragment Shader Compilation Error: {fragment_shader_path}");
-                Shader::check_shader_compilation(fragment_shader, &error_message)?;
-            }
+            })
+            .collect()
+    }
+}
 
-            let program_id = gl::CreateProgram();
-            gl::AttachShader(program_id, vertex_shader);
-            gl::AttachShader(program_id, fragment_shader);
-            gl::LinkProgram(program_id);
+/// Programmable stage of a shader program, ordered as in the pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    TessControl,
+    TessEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+}
 
-            #[cfg(debug_assertions)]
-            {
-                let mut success = 0;
-                let mut buffer = [0i8; 512];
-                gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
-                if success == 0 {
-                    gl::GetProgramInfoLog(program_id, 512, null_mut(), &mut buffer[0]);
-                    let string = from_utf8_unchecked(&*(buffer.as_ptr() as *const [u8; 512]));
-                    let string = format!(
-                        "Shader Program Linking Failed:\n
-                                          Vertex Shader: {vertex_shader_path}\n
-                                          Fragment Shader: {fragment_shader_path}\n
-                                          OpenGL Error: {string}"
-                    );
-                    return Err(WhirlwingError::new(
-                        string.to_string(),
-                        WhirlwingErrorKind::ShaderCompilationFailure,
-                    ));
-                }
-            }
+impl ShaderStage {
+    pub fn gl_enum(self) -> u32 {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::TessControl => gl::TESS_CONTROL_SHADER,
+            ShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+            ShaderStage::Compute => gl::COMPUTE_SHADER,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShaderStage::Vertex => "Vertex Shader",
+            ShaderStage::TessControl => "Tessellation Control Shader",
+            ShaderStage::TessEvaluation => "Tessellation Evaluation Shader",
+            ShaderStage::Geometry => "Geometry Shader",
+            ShaderStage::Fragment => "Fragment Shader",
+            ShaderStage::Compute => "Compute Shader",
+        }
+    }
+
+    /// Oldest OpenGL and OpenGL ES versions supporting the stage.
+    pub fn min_version(self) -> ([u8; 2], [u8; 2]) {
+        match self {
+            ShaderStage::Vertex | ShaderStage::Fragment => ([2, 0], [2, 0]),
+            ShaderStage::Geometry => ([3, 2], [3, 2]),
+            ShaderStage::TessControl | ShaderStage::TessEvaluation => ([4, 0], [3, 2]),
+            ShaderStage::Compute => ([4, 3], [3, 1]),
+        }
+    }
 
-            gl::DeleteShader(vertex_shader);
-            gl::DeleteShader(fragment_shader);
+    // Names sources passed as strings in error messages.
+    fn source_name(self) -> &'static str {
+        match self {
+            ShaderStage::Vertex => "<vertex shader>",
+            ShaderStage::TessControl => "<tessellation control shader>",
+            ShaderStage::TessEvaluation => "<tessellation evaluation shader>",
+            ShaderStage::Geometry => "<geometry shader>",
+            ShaderStage::Fragment => "<fragment shader>",
+            ShaderStage::Compute => "<compute shader>",
+        }
+    }
 
-            Ok(Shader { program_id })
+    /// Fails if the current context is older than `min_version`.
+    pub fn check_support(self) -> Result<(), WhirlwingError> {
+        let (major, minor, gles) = context_version();
+        let (gl_version, gles_version) = self.min_version();
+        let ([required_major, required_minor], api) = if gles {
+            (gles_version, "OpenGL ES")
+        } else {
+            (gl_version, "OpenGL")
+        };
+        if (major, minor) >= (required_major, required_minor) {
+            return Ok(());
         }
+        Err(WhirlwingError::new(
+            format!(
+                "{} needs {api} {required_major}.{required_minor}, the context is {api} {major}.{minor}",
+                self.name()
+            ),
+            WhirlwingErrorKind::UnsupportedFeature,
+        ))
     }
+}
 
-    /// Slices should have b'\0' at the end.
+/// Major and minor version of the current context, and whether it is OpenGL ES.
+pub(crate) fn context_version() -> (u8, u8, bool) {
+    unsafe {
+        let mut major = 0;
+        let mut minor = 0;
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        let version = gl::GetString(gl::VERSION);
+        let gles = !version.is_null()
+            && CStr::from_ptr(version as *const _)
+                .to_bytes()
+                .starts_with(b"OpenGL ES");
+        (major as u8, minor as u8, gles)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum StageSource {
+    File(PathBuf),
+    Str(String),
+}
+
+/// Builds a `Shader` from any combination of vertex, tessellation, geometry and fragment stages.
+///
+/// A vertex stage is required and a tessellation control stage needs an evaluation stage.
+/// `build` fails for stages the context version does not support.
+/// Compute shaders are built with `ComputeProgram` instead.
+///
+/// ```no_run
+/// use whirlwing::shader::{ShaderProgramBuilder, ShaderStage};
+///
+/// # fn example() -> Result<(), whirlwing::error::WhirlwingError> {
+/// let shader = ShaderProgramBuilder::new()
+///     .with_file(ShaderStage::Vertex, "res/shaders/points.vert")
+///     .with_file(ShaderStage::Geometry, "res/shaders/points_to_quads.geom")
+///     .with_file(ShaderStage::Fragment, "res/shaders/points.frag")
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ShaderProgramBuilder {
+    // Kept in pipeline order.
+    stages: Vec<(ShaderStage, StageSource)>,
+    preprocessor: Preprocessor,
+    cache: Option<ProgramCache>,
+}
+
+impl ShaderProgramBuilder {
+    pub fn new() -> Self {
+        ShaderProgramBuilder::default()
+    }
+
+    /// Setting a stage again replaces its source.
+    pub fn with_file<P: Into<PathBuf>>(self, stage: ShaderStage, path: P) -> Self {
+        self.with_stage(stage, StageSource::File(path.into()))
+    }
+
+    /// Setting a stage again replaces its source.
+    pub fn with_source(self, stage: ShaderStage, source: &str) -> Self {
+        self.with_stage(stage, StageSource::Str(source.to_string()))
+    }
+
+    /// Includes are resolved relative to the working directory if not set.
+    pub fn with_preprocessor(mut self, preprocessor: Preprocessor) -> Self {
+        self.preprocessor = preprocessor;
+        self
+    }
+
+    /// Reuses the program binary stored in `cache` if there is one.
+    pub fn with_program_cache(mut self, cache: ProgramCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn build(&self) -> Result<Shader, WhirlwingError> {
+        let has = |stage| self.stages.iter().any(|(other, _)| *other == stage);
+        let message = if has(ShaderStage::Compute) {
+            Some("Compute shaders are built with `ComputeProgram`")
+        } else if !has(ShaderStage::Vertex) {
+            Some("A shader program needs a vertex shader")
+        } else if has(ShaderStage::TessControl) && !has(ShaderStage::TessEvaluation) {
+            Some("A tessellation control shader needs a tessellation evaluation shader")
+        } else {
+            None
+        };
+        if let Some(message) = message {
+            return Err(WhirlwingError::new(
+                message.to_string(),
+                WhirlwingErrorKind::ShaderCompilationFailure,
+            ));
+        }
+        self.link()
+    }
+
+    fn with_stage(mut self, stage: ShaderStage, source: StageSource) -> Self {
+        self.stages.retain(|(other, _)| *other != stage);
+        self.stages.push((stage, source));
+        self.stages.sort_by_key(|(stage, _)| *stage);
+        self
+    }
+
+    /// Builds the program without checking that the stages form a pipeline.
+    pub(crate) fn link(&self) -> Result<Shader, WhirlwingError> {
+        for (stage, _) in &self.stages {
+            stage.check_support()?;
+        }
+        let stages = self
+            .stages
+            .iter()
+            .map(|(stage, source)| {
+                let processed = match source {
+                    StageSource::File(path) => self.preprocessor.process_file(path)?,
+                    StageSource::Str(source) => {
+                        self.preprocessor.process_str(source, stage.source_name())?
+                    }
+                };
+                Ok((*stage, processed))
+            })
+            .collect::<Result<Vec<_>, WhirlwingError>>()?;
+
+        let program_id = Shader::compile_program(&stages, self.cache.as_ref())?;
+        let mut reflection = unsafe { Reflection::of(program_id) };
+        unsafe { reflection.bind_blocks(program_id, &HashMap::new()) };
+
+        let files = self
+            .stages
+            .iter()
+            .map(|(stage, source)| match source {
+                StageSource::File(path) => Some((*stage, path.clone())),
+                StageSource::Str(_) => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|paths| ShaderFiles {
+                paths,
+                builder: self.clone(),
+                includes: RefCell::new(
+                    stages
+                        .iter()
+                        .flat_map(|(_, source)| source.includes().iter().cloned())
+                        .collect(),
+                ),
+            });
+        Ok(Shader {
+            program_id: Cell::new(program_id),
+            reflection: RefCell::new(reflection),
+            block_bindings: RefCell::default(),
+            files,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct ShaderFiles {
+    paths: Vec<(ShaderStage, PathBuf)>,
+    // Builds the shader again on reload.
+    builder: ShaderProgramBuilder,
+    // Updated on reload, the sources may include different files by then.
+    includes: RefCell<Vec<PathBuf>>,
+}
+
+impl Shader {
+    /// Includes are resolved relative to the working directory, see `from_files`.
+    pub fn new(
+        vertex_shader_path: &str,
+        fragment_shader_path: &str,
+    ) -> Result<Shader, WhirlwingError> {
+        Shader::from_files(
+            vertex_shader_path,
+            fragment_shader_path,
+            &Preprocessor::default(),
+        )
+    }
+
+    pub fn from_files<P: AsRef<Path>>(
+        vertex_shader_path: P,
+        fragment_shader_path: P,
+        preprocessor: &Preprocessor,
+    ) -> Result<Shader, WhirlwingError> {
+        ShaderProgramBuilder::new()
+            .with_file(ShaderStage::Vertex, vertex_shader_path.as_ref())
+            .with_file(ShaderStage::Fragment, fragment_shader_path.as_ref())
+            .with_preprocessor(preprocessor.clone())
+            .build()
+    }
+
+    /// Like `from_files`, but reuses the program binary stored in `cache` if there is one.
+    pub fn from_files_cached<P: AsRef<Path>>(
+        vertex_shader_path: P,
+        fragment_shader_path: P,
+        preprocessor: &Preprocessor,
+        cache: &ProgramCache,
+    ) -> Result<Shader, WhirlwingError> {
+        ShaderProgramBuilder::new()
+            .with_file(ShaderStage::Vertex, vertex_shader_path.as_ref())
+            .with_file(ShaderStage::Fragment, fragment_shader_path.as_ref())
+            .with_preprocessor(preprocessor.clone())
+            .with_program_cache(cache.clone())
+            .build()
+    }
+
+    /// Slices may have b'\0' at the end.
     pub fn from_utf8_slices(
         vertex_shader_slice: &[u8],
         fragment_shader_slice: &[u8],
     ) -> Result<Shader, WhirlwingError> {
+        let vertex_shader = String::from_utf8_lossy(vertex_shader_slice);
+        let fragment_shader = String::from_utf8_lossy(fragment_shader_slice);
+        Shader::from_str(
+            vertex_shader.trim_end_matches('\0'),
+            fragment_shader.trim_end_matches('\0'),
+        )
+    }
+
+    /// Includes are resolved relative to the working directory.
+    pub fn from_str(
+        vertex_shader_str: &str,
+        fragment_shader_str: &str,
+    ) -> Result<Shader, WhirlwingError> {
+        ShaderProgramBuilder::new()
+            .with_source(ShaderStage::Vertex, vertex_shader_str)
+            .with_source(ShaderStage::Fragment, fragment_shader_str)
+            .build()
+    }
+
+    /// Like `from_str`, but reuses the program binary stored in `cache` if there is one.
+    pub fn from_str_cached(
+        vertex_shader_str: &str,
+        fragment_shader_str: &str,
+        cache: &ProgramCache,
+    ) -> Result<Shader, WhirlwingError> {
+        ShaderProgramBuilder::new()
+            .with_source(ShaderStage::Vertex, vertex_shader_str)
+            .with_source(ShaderStage::Fragment, fragment_shader_str)
+            .with_program_cache(cache.clone())
+            .build()
+    }
+
+    fn compile_program(
+        stages: &[(ShaderStage, ProcessedSource)],
+        cache: Option<&ProgramCache>,
+    ) -> Result<u32, WhirlwingError> {
+        let cache = cache.filter(|_| ProgramCache::is_supported()).map(|cache| {
+            let sources: Vec<_> = stages
+                .iter()
+                .map(|(stage, source)| (stage.gl_enum(), source.source()))
+                .collect();
+            (cache, cache.key(&sources))
+        });
+        if let Some(program_id) = cache.and_then(|(cache, key)| cache.load(key)) {
+            return Ok(program_id);
+        }
+
         unsafe {
-            let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
-            gl::ShaderSource(
-                vertex_shader,
-                1,
-                &(vertex_shader_slice.as_ptr() as *const i8),
-                null(),
-            );
-            gl::CompileShader(vertex_shader);
-
-            #[cfg(debug_assertions)]
-            {
-                let error_message = format!("Vertex Shader Compilation Error: Shader was compiled from slice{vertex_shader_slice:?}");
-                Shader::check_shader_compilation(vertex_shader, &error_message)?;
-            }
+            let mut shader_ids = Vec::with_capacity(stages.len());
+            for (stage, source) in stages {
+                let shader_id = Shader::compile_shader(stage.gl_enum(), source);
+                shader_ids.push(shader_id);
 
-            let fragment_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
-            gl::ShaderSource(
-                fragment_shader,
-                1,
-                &(fragment_shader_slice.as_ptr() as *const i8),
-                null(),
-            );
-            gl::CompileShader(fragment_shader);
-
-            #[cfg(debug_assertions)]
-            {
-                let error_message = format!("Fragment Shader Compilation Error: Shader was compiled from slice{fragment_shader_slice:?}");
-                Shader::check_shader_compilation(fragment_shader, &error_message)?;
+                let mut success = 0;
+                gl::GetShaderiv(shader_id, gl::COMPILE_STATUS, &mut success);
+                if success == 0 {
+                    let log = info_log(shader_id, gl::GetShaderiv, gl::GetShaderInfoLog);
+                    for shader_id in shader_ids {
+                        gl::DeleteShader(shader_id);
+                    }
+                    return Err(ShaderError::compilation(*stage, source, &log).into_error());
+                }
             }
 
             let program_id = gl::CreateProgram();
-            gl::AttachShader(program_id, vertex_shader);
-            gl::AttachShader(program_id, fragment_shader);
+            for shader_id in &shader_ids {
+                gl::AttachShader(program_id, *shader_id);
+            }
+            if cache.is_some() {
+                ProgramCache::prepare(program_id);
+            }
             gl::LinkProgram(program_id);
 
-            #[cfg(debug_assertions)]
-            {
-                let mut success = 0;
-                let mut buffer = [0i8; 512];
-                gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
-                if success == 0 {
-                    gl::GetProgramInfoLog(program_id, 512, null_mut(), &mut buffer[0]);
-                    let string = from_utf8_unchecked(&*(buffer.as_ptr() as *const [u8; 512]));
-                    let string = format!(
-                        "Shader Program Linking Failed:\n
-                                          Vertex Shader: {vertex_shader_slice:?}\n
-                                          Fragment Shader: {fragment_shader_slice:?}\n
-                                          OpenGL Error: {string}"
-                    );
-                    return Err(WhirlwingError::new(
-                        string.to_string(),
-                        WhirlwingErrorKind::ShaderCompilationFailure,
-                    ));
-                }
+            for shader_id in shader_ids {
+                gl::DeleteShader(shader_id);
+            }
+
+            let mut success = 0;
+            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
+            if success == 0 {
+                let log = info_log(program_id, gl::GetProgramiv, gl::GetProgramInfoLog);
+                gl::DeleteProgram(program_id);
+                let sources = stages
+                    .iter()
+                    .map(|(_, source)| source.name().to_path_buf())
+                    .collect();
+                return Err(ShaderError::link(sources, &log).into_error());
             }
-            gl::DeleteShader(vertex_shader);
-            gl::DeleteShader(fragment_shader);
 
-            Ok(Shader { program_id })
+            if let Some((cache, key)) = cache {
+                cache.store(key, program_id);
+            }
+            Ok(program_id)
         }
     }
 
-    pub fn from_str(
-        vertex_shader_str: &str,
-        fragment_shader_str: &str,
-    ) -> Result<Shader, WhirlwingError> {
-        unsafe {
-            let mut vertex_shader_str = vertex_shader_str.to_string();
-            vertex_shader_str.push('\0');
-
-            let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
-            gl::ShaderSource(
-                vertex_shader,
-                1,
-                &(vertex_shader_str.as_ptr() as *const i8),
-                null(),
-            );
-            gl::CompileShader(vertex_shader);
-
-            #[cfg(debug_assertions)]
-            {
-                let error_message = format!("Vertex Shader Compilation Error: Shader was compiled from string:\n{vertex_shader_str:?}");
-                Shader::check_shader_compilation(vertex_shader, &error_message)?;
-            }
+    unsafe fn compile_shader(kind: u32, source: &ProcessedSource) -> u32 {
+        let shader = gl::CreateShader(kind);
+        let length = source.source().len() as i32;
+        gl::ShaderSource(shader, 1, &(source.source().as_ptr() as *const i8), &length);
+        gl::CompileShader(shader);
+        shader
+    }
 
-            let mut fragment_shader_str = fragment_shader_str.to_string();
-            fragment_shader_str.push('\0');
-
-            let fragment_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
-            gl::ShaderSource(
-                fragment_shader,
-                1,
-                &(fragment_shader_str.as_ptr() as *const i8),
-                null(),
-            );
-            gl::CompileShader(fragment_shader);
-
-            #[cfg(debug_assertions)]
-            {
-                let error_message = format!("Fragment Shader Compilation Error: Shader was compiled from string:\n{fragment_shader_str:?}");
-                Shader::check_shader_compilation(fragment_shader, &error_message)?;
+    /// Sets a uniform of the bound program from any `Uniform` value.
+    ///
+    /// Slices, arrays and `Vec`s fill consecutive array elements, starting at the first one
+    /// or at the element named, e.g. `lights[2]`.
+    ///
+    /// ```no_run
+    /// # use whirlwing::{math::{Mat4, Vec3}, shader::Shader};
+    /// # fn example(shader: &Shader) -> Result<(), whirlwing::error::WhirlwingError> {
+    /// shader.bind();
+    /// shader.set("model", &Mat4::identity())?;
+    /// shader.set("light_colors", &[Vec3::one(), Vec3::zero()])?;
+    /// shader.set("albedo", &0)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set<T: Uniform + ?Sized>(&self, name: &str, value: &T) -> Result<(), WhirlwingError> {
+        let elements = value.elements();
+        let location = self.uniform_location(
+            name,
+            T::Element::GL_TYPES,
+            T::Element::GLSL_TYPE,
+            elements.len(),
+        )?;
+        if !elements.is_empty() {
+            unsafe {
+                T::Element::upload(location, elements);
             }
+        }
+        Ok(())
+    }
 
-            let program_id = gl::CreateProgram();
-            gl::AttachShader(program_id, vertex_shader);
-            gl::AttachShader(program_id, fragment_shader);
-            gl::LinkProgram(program_id);
+    pub fn set_bool(&self, name: &str, uniform: bool) -> Result<(), WhirlwingError> {
+        self.set(name, &uniform)
+    }
 
-            #[cfg(debug_assertions)]
-            {
-                let mut success = 0;
-                let mut buffer = [0i8; 512];
-                gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
-                if success == 0 {
-                    gl::GetProgramInfoLog(program_id, 512, null_mut(), &mut buffer[0]);
-                    let string = from_utf8_unchecked(&*(buffer.as_ptr() as *const [u8; 512]));
-                    let string = format!(
-                        "Shader Program Linking Failed:\n
-                                          Vertex Shader:\n{vertex_shader_str}\n
-                                          Fragment Shader:\n{fragment_shader_str}\n
-                                          OpenGL Error: {string}"
-                    );
-                    return Err(WhirlwingError::new(
-                        string.to_string(),
-                        WhirlwingErrorKind::ShaderCompilationFailure,
-                    ));
-                }
-            }
+    /// Also sets samplers, `uniform` is the texture unit then.
+    pub fn set_int(&self, name: &str, uniform: i32) -> Result<(), WhirlwingError> {
+        self.set(name, &uniform)
+    }
 
-            gl::DeleteShader(vertex_shader);
-            gl::DeleteShader(fragment_shader);
+    pub fn set_float(&self, name: &str, uniform: f32) -> Result<(), WhirlwingError> {
+        self.set(name, &uniform)
+    }
+
+    pub fn set_mat4(&self, name: &str, uniform: &Mat4) -> Result<(), WhirlwingError> {
+        self.set(name, uniform)
+    }
 
-            Ok(Shader { program_id })
+    /// Active uniform by name, single array elements like `lights[2]` are found as well.
+    pub fn uniform(&self, name: &str) -> Option<ActiveVariable> {
+        let reflection = self.reflection.borrow();
+        if let Some(uniform) = reflection.uniforms.get(name) {
+            return Some(uniform.clone());
         }
+
+        let (array, index) = array_element(name)?;
+        let uniform = reflection.uniforms.get(array)?;
+        (index < uniform.size).then(|| ActiveVariable {
+            name: name.to_string(),
+            location: uniform.location + index,
+            gl_type: uniform.gl_type,
+            size: uniform.size - index,
+        })
     }
 
-    pub fn set_bool(&self, name: &str, uniform: bool) {
-        unsafe {
-            let mut name = name.to_string();
-            name.push('\0');
-            let location = gl::GetUniformLocation(self.program_id, name.as_ptr() as *const i8);
-            gl::Uniform1i(location, uniform as i32);
+    /// Whether `uniform` finds `name`, without copying the variable.
+    pub fn has_uniform(&self, name: &str) -> bool {
+        let reflection = self.reflection.borrow();
+        if reflection.uniforms.contains_key(name) {
+            return true;
         }
+        array_element(name).is_some_and(|(array, index)| {
+            let uniform = reflection.uniforms.get(array);
+            uniform.is_some_and(|uniform| index < uniform.size)
+        })
     }
 
-    pub fn set_int(&self, name: &str, uniform: i32) {
+    /// Active uniforms outside of uniform blocks, in no particular order.
+    pub fn uniforms(&self) -> Vec<ActiveVariable> {
+        self.reflection
+            .borrow()
+            .uniforms
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Active uniform blocks, blocks named in `RESERVED_BLOCKS` are already bound.
+    pub fn uniform_blocks(&self) -> Vec<UniformBlock> {
+        self.reflection.borrow().blocks.clone()
+    }
+
+    pub fn uniform_block(&self, name: &str) -> Option<UniformBlock> {
+        self.reflection
+            .borrow()
+            .blocks
+            .iter()
+            .find(|block| block.name == name)
+            .cloned()
+    }
+
+    /// Binds a uniform block to a binding point, so it reads the `UniformBuffer` bound there.
+    /// The binding is kept across hot reloads.
+    pub fn bind_uniform_block(&self, name: &str, binding: u32) -> Result<(), WhirlwingError> {
+        if self.uniform_block(name).is_none() {
+            return Err(WhirlwingError::new(
+                format!(
+                    "Uniform block `{name}` is not active in shader program {}",
+                    self.program_id.get()
+                ),
+                WhirlwingErrorKind::UniformFailure,
+            ));
+        }
+        self.block_bindings
+            .borrow_mut()
+            .insert(name.to_string(), binding);
         unsafe {
-            let mut name = name.to_string();
-            name.push('\0');
-            let location = gl::GetUniformLocation(self.program_id, name.as_ptr() as *const i8);
-            gl::Uniform1i(location, uniform);
+            self.reflection
+                .borrow_mut()
+                .bind_blocks(self.program_id.get(), &self.block_bindings.borrow());
         }
+        Ok(())
     }
 
-    pub fn set_float(&self, name: &str, uniform: f32) {
-        unsafe {
-            let mut name = name.to_string();
-            name.push('\0');
-            let location = gl::GetUniformLocation(self.program_id, name.as_ptr() as *const i8);
-            gl::Uniform1f(location, uniform);
+    /// Active vertex attributes sorted by location.
+    pub fn attributes(&self) -> Vec<ActiveVariable> {
+        self.reflection.borrow().attributes.clone()
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<ActiveVariable> {
+        let reflection = self.reflection.borrow();
+        let attribute = reflection
+            .attributes
+            .iter()
+            .find(|attribute| attribute.name == name);
+        attribute.cloned()
+    }
+
+    /// Whether `attribute` finds `name`, without copying the variable.
+    pub fn has_attribute(&self, name: &str) -> bool {
+        let reflection = self.reflection.borrow();
+        reflection
+            .attributes
+            .iter()
+            .any(|attribute| attribute.name == name)
+    }
+
+    /// Unknown names, type mismatches and writes past the end of an array are errors in
+    /// debug builds. Release builds skip the checks and return location -1 for unknown names,
+    /// which OpenGL ignores.
+    fn uniform_location(
+        &self,
+        name: &str,
+        gl_types: &[u32],
+        glsl_type: &str,
+        count: usize,
+    ) -> Result<i32, WhirlwingError> {
+        let reflection = self.reflection.borrow();
+        // Plain names avoid building an `ActiveVariable` every frame.
+        let uniform = match reflection.uniforms.get(name) {
+            Some(uniform) => Some((uniform.location, uniform.gl_type, uniform.size)),
+            None => self
+                .uniform(name)
+                .map(|uniform| (uniform.location, uniform.gl_type, uniform.size)),
+        };
+
+        #[cfg(debug_assertions)]
+        {
+            let error = |content| {
+                Err(WhirlwingError::new(
+                    content,
+                    WhirlwingErrorKind::UniformFailure,
+                ))
+            };
+            let Some((_, gl_type, size)) = uniform else {
+                return error(format!(
+                    "Uniform `{name}` is not active in shader program {}",
+                    self.program_id.get()
+                ));
+            };
+            if !gl_types.contains(&gl_type) {
+                return error(format!(
+                    "Uniform `{name}` has type {}, it cannot be set from {}",
+                    glsl_type_name(gl_type),
+                    glsl_type
+                ));
+            }
+            if count > size as usize {
+                return error(format!(
+                    "Uniform `{name}` has {size} elements left, it cannot be set from {count}"
+                ));
+            }
         }
+        #[cfg(not(debug_assertions))]
+        let _ = (gl_types, glsl_type, count);
+
+        Ok(uniform.map_or(-1, |(location, _, _)| location))
+    }
+
+    /// Source file of every stage if the shader was created from files, in pipeline order.
+    pub fn source_paths(&self) -> Vec<(ShaderStage, &Path)> {
+        let Some(files) = &self.files else {
+            return Vec::new();
+        };
+        files
+            .paths
+            .iter()
+            .map(|(stage, path)| (*stage, path.as_path()))
+            .collect()
+    }
+
+    /// Source files and every file they include, empty if the shader was not created from files.
+    pub fn dependencies(&self) -> Vec<PathBuf> {
+        let Some(files) = &self.files else {
+            return Vec::new();
+        };
+        let mut dependencies: Vec<_> = files.paths.iter().map(|(_, path)| path.clone()).collect();
+        dependencies.extend(files.includes.borrow().iter().cloned());
+        dependencies
     }
 
-    pub fn set_mat4(&self, name: &str, uniform: &Mat4) {
+    /// Compiles the shader again from its source files and swaps in the new program.
+    /// If compilation fails the current program stays in use.
+    /// Does nothing for shaders which were not created from files.
+    pub fn reload(&self) -> Result<(), WhirlwingError> {
+        let Some(files) = &self.files else {
+            return Ok(());
+        };
+        let reloaded = files.builder.link()?;
+        // The old program is deleted when `reloaded` drops.
+        reloaded
+            .program_id
+            .set(self.program_id.replace(reloaded.program_id.get()));
+        self.reflection.swap(&reloaded.reflection);
         unsafe {
-            let mut name = name.to_string();
-            name.push('\0');
-            let location = gl::GetUniformLocation(self.program_id, name.as_ptr() as *const i8);
-            gl::UniformMatrix4fv(location, 1, gl::FALSE, uniform.as_ptr());
+            self.reflection
+                .borrow_mut()
+                .bind_blocks(self.program_id.get(), &self.block_bindings.borrow());
         }
+        files
+            .includes
+            .swap(&reloaded.files.as_ref().unwrap().includes);
+        Ok(())
     }
 
     pub fn id(&self) -> u32 {
-        self.program_id
+        self.program_id.get()
     }
 
     pub fn bind(&self) {
         unsafe {
-            gl::UseProgram(self.program_id);
+            gl::UseProgram(self.program_id.get());
         }
     }
+}
 
-    #[cfg(debug_assertions)]
-    fn check_shader_compilation(
-        shader: u32,
-        possible_error_message: &str,
-    ) -> Result<(), WhirlwingError> {
-        unsafe {
-            let mut success = 0;
-            let mut buffer = [0i8; 512];
-            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
-            if success == 0 {
-                gl::GetShaderInfoLog(shader, 512, null_mut(), &mut buffer[0]);
-                let string = from_utf8_unchecked(&*(buffer.as_ptr() as *const [u8; 512]));
-                let string = format!("{possible_error_message}\nOpenGL Error: {string}");
-                return Err(WhirlwingError::new(
-                    string.to_string(),
-                    WhirlwingErrorKind::ShaderCompilationFailure,
-                ));
-            }
-            Ok(())
-        }
-    }
+/// Splits `lights[2]` into `lights` and 2.
+fn array_element(name: &str) -> Option<(&str, i32)> {
+    let (array, index) = name.strip_suffix(']')?.rsplit_once('[')?;
+    Some((array, index.parse().ok()?))
+}
+
+/// Info log of a shader or program, sized with `GL_INFO_LOG_LENGTH`.
+unsafe fn info_log(
+    id: u32,
+    get_iv: unsafe fn(u32, u32, *mut i32),
+    get_log: unsafe fn(u32, i32, *mut i32, *mut i8),
+) -> String {
+    let mut length = 0;
+    get_iv(id, gl::INFO_LOG_LENGTH, &mut length);
+    let mut buffer = vec![0u8; length.max(1) as usize];
+    let mut written = 0;
+    get_log(
+        id,
+        buffer.len() as i32,
+        &mut written,
+        buffer.as_mut_ptr() as *mut i8,
+    );
+    buffer.truncate(written.max(0) as usize);
+    String::from_utf8_lossy(&buffer).into_owned()
 }
 
 impl Drop for Shader {
     fn drop(&mut self) {
         unsafe {
-            gl::DeleteProgram(self.program_id);
+            gl::DeleteProgram(self.program_id.get());
         }
     }
 }
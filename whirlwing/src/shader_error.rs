@@ -0,0 +1,225 @@
+use std::path::PathBuf;
+
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+
+use crate::{preprocessor::ProcessedSource, shader::ShaderStage};
+
+/// Details of a shader which failed to compile or link.
+///
+/// Attached as the source of the returned `WhirlwingError`, see `ShaderError::of`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderError {
+    /// Stage which failed to compile, `None` if linking failed.
+    pub stage: Option<ShaderStage>,
+    /// Source file or name of the failed stage, or of every stage if linking failed.
+    pub sources: Vec<PathBuf>,
+    /// Driver info log, line numbers rewritten to the files before includes were expanded.
+    pub log: String,
+    /// Errors and warnings parsed from the log, in order.
+    pub diagnostics: Vec<ShaderDiagnostic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderDiagnostic {
+    pub severity: Severity,
+    /// File the diagnostic points into, `None` for link errors and logs without a location.
+    pub file: Option<PathBuf>,
+    pub line: Option<u32>,
+    /// Only reported by some drivers.
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl ShaderError {
+    /// The details of a `WhirlwingError` returned for a shader which failed to compile or link.
+    pub fn of(error: &WhirlwingError) -> Option<&ShaderError> {
+        std::error::Error::source(error)?.downcast_ref()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ShaderDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub(crate) fn compilation(stage: ShaderStage, source: &ProcessedSource, log: &str) -> Self {
+        ShaderError {
+            stage: Some(stage),
+            sources: vec![source.name().to_path_buf()],
+            log: source.map_log(log),
+            diagnostics: parse_log(log, Some(source)),
+        }
+    }
+
+    pub(crate) fn link(sources: Vec<PathBuf>, log: &str) -> Self {
+        ShaderError {
+            stage: None,
+            sources,
+            log: log.to_string(),
+            diagnostics: parse_log(log, None),
+        }
+    }
+
+    pub(crate) fn into_error(self) -> WhirlwingError {
+        let content = format!("{self}\nOpenGL Error: {}", self.log);
+        WhirlwingError::new_with_source(
+            content,
+            WhirlwingErrorKind::ShaderCompilationFailure,
+            Box::new(self),
+        )
+    }
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.stage {
+            Some(stage) => write!(
+                f,
+                "{} Compilation Error: {}",
+                stage.name(),
+                self.sources[0].display()
+            ),
+            None => {
+                write!(f, "Shader Program Linking Failed:")?;
+                for source in &self.sources {
+                    write!(f, " {}", source.display())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+impl std::fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "{line}:")?;
+        }
+        if let Some(column) = self.column {
+            write!(f, "{column}:")?;
+        }
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, " {severity}: {}", self.message)
+    }
+}
+
+/// Parses the log formats of the common drivers, e.g. `0:12(5): error: ...` (Mesa),
+/// `0(12) : error C0000: ...` (NVIDIA) and `ERROR: 0:12: ...` (AMD, Intel, ANGLE).
+/// Lines which are not diagnostics themselves continue the previous message.
+fn parse_log(log: &str, source: Option<&ProcessedSource>) -> Vec<ShaderDiagnostic> {
+    let mut diagnostics: Vec<ShaderDiagnostic> = Vec::new();
+    for line in log.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        match parse_line(line) {
+            Some((severity, location, message)) => {
+                let (file, line) = match (location, source) {
+                    (Some((line, _)), Some(source)) => match source.original_line(line) {
+                        Some((file, line)) => (Some(file.to_path_buf()), Some(line)),
+                        // Injected defines have no original line.
+                        None => (Some(source.name().to_path_buf()), None),
+                    },
+                    (Some((line, _)), None) => (None, Some(line)),
+                    (None, source) => (source.map(|source| source.name().to_path_buf()), None),
+                };
+                diagnostics.push(ShaderDiagnostic {
+                    severity,
+                    file,
+                    line,
+                    column: location.and_then(|(_, column)| column),
+                    message: message.to_string(),
+                });
+            }
+            None => match diagnostics.last_mut() {
+                Some(diagnostic) => {
+                    diagnostic.message.push('\n');
+                    diagnostic.message.push_str(line);
+                }
+                None => diagnostics.push(ShaderDiagnostic {
+                    severity: Severity::Error,
+                    file: source.map(|source| source.name().to_path_buf()),
+                    line: None,
+                    column: None,
+                    message: line.to_string(),
+                }),
+            },
+        }
+    }
+    diagnostics
+}
+
+type Location = (u32, Option<u32>);
+
+fn parse_line(line: &str) -> Option<(Severity, Option<Location>, &str)> {
+    let (severity, rest) = if let Some(rest) = line.strip_prefix("ERROR:") {
+        (Some(Severity::Error), rest.trim_start())
+    } else if let Some(rest) = line.strip_prefix("WARNING:") {
+        (Some(Severity::Warning), rest.trim_start())
+    } else {
+        (None, line)
+    };
+
+    let (location, rest) = match parse_location(rest) {
+        Some((location, rest)) => (Some(location), rest.trim_start()),
+        None => (None, rest),
+    };
+
+    let (severity, rest) = match severity {
+        Some(severity) => (severity, rest),
+        None => strip_severity(rest)?,
+    };
+    Some((severity, location, rest.trim()))
+}
+
+/// `0:12(5):`, `0:12:` or `0(12) :`, the leading number is the source string, always 0.
+fn parse_location(text: &str) -> Option<(Location, &str)> {
+    let rest = text.strip_prefix('0')?;
+    let (line, column, rest) = if let Some(rest) = rest.strip_prefix(':') {
+        let (line, rest) = parse_number(rest)?;
+        match rest.strip_prefix('(') {
+            Some(rest) => {
+                let (column, rest) = parse_number(rest)?;
+                (line, Some(column), rest.strip_prefix(')')?)
+            }
+            None => (line, None, rest),
+        }
+    } else {
+        let (line, rest) = parse_number(rest.strip_prefix('(')?)?;
+        (line, None, rest.strip_prefix(')')?)
+    };
+    let rest = rest.trim_start().strip_prefix(':')?;
+    Some(((line, column), rest))
+}
+
+fn parse_number(text: &str) -> Option<(u32, &str)> {
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    Some((text[..digits].parse().ok()?, &text[digits..]))
+}
+
+/// `error: ...` or `error C0000: ...`, in any case.
+fn strip_severity(text: &str) -> Option<(Severity, &str)> {
+    let (word, rest) = text.split_once(':')?;
+    let mut words = word.split_whitespace();
+    let severity = match words.next()?.to_ascii_lowercase().as_str() {
+        "error" => Severity::Error,
+        "warning" => Severity::Warning,
+        _ => return None,
+    };
+    // At most an error code may follow.
+    if words.count() > 1 {
+        return None;
+    }
+    Some((severity, rest))
+}
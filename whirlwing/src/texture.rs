@@ -0,0 +1,541 @@
+use std::cell::Cell;
+use std::ffi::c_void;
+use std::path::Path;
+
+use image::DynamicImage;
+use serde::Deserialize;
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+
+use crate::shader::context_version;
+
+// Same values for GL_EXT_texture_filter_anisotropic and core OpenGL 4.6,
+// the generated bindings stop at 4.5.
+const TEXTURE_MAX_ANISOTROPY: u32 = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: u32 = 0x84FF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+}
+
+impl Wrap {
+    /// `ClampToBorder` falls back to `ClampToEdge` without `border_supported`.
+    fn gl_wrap(self, border_supported: bool) -> i32 {
+        (match self {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            Wrap::ClampToBorder if border_supported => gl::CLAMP_TO_BORDER,
+            Wrap::ClampToBorder => gl::CLAMP_TO_EDGE,
+        }) as i32
+    }
+}
+
+/// Border colours and `CLAMP_TO_BORDER` need OpenGL ES 3.2 on OpenGL ES contexts.
+fn border_supported() -> bool {
+    let (major, minor, gles) = context_version();
+    !gles || (major, minor) >= (3, 2)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+/// How a texture is sampled.
+///
+/// With `mipmaps` enabled the mip chain is generated on upload and the minification filter
+/// also interpolates between mip levels using `min_filter`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerDesc {
+    pub wrap_s: Wrap,
+    pub wrap_t: Wrap,
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    pub mipmaps: bool,
+    /// Used with `Wrap::ClampToBorder`.
+    pub border_color: [f32; 4],
+    /// Maximum anisotropy, clamped to what the driver supports. Ignored if unsupported.
+    pub anisotropy: Option<f32>,
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        SamplerDesc {
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::Repeat,
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmaps: true,
+            border_color: [0.0, 0.0, 0.0, 0.0],
+            anisotropy: None,
+        }
+    }
+}
+
+impl SamplerDesc {
+    fn gl_min_filter(&self) -> i32 {
+        (match (self.min_filter, self.mipmaps) {
+            (Filter::Nearest, false) => gl::NEAREST,
+            (Filter::Linear, false) => gl::LINEAR,
+            (Filter::Nearest, true) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Linear, true) => gl::LINEAR_MIPMAP_LINEAR,
+        }) as i32
+    }
+
+    fn gl_mag_filter(&self) -> i32 {
+        (match self.mag_filter {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        }) as i32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextureDesc {
+    pub sampler: SamplerDesc,
+    /// Store 8-bit RGB(A) data in an sRGB format so sampling returns linear values.
+    /// Should be set for colour textures and left unset for data like normal maps.
+    pub srgb: bool,
+    /// Flip rows on upload, images store the top row first while OpenGL expects the bottom one.
+    pub flip_vertically: bool,
+}
+
+/// Storage format of textures and renderbuffers which are rendered into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    R8,
+    Rg8,
+    Rgba8,
+    /// 8-bit colour stored in sRGB, written and sampled as linear values.
+    Srgb8Alpha8,
+    R16F,
+    Rg16F,
+    Rgba16F,
+    R32F,
+    Rgba32F,
+    Depth24,
+    Depth32F,
+    Depth24Stencil8,
+    Depth32FStencil8,
+}
+
+impl TextureFormat {
+    pub fn is_depth(self) -> bool {
+        matches!(
+            self,
+            TextureFormat::Depth24
+                | TextureFormat::Depth32F
+                | TextureFormat::Depth24Stencil8
+                | TextureFormat::Depth32FStencil8
+        )
+    }
+
+    pub fn has_stencil(self) -> bool {
+        matches!(
+            self,
+            TextureFormat::Depth24Stencil8 | TextureFormat::Depth32FStencil8
+        )
+    }
+
+    pub(crate) fn gl_internal_format(self) -> u32 {
+        match self {
+            TextureFormat::R8 => gl::R8,
+            TextureFormat::Rg8 => gl::RG8,
+            TextureFormat::Rgba8 => gl::RGBA8,
+            TextureFormat::Srgb8Alpha8 => gl::SRGB8_ALPHA8,
+            TextureFormat::R16F => gl::R16F,
+            TextureFormat::Rg16F => gl::RG16F,
+            TextureFormat::Rgba16F => gl::RGBA16F,
+            TextureFormat::R32F => gl::R32F,
+            TextureFormat::Rgba32F => gl::RGBA32F,
+            TextureFormat::Depth24 => gl::DEPTH_COMPONENT24,
+            TextureFormat::Depth32F => gl::DEPTH_COMPONENT32F,
+            TextureFormat::Depth24Stencil8 => gl::DEPTH24_STENCIL8,
+            TextureFormat::Depth32FStencil8 => gl::DEPTH32F_STENCIL8,
+        }
+    }
+
+    /// Format and type passed along with the internal format when allocating storage.
+    fn gl_format_and_type(self) -> (u32, u32) {
+        match self {
+            TextureFormat::R8 => (gl::RED, gl::UNSIGNED_BYTE),
+            TextureFormat::Rg8 => (gl::RG, gl::UNSIGNED_BYTE),
+            TextureFormat::Rgba8 | TextureFormat::Srgb8Alpha8 => (gl::RGBA, gl::UNSIGNED_BYTE),
+            TextureFormat::R16F | TextureFormat::R32F => (gl::RED, gl::FLOAT),
+            TextureFormat::Rg16F => (gl::RG, gl::FLOAT),
+            TextureFormat::Rgba16F | TextureFormat::Rgba32F => (gl::RGBA, gl::FLOAT),
+            TextureFormat::Depth24 => (gl::DEPTH_COMPONENT, gl::UNSIGNED_INT),
+            TextureFormat::Depth32F => (gl::DEPTH_COMPONENT, gl::FLOAT),
+            TextureFormat::Depth24Stencil8 => (gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8),
+            TextureFormat::Depth32FStencil8 => {
+                (gl::DEPTH_STENCIL, gl::FLOAT_32_UNSIGNED_INT_24_8_REV)
+            }
+        }
+    }
+}
+
+/// Two-dimensional texture living on the GPU.
+///
+/// Must be created and dropped while the OpenGL context is current.
+#[derive(Debug)]
+pub struct Texture2D {
+    texture_id: u32,
+    width: u32,
+    height: u32,
+    // Mipmaps are generated once, the first time a sampler asks for them.
+    has_mipmaps: Cell<bool>,
+}
+
+impl Texture2D {
+    pub fn from_path<P: AsRef<Path>>(path: P, desc: &TextureDesc) -> Result<Texture2D, WhirlwingError> {
+        let image = Self::decode(path.as_ref())?;
+        Ok(Self::from_image(&image, desc))
+    }
+
+    /// Decodes an image in any format supported by the `image` crate, e.g. PNG or JPEG.
+    pub fn from_bytes(bytes: &[u8], desc: &TextureDesc) -> Result<Texture2D, WhirlwingError> {
+        let image = image::load_from_memory(bytes).map_err(|error| {
+            WhirlwingError::new_with_source(
+                "Failed to decode image from bytes".to_string(),
+                WhirlwingErrorKind::TextureLoadFailure,
+                Box::new(error),
+            )
+        })?;
+        Ok(Self::from_image(&image, desc))
+    }
+
+    pub fn from_image(image: &DynamicImage, desc: &TextureDesc) -> Texture2D {
+        let flipped;
+        let image = if desc.flip_vertically {
+            flipped = image.flipv();
+            &flipped
+        } else {
+            image
+        };
+
+        let converted;
+        let (format, image) = match PixelFormat::of(image, desc.srgb) {
+            Some(format) => (format, image),
+            None => {
+                converted = DynamicImage::ImageRgba8(image.to_rgba8());
+                (PixelFormat::of(&converted, desc.srgb).unwrap(), &converted)
+            }
+        };
+
+        let width = image.width();
+        let height = image.height();
+
+        unsafe {
+            let mut texture_id = 0;
+            gl::GenTextures(1, &mut texture_id);
+            gl::BindTexture(gl::TEXTURE_2D, texture_id);
+
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                format.internal_format as i32,
+                width as i32,
+                height as i32,
+                0,
+                format.format,
+                format.data_type,
+                image.as_bytes().as_ptr() as *const c_void,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+
+            if let Some(swizzle) = format.swizzle {
+                gl::TexParameteriv(gl::TEXTURE_2D, gl::TEXTURE_SWIZZLE_RGBA, swizzle.as_ptr());
+            }
+
+            let texture = Texture2D {
+                texture_id,
+                width,
+                height,
+                has_mipmaps: Cell::new(false),
+            };
+            texture.set_sampler(&desc.sampler);
+            texture
+        }
+    }
+
+    /// Texture with uninitialised contents, e.g. to render into.
+    pub fn empty(
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        sampler: &SamplerDesc,
+    ) -> Texture2D {
+        let (pixel_format, data_type) = format.gl_format_and_type();
+        unsafe {
+            let mut texture_id = 0;
+            gl::GenTextures(1, &mut texture_id);
+            gl::BindTexture(gl::TEXTURE_2D, texture_id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                format.gl_internal_format() as i32,
+                width as i32,
+                height as i32,
+                0,
+                pixel_format,
+                data_type,
+                std::ptr::null(),
+            );
+
+            let texture = Texture2D {
+                texture_id,
+                width,
+                height,
+                has_mipmaps: Cell::new(false),
+            };
+            texture.set_sampler(sampler);
+            texture
+        }
+    }
+
+    /// CPU side of `from_path`, safe to call without an OpenGL context.
+    pub(crate) fn decode(path: &Path) -> Result<DynamicImage, WhirlwingError> {
+        image::open(path).map_err(|error| {
+            WhirlwingError::new_with_source(
+                format!("Failed to load image from path: {}", path.display()),
+                WhirlwingErrorKind::TextureLoadFailure,
+                Box::new(error),
+            )
+        })
+    }
+
+    /// Changes sampling parameters. Mipmaps are generated when they are first enabled,
+    /// the border colour is only set for `Wrap::ClampToBorder`.
+    pub fn set_sampler(&self, sampler: &SamplerDesc) {
+        let border_supported = border_supported();
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture_id);
+            let wrap_s = sampler.wrap_s.gl_wrap(border_supported);
+            let wrap_t = sampler.wrap_t.gl_wrap(border_supported);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap_s);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap_t);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, sampler.gl_min_filter());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, sampler.gl_mag_filter());
+            let clamp_to_border = [sampler.wrap_s, sampler.wrap_t].contains(&Wrap::ClampToBorder);
+            if clamp_to_border && border_supported {
+                gl::TexParameterfv(
+                    gl::TEXTURE_2D,
+                    gl::TEXTURE_BORDER_COLOR,
+                    sampler.border_color.as_ptr(),
+                );
+            }
+
+            if let Some(anisotropy) = sampler.anisotropy {
+                let mut max_anisotropy = 0.0;
+                gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+                if max_anisotropy >= 1.0 {
+                    let anisotropy = anisotropy.clamp(1.0, max_anisotropy);
+                    gl::TexParameterf(gl::TEXTURE_2D, TEXTURE_MAX_ANISOTROPY, anisotropy);
+                } else {
+                    wwg_log::wwg_warn!("Anisotropic filtering is not supported");
+                }
+            }
+
+            if sampler.mipmaps && !self.has_mipmaps.replace(true) {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+        }
+    }
+
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture_id);
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.texture_id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl Drop for Texture2D {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture_id);
+        }
+    }
+}
+
+/// Three-dimensional texture, used as colour lookup table for colour grading.
+///
+/// Sampled linearly and clamped to the edges.
+/// Must be created and dropped while the OpenGL context is current.
+#[derive(Debug)]
+pub struct Texture3D {
+    texture_id: u32,
+    size: [u32; 3],
+}
+
+impl Texture3D {
+    /// `texels` are 8-bit RGB, x varying fastest and z slowest.
+    ///
+    /// Panics if `texels` does not hold `width * height * depth` texels.
+    pub fn from_rgb8(size: [u32; 3], texels: &[u8]) -> Texture3D {
+        let [width, height, depth] = size;
+        assert_eq!(
+            texels.len(),
+            width as usize * height as usize * depth as usize * 3,
+            "texel data does not match the texture size"
+        );
+        unsafe {
+            let mut texture_id = 0;
+            gl::GenTextures(1, &mut texture_id);
+            gl::BindTexture(gl::TEXTURE_3D, texture_id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage3D(
+                gl::TEXTURE_3D,
+                0,
+                gl::RGB8 as i32,
+                width as i32,
+                height as i32,
+                depth as i32,
+                0,
+                gl::RGB,
+                gl::UNSIGNED_BYTE,
+                texels.as_ptr() as *const c_void,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+
+            let clamp = gl::CLAMP_TO_EDGE as i32;
+            gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_S, clamp);
+            gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_T, clamp);
+            gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_R, clamp);
+            gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+
+            Texture3D { texture_id, size }
+        }
+    }
+
+    /// Lookup table which leaves colours unchanged, `size` entries per channel.
+    pub fn identity_lut(size: u32) -> Texture3D {
+        let scale = |value: u32| (value * 255 / (size - 1).max(1)) as u8;
+        let mut texels = Vec::with_capacity(size.pow(3) as usize * 3);
+        for blue in 0..size {
+            for green in 0..size {
+                for red in 0..size {
+                    texels.extend([scale(red), scale(green), scale(blue)]);
+                }
+            }
+        }
+        Self::from_rgb8([size; 3], &texels)
+    }
+
+    /// Lookup table laid out as a horizontal strip of `size` slices of `size` by `size`
+    /// pixels, as exported by most image editors. Red grows to the right within a slice,
+    /// green from the top row down and blue from slice to slice.
+    pub fn lut_from_image(image: &DynamicImage) -> Result<Texture3D, WhirlwingError> {
+        let (width, size) = (image.width(), image.height());
+        if width != size * size {
+            return Err(WhirlwingError::new(
+                format!("{width}x{size} image is not a strip of {size} lookup table slices"),
+                WhirlwingErrorKind::TextureLoadFailure,
+            ));
+        }
+        let image = image.to_rgb8();
+        let mut texels = Vec::with_capacity(width as usize * size as usize * 3);
+        for blue in 0..size {
+            for green in 0..size {
+                for red in 0..size {
+                    texels.extend(image.get_pixel(blue * size + red, green).0);
+                }
+            }
+        }
+        Ok(Self::from_rgb8([size; 3], &texels))
+    }
+
+    pub fn lut_from_path<P: AsRef<Path>>(path: P) -> Result<Texture3D, WhirlwingError> {
+        Self::lut_from_image(&Texture2D::decode(path.as_ref())?)
+    }
+
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_3D, self.texture_id);
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.texture_id
+    }
+
+    pub fn size(&self) -> [u32; 3] {
+        self.size
+    }
+}
+
+impl Drop for Texture3D {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture_id);
+        }
+    }
+}
+
+struct PixelFormat {
+    internal_format: u32,
+    format: u32,
+    data_type: u32,
+    swizzle: Option<[i32; 4]>,
+}
+
+impl PixelFormat {
+    const GREY: [i32; 4] = [gl::RED as i32, gl::RED as i32, gl::RED as i32, gl::ONE as i32];
+    const GREY_ALPHA: [i32; 4] = [gl::RED as i32, gl::RED as i32, gl::RED as i32, gl::GREEN as i32];
+
+    /// Returns `None` for images which have to be converted before upload.
+    /// sRGB only exists for 8-bit colour formats and is ignored for the rest.
+    fn of(image: &DynamicImage, srgb: bool) -> Option<PixelFormat> {
+        let (internal_format, format, data_type, swizzle) = match image {
+            DynamicImage::ImageLuma8(_) => (gl::R8, gl::RED, gl::UNSIGNED_BYTE, Some(Self::GREY)),
+            DynamicImage::ImageLumaA8(_) => {
+                (gl::RG8, gl::RG, gl::UNSIGNED_BYTE, Some(Self::GREY_ALPHA))
+            }
+            DynamicImage::ImageRgb8(_) if srgb => (gl::SRGB8, gl::RGB, gl::UNSIGNED_BYTE, None),
+            DynamicImage::ImageRgb8(_) => (gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE, None),
+            DynamicImage::ImageRgba8(_) if srgb => {
+                (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE, None)
+            }
+            DynamicImage::ImageRgba8(_) => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE, None),
+            DynamicImage::ImageLuma16(_) => {
+                (gl::R16, gl::RED, gl::UNSIGNED_SHORT, Some(Self::GREY))
+            }
+            DynamicImage::ImageLumaA16(_) => {
+                (gl::RG16, gl::RG, gl::UNSIGNED_SHORT, Some(Self::GREY_ALPHA))
+            }
+            DynamicImage::ImageRgb16(_) => (gl::RGB16, gl::RGB, gl::UNSIGNED_SHORT, None),
+            DynamicImage::ImageRgba16(_) => (gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT, None),
+            DynamicImage::ImageRgb32F(_) => (gl::RGB32F, gl::RGB, gl::FLOAT, None),
+            DynamicImage::ImageRgba32F(_) => (gl::RGBA32F, gl::RGBA, gl::FLOAT, None),
+            _ => return None,
+        };
+        Some(PixelFormat {
+            internal_format,
+            format,
+            data_type,
+            swizzle,
+        })
+    }
+}
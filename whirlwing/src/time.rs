@@ -1,7 +1,7 @@
 use std::time::{Duration, Instant};
 
-#[derive(Debug)]
-pub(crate) struct Time {
+#[derive(Debug, Clone)]
+pub struct Time {
     start: Instant,
     now: Duration,
     delta_time: Duration,
@@ -20,6 +20,20 @@ impl Time {
         }
     }
 
+    /// Creates a clock which only moves when `advance` is called, for deterministic rendering.
+    pub fn fixed(now: Duration, delta_time: Duration) -> Time {
+        Time {
+            start: Instant::now(),
+            now,
+            delta_time,
+        }
+    }
+
+    /// Moves the clock forward by `delta_time` without looking at the wall clock.
+    pub fn advance(&mut self) {
+        self.now += self.delta_time;
+    }
+
     pub(crate) fn reset(&mut self) {
         self.start = Instant::now();
         self.now = self.start.elapsed();
@@ -32,11 +46,11 @@ impl Time {
         self.now = elapsed;
     }
 
-    pub(crate) fn now(&self) -> Duration {
+    pub fn now(&self) -> Duration {
         self.now
     }
 
-    pub(crate) fn delta_time(&self) -> Duration {
+    pub fn delta_time(&self) -> Duration {
         self.delta_time
     }
 }
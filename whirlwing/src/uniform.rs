@@ -0,0 +1,231 @@
+use wwg_math::{IVec2, IVec3, IVec4, Mat2, Mat3, Mat4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4};
+
+/// Value which can be written to a GLSL uniform with `Shader::set`.
+///
+/// Implemented for every `UniformElement`, and for slices, arrays and `Vec`s of them,
+/// which fill consecutive elements of a GLSL array.
+pub trait Uniform {
+    type Element: UniformElement;
+
+    fn elements(&self) -> &[Self::Element];
+}
+
+/// Single GLSL value, e.g. a `vec3` or a `mat4`.
+///
+/// Implemented for `bool`, `i32`, `u32`, `f32` and the `wwg_math` vectors and matrices.
+/// `i32` is also used for samplers, its value is the texture unit.
+pub trait UniformElement: Sized {
+    /// GLSL types this can be written to, as OpenGL enums.
+    const GL_TYPES: &'static [u32];
+    /// GLSL spelling used in error messages.
+    const GLSL_TYPE: &'static str;
+
+    /// Writes `values` to consecutive array elements starting at `location`.
+    ///
+    /// # Safety
+    /// An OpenGL context must be current and the program owning `location` bound.
+    unsafe fn upload(location: i32, values: &[Self]);
+}
+
+impl<T: UniformElement> Uniform for T {
+    type Element = T;
+
+    fn elements(&self) -> &[T] {
+        std::slice::from_ref(self)
+    }
+}
+
+impl<T: UniformElement> Uniform for [T] {
+    type Element = T;
+
+    fn elements(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T: UniformElement, const N: usize> Uniform for [T; N] {
+    type Element = T;
+
+    fn elements(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T: UniformElement> Uniform for Vec<T> {
+    type Element = T;
+
+    fn elements(&self) -> &[T] {
+        self
+    }
+}
+
+impl UniformElement for bool {
+    const GL_TYPES: &'static [u32] = &[gl::BOOL];
+    const GLSL_TYPE: &'static str = "bool";
+
+    unsafe fn upload(location: i32, values: &[bool]) {
+        let values: Vec<i32> = values.iter().map(|&value| value as i32).collect();
+        gl::Uniform1iv(location, values.len() as i32, values.as_ptr());
+    }
+}
+
+impl UniformElement for i32 {
+    const GL_TYPES: &'static [u32] = &[
+        gl::INT,
+        gl::BOOL,
+        gl::SAMPLER_1D,
+        gl::SAMPLER_2D,
+        gl::SAMPLER_3D,
+        gl::SAMPLER_CUBE,
+        gl::SAMPLER_1D_SHADOW,
+        gl::SAMPLER_2D_SHADOW,
+        gl::SAMPLER_1D_ARRAY,
+        gl::SAMPLER_2D_ARRAY,
+        gl::SAMPLER_CUBE_MAP_ARRAY,
+        gl::SAMPLER_2D_ARRAY_SHADOW,
+        gl::SAMPLER_2D_MULTISAMPLE,
+        gl::SAMPLER_2D_MULTISAMPLE_ARRAY,
+        gl::SAMPLER_CUBE_SHADOW,
+        gl::SAMPLER_BUFFER,
+        gl::SAMPLER_2D_RECT,
+        gl::INT_SAMPLER_2D,
+        gl::INT_SAMPLER_3D,
+        gl::INT_SAMPLER_CUBE,
+        gl::INT_SAMPLER_2D_ARRAY,
+        gl::UNSIGNED_INT_SAMPLER_2D,
+        gl::UNSIGNED_INT_SAMPLER_3D,
+        gl::UNSIGNED_INT_SAMPLER_CUBE,
+        gl::UNSIGNED_INT_SAMPLER_2D_ARRAY,
+    ];
+    const GLSL_TYPE: &'static str = "int";
+
+    unsafe fn upload(location: i32, values: &[i32]) {
+        gl::Uniform1iv(location, values.len() as i32, values.as_ptr());
+    }
+}
+
+impl UniformElement for u32 {
+    const GL_TYPES: &'static [u32] = &[gl::UNSIGNED_INT, gl::BOOL];
+    const GLSL_TYPE: &'static str = "uint";
+
+    unsafe fn upload(location: i32, values: &[u32]) {
+        gl::Uniform1uiv(location, values.len() as i32, values.as_ptr());
+    }
+}
+
+impl UniformElement for f32 {
+    const GL_TYPES: &'static [u32] = &[gl::FLOAT];
+    const GLSL_TYPE: &'static str = "float";
+
+    unsafe fn upload(location: i32, values: &[f32]) {
+        gl::Uniform1fv(location, values.len() as i32, values.as_ptr());
+    }
+}
+
+// The `wwg_math` types are `repr(C)` structs of their components, so a slice of them
+// can be passed as a flat array of scalars.
+macro_rules! uniform_vector {
+    ($($type:ty => $glsl_type:literal, $gl_type:expr, $scalar:ty, $upload:ident;)+) => {$(
+        impl UniformElement for $type {
+            const GL_TYPES: &'static [u32] = &[$gl_type];
+            const GLSL_TYPE: &'static str = $glsl_type;
+
+            unsafe fn upload(location: i32, values: &[$type]) {
+                gl::$upload(location, values.len() as i32, values.as_ptr() as *const $scalar);
+            }
+        }
+    )+};
+}
+
+uniform_vector! {
+    Vec2 => "vec2", gl::FLOAT_VEC2, f32, Uniform2fv;
+    Vec3 => "vec3", gl::FLOAT_VEC3, f32, Uniform3fv;
+    Vec4 => "vec4", gl::FLOAT_VEC4, f32, Uniform4fv;
+    IVec2 => "ivec2", gl::INT_VEC2, i32, Uniform2iv;
+    IVec3 => "ivec3", gl::INT_VEC3, i32, Uniform3iv;
+    IVec4 => "ivec4", gl::INT_VEC4, i32, Uniform4iv;
+    UVec2 => "uvec2", gl::UNSIGNED_INT_VEC2, u32, Uniform2uiv;
+    UVec3 => "uvec3", gl::UNSIGNED_INT_VEC3, u32, Uniform3uiv;
+    UVec4 => "uvec4", gl::UNSIGNED_INT_VEC4, u32, Uniform4uiv;
+}
+
+// Matrices are column major like OpenGL expects, no transpose needed.
+macro_rules! uniform_matrix {
+    ($($type:ty => $glsl_type:literal, $gl_type:expr, $upload:ident;)+) => {$(
+        impl UniformElement for $type {
+            const GL_TYPES: &'static [u32] = &[$gl_type];
+            const GLSL_TYPE: &'static str = $glsl_type;
+
+            unsafe fn upload(location: i32, values: &[$type]) {
+                gl::$upload(
+                    location,
+                    values.len() as i32,
+                    gl::FALSE,
+                    values.as_ptr() as *const f32,
+                );
+            }
+        }
+    )+};
+}
+
+uniform_matrix! {
+    Mat2 => "mat2", gl::FLOAT_MAT2, UniformMatrix2fv;
+    Mat3 => "mat3", gl::FLOAT_MAT3, UniformMatrix3fv;
+    Mat4 => "mat4", gl::FLOAT_MAT4, UniformMatrix4fv;
+}
+
+/// GLSL spelling of an OpenGL type enum, for error messages.
+#[cfg(debug_assertions)]
+pub(crate) fn glsl_type_name(gl_type: u32) -> &'static str {
+    match gl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL => "bool",
+        gl::BOOL_VEC2 => "bvec2",
+        gl::BOOL_VEC3 => "bvec3",
+        gl::BOOL_VEC4 => "bvec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::FLOAT_MAT2x3 => "mat2x3",
+        gl::FLOAT_MAT2x4 => "mat2x4",
+        gl::FLOAT_MAT3x2 => "mat3x2",
+        gl::FLOAT_MAT3x4 => "mat3x4",
+        gl::FLOAT_MAT4x2 => "mat4x2",
+        gl::FLOAT_MAT4x3 => "mat4x3",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_1D_SHADOW => "sampler1DShadow",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_1D_ARRAY => "sampler1DArray",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_CUBE_MAP_ARRAY => "samplerCubeArray",
+        gl::SAMPLER_2D_ARRAY_SHADOW => "sampler2DArrayShadow",
+        gl::SAMPLER_2D_MULTISAMPLE => "sampler2DMS",
+        gl::SAMPLER_2D_MULTISAMPLE_ARRAY => "sampler2DMSArray",
+        gl::SAMPLER_CUBE_SHADOW => "samplerCubeShadow",
+        gl::SAMPLER_BUFFER => "samplerBuffer",
+        gl::SAMPLER_2D_RECT => "sampler2DRect",
+        gl::INT_SAMPLER_2D => "isampler2D",
+        gl::INT_SAMPLER_3D => "isampler3D",
+        gl::INT_SAMPLER_CUBE => "isamplerCube",
+        gl::INT_SAMPLER_2D_ARRAY => "isampler2DArray",
+        gl::UNSIGNED_INT_SAMPLER_2D => "usampler2D",
+        gl::UNSIGNED_INT_SAMPLER_3D => "usampler3D",
+        gl::UNSIGNED_INT_SAMPLER_CUBE => "usamplerCube",
+        gl::UNSIGNED_INT_SAMPLER_2D_ARRAY => "usampler2DArray",
+        _ => "an unsupported type",
+    }
+}
@@ -0,0 +1,309 @@
+use std::{ffi::c_void, marker::PhantomData, mem::size_of};
+
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+use wwg_math::{IVec2, IVec3, IVec4, Mat4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4};
+
+/// Uniform blocks every `Shader` binds automatically, by block name.
+pub const RESERVED_BLOCKS: &[(&str, u32)] = &[("Camera", 0), ("Lights", 1)];
+
+/// First binding point which is not reserved, for custom blocks.
+pub const FIRST_FREE_BINDING: u32 = 2;
+
+/// Binding point reserved for a uniform block name.
+pub fn reserved_binding(block_name: &str) -> Option<u32> {
+    RESERVED_BLOCKS
+        .iter()
+        .find(|(name, _)| *name == block_name)
+        .map(|(_, binding)| *binding)
+}
+
+/// Data whose Rust layout matches the std140 layout of a GLSL uniform block member.
+///
+/// Implemented for 4-byte scalars, the `wwg_math` vectors, `Mat4`, arrays and `Padding`.
+/// `bool`, `Mat2` and `Mat3` are left out since their sizes differ from std140, use `u32`
+/// and `Mat4` or `Vec4` columns instead. Structs implement it through `std140!`.
+///
+/// # Safety
+///
+/// `UniformBuffer` uploads the bytes of a `Self`, so it must not have padding bytes, which
+/// are uninitialized. `SIZE` must equal `size_of::<Self>()`, or `check_layout` has to fail
+/// when it does not. The implementations `std140!` emits uphold this.
+pub unsafe trait Std140: Copy + 'static {
+    /// Base alignment in bytes.
+    const ALIGN: usize;
+    /// Size in bytes, must equal `size_of::<Self>()`.
+    const SIZE: usize;
+
+    /// Describes the first place where the Rust layout differs from std140.
+    fn check_layout() -> Result<(), String> {
+        let size = std::mem::size_of::<Self>();
+        if size == Self::SIZE {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} is {size} bytes, std140 expects {}",
+                std::any::type_name::<Self>(),
+                Self::SIZE
+            ))
+        }
+    }
+}
+
+macro_rules! std140_primitive {
+    ($($type:ty => $align:literal;)+) => {$(
+        // SAFETY: `SIZE` is the size of the type.
+        unsafe impl Std140 for $type {
+            const ALIGN: usize = $align;
+            const SIZE: usize = std::mem::size_of::<$type>();
+        }
+    )+};
+}
+
+std140_primitive! {
+    f32 => 4;
+    i32 => 4;
+    u32 => 4;
+    Vec2 => 8;
+    IVec2 => 8;
+    UVec2 => 8;
+    Vec3 => 16;
+    IVec3 => 16;
+    UVec3 => 16;
+    Vec4 => 16;
+    IVec4 => 16;
+    UVec4 => 16;
+    Mat4 => 16;
+}
+
+/// Array elements are padded to a multiple of 16 bytes in std140, so only elements whose
+/// size already is one, e.g. `Vec4`, `Mat4` or `std140!` structs, have a matching layout.
+// SAFETY: `check_layout` fails unless the elements are `SIZE / N` bytes apart.
+unsafe impl<T: Std140, const N: usize> Std140 for [T; N] {
+    const ALIGN: usize = align_up(T::ALIGN, 16);
+    const SIZE: usize = N * align_up(T::SIZE, Self::ALIGN);
+
+    fn check_layout() -> Result<(), String> {
+        T::check_layout()?;
+        let stride = std::mem::size_of::<T>();
+        let std140_stride = align_up(T::SIZE, Self::ALIGN);
+        if stride == std140_stride {
+            Ok(())
+        } else {
+            Err(format!(
+                "Array elements of {} are {stride} bytes apart, std140 expects {std140_stride}",
+                std::any::type_name::<T>()
+            ))
+        }
+    }
+}
+
+/// Explicit padding to line the next member up with its std140 offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Padding<const N: usize>([u8; N]);
+
+impl<const N: usize> Default for Padding<N> {
+    fn default() -> Self {
+        Padding([0; N])
+    }
+}
+
+// SAFETY: `SIZE` is the size of the type.
+unsafe impl<const N: usize> Std140 for Padding<N> {
+    const ALIGN: usize = 1;
+    const SIZE: usize = N;
+}
+
+#[doc(hidden)]
+pub const fn align_up(offset: usize, align: usize) -> usize {
+    (offset + align - 1) / align * align
+}
+
+/// Alignment and size of a std140 struct with members of the given alignments and sizes.
+#[doc(hidden)]
+pub const fn struct_layout(members: &[(usize, usize)]) -> (usize, usize) {
+    let mut align = 16;
+    let mut end = 0;
+    let mut i = 0;
+    while i < members.len() {
+        let (member_align, member_size) = members[i];
+        if member_align > align {
+            align = member_align;
+        }
+        end = align_up(end, member_align) + member_size;
+        i += 1;
+    }
+    (align, align_up(end, align))
+}
+
+/// Declares a `#[repr(C)]` struct implementing `Std140`, to be uploaded with `UniformBuffer`.
+///
+/// Members follow the std140 rules, e.g. a `Vec3` followed by another `Vec3` needs a
+/// `Padding<4>` between them. `UniformBuffer::new` reports members which are out of place.
+///
+/// ```
+/// use whirlwing::{math::{Mat4, Vec3}, std140, uniform_buffer::Padding};
+///
+/// std140! {
+///     /// Matches `uniform Sun { mat4 shadow_matrix; vec3 direction; float intensity; };`
+///     #[derive(Debug, Default)]
+///     pub struct Sun {
+///         pub shadow_matrix: Mat4,
+///         pub direction: Vec3,
+///         pub intensity: f32,
+///     }
+/// }
+///
+/// std140! {
+///     pub struct Colors {
+///         pub ambient: Vec3,
+///         _padding: Padding<4>,
+///         pub diffuse: Vec3,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! std140 {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $type:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy)]
+        #[repr(C)]
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $type),*
+        }
+
+        // SAFETY: `check_layout` fails unless `SIZE` is the size of the struct.
+        unsafe impl $crate::uniform_buffer::Std140 for $name {
+            const ALIGN: usize = $crate::uniform_buffer::struct_layout(&[
+                $((<$type as $crate::uniform_buffer::Std140>::ALIGN, <$type as $crate::uniform_buffer::Std140>::SIZE)),*
+            ]).0;
+            const SIZE: usize = $crate::uniform_buffer::struct_layout(&[
+                $((<$type as $crate::uniform_buffer::Std140>::ALIGN, <$type as $crate::uniform_buffer::Std140>::SIZE)),*
+            ]).1;
+
+            #[allow(unused_mut, unused_variables, unused_assignments)]
+            fn check_layout() -> Result<(), String> {
+                let mut end = 0;
+                $(
+                    let expected = $crate::uniform_buffer::align_up(
+                        end,
+                        <$type as $crate::uniform_buffer::Std140>::ALIGN,
+                    );
+                    // `offset_of!` needs a newer compiler than the minimum supported one.
+                    let offset = {
+                        let data = ::std::mem::MaybeUninit::<$name>::uninit();
+                        let base = data.as_ptr();
+                        // SAFETY: Only the address is taken, nothing is read.
+                        let field = unsafe { ::std::ptr::addr_of!((*base).$field) };
+                        field as usize - base as usize
+                    };
+                    if offset != expected {
+                        return Err(format!(
+                            "`{}::{}` is at offset {offset}, std140 places it at {expected}",
+                            stringify!($name),
+                            stringify!($field)
+                        ));
+                    }
+                    <$type as $crate::uniform_buffer::Std140>::check_layout().map_err(|error| {
+                        format!("`{}::{}`: {error}", stringify!($name), stringify!($field))
+                    })?;
+                    end = expected + <$type as $crate::uniform_buffer::Std140>::SIZE;
+                )*
+
+                let size = ::std::mem::size_of::<$name>();
+                let std140_size = <$name as $crate::uniform_buffer::Std140>::SIZE;
+                if size != std140_size {
+                    return Err(format!(
+                        "`{}` is {size} bytes, std140 expects {std140_size}, add trailing padding",
+                        stringify!($name)
+                    ));
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+/// Buffer backing a GLSL uniform block, bound to a fixed binding point.
+///
+/// Every program whose block is bound to the same point, see `Shader::bind_uniform_block`,
+/// reads the data uploaded here. Blocks named in `RESERVED_BLOCKS` are bound automatically.
+/// Must be created and dropped while the OpenGL context is current.
+#[derive(Debug)]
+pub struct UniformBuffer<T: Std140> {
+    buffer_id: u32,
+    binding: u32,
+    _data: PhantomData<T>,
+}
+
+impl<T: Std140> UniformBuffer<T> {
+    /// Fails if the layout of `T` does not follow std140.
+    pub fn new(binding: u32, data: &T) -> Result<UniformBuffer<T>, WhirlwingError> {
+        T::check_layout().map_err(|error| {
+            WhirlwingError::new(
+                format!("Invalid std140 layout: {error}"),
+                WhirlwingErrorKind::UniformFailure,
+            )
+        })?;
+
+        unsafe {
+            let mut buffer_id = 0;
+            gl::GenBuffers(1, &mut buffer_id);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, buffer_id);
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                size_of::<T>() as isize,
+                data as *const T as *const c_void,
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, buffer_id);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+
+            Ok(UniformBuffer {
+                buffer_id,
+                binding,
+                _data: PhantomData,
+            })
+        }
+    }
+
+    pub fn update(&self, data: &T) {
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.buffer_id);
+            gl::BufferSubData(
+                gl::UNIFORM_BUFFER,
+                0,
+                size_of::<T>() as isize,
+                data as *const T as *const c_void,
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+    }
+
+    /// Binds the buffer to its binding point, in case another buffer was bound there.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, self.binding, self.buffer_id);
+        }
+    }
+
+    pub fn binding(&self) -> u32 {
+        self.binding
+    }
+
+    pub fn id(&self) -> u32 {
+        self.buffer_id
+    }
+}
+
+impl<T: Std140> Drop for UniformBuffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.buffer_id);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::{num::NonZeroU32, path::Path};
+
+use glutin::{
+    config::ConfigTemplateBuilder,
+    context::{ContextApi, ContextAttributesBuilder, Version},
+    surface::{SurfaceAttributesBuilder, SwapInterval, WindowSurface},
+};
+use serde::Deserialize;
+use winit::{
+    dpi::PhysicalSize,
+    event_loop::EventLoopWindowTarget,
+    window::{Fullscreen, WindowBuilder},
+};
+use wwg_error::{WhirlwingError, WhirlwingErrorKind};
+
+/// Window and OpenGL context settings used by `App` on startup.
+///
+/// Every field has a default, so a TOML file only has to list the values it changes:
+///
+/// ```toml
+/// title = "My Game"
+/// size = [1280, 720]
+/// mode = "borderless"
+/// vsync = "off"
+/// samples = 4
+/// srgb = true
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    pub title: String,
+    pub size: [u32; 2],
+    pub min_size: Option<[u32; 2]>,
+    pub max_size: Option<[u32; 2]>,
+    pub resizable: bool,
+    pub transparent: bool,
+    pub mode: WindowMode,
+    pub vsync: VSync,
+    /// MSAA sample count, a power of two. 0 disables multisampling.
+    ///
+    /// While the post-processing stack is active its scene target is multisampled instead
+    /// of the default framebuffer.
+    pub samples: u8,
+    /// Request an sRGB default framebuffer, which encodes the linear colours written by
+    /// shaders for display. Without one `App` adds a `gamma` pass to the post-processing
+    /// stack.
+    pub srgb: bool,
+    /// Desktop OpenGL by default, the bundled shaders are GLSL 3.30 core which OpenGL ES
+    /// cannot compile. Shaders for OpenGL ES contexts need `#version 300 es` or newer.
+    pub gl_api: GlApi,
+    pub gl_version: [u8; 2],
+    pub gl_profile: GlProfile,
+    pub debug_context: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowMode {
+    Windowed,
+    /// Fullscreen window covering the primary monitor without changing its video mode.
+    Borderless,
+    /// Exclusive fullscreen using the primary monitor video mode closest to `WindowConfig::size`.
+    Fullscreen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VSync {
+    Off,
+    On,
+    /// Wait for the given number of vertical blanks between swaps.
+    Interval(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GlApi {
+    OpenGl,
+    Gles,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GlProfile {
+    Core,
+    Compatibility,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            title: "Whirlwing Window".to_string(),
+            size: [800, 600],
+            min_size: None,
+            max_size: None,
+            resizable: true,
+            transparent: true,
+            mode: WindowMode::Windowed,
+            vsync: VSync::On,
+            samples: 0,
+            srgb: true,
+            gl_api: GlApi::OpenGl,
+            gl_version: [3, 3],
+            gl_profile: GlProfile::Core,
+            debug_context: cfg!(debug_assertions),
+        }
+    }
+}
+
+impl WindowConfig {
+    pub fn from_toml_str(toml: &str) -> Result<WindowConfig, WhirlwingError> {
+        let config: WindowConfig = toml::from_str(toml).map_err(|error| {
+            WhirlwingError::new_with_source(
+                "Failed to parse window config".to_string(),
+                WhirlwingErrorKind::ConfigLoadFailure,
+                Box::new(error),
+            )
+        })?;
+
+        // glutin asserts on sample counts which are not a power of two.
+        if config.samples > 0 && !config.samples.is_power_of_two() {
+            return Err(WhirlwingError::new(
+                format!(
+                    "MSAA sample count must be a power of two, got {}",
+                    config.samples
+                ),
+                WhirlwingErrorKind::ConfigLoadFailure,
+            ));
+        }
+
+        Ok(config)
+    }
+
+    pub fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<WindowConfig, WhirlwingError> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path).map_err(|error| {
+            WhirlwingError::new_with_source(
+                format!("Failed to read from path: {}", path.display()),
+                WhirlwingErrorKind::ConfigLoadFailure,
+                Box::new(error),
+            )
+        })?;
+        Self::from_toml_str(&toml)
+    }
+
+    pub(crate) fn window_builder<T>(&self, elwt: &EventLoopWindowTarget<T>) -> WindowBuilder {
+        let mut builder = WindowBuilder::new()
+            .with_title(self.title.clone())
+            .with_inner_size(PhysicalSize::new(self.size[0], self.size[1]))
+            .with_resizable(self.resizable)
+            .with_transparent(self.transparent)
+            .with_fullscreen(self.fullscreen(elwt));
+
+        if let Some([width, height]) = self.min_size {
+            builder = builder.with_min_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some([width, height]) = self.max_size {
+            builder = builder.with_max_inner_size(PhysicalSize::new(width, height));
+        }
+        builder
+    }
+
+    /// glutin has no sRGB field in the template, `App` prefers sRGB capable configs among
+    /// the matching ones instead.
+    pub(crate) fn config_template(&self) -> ConfigTemplateBuilder {
+        let template = ConfigTemplateBuilder::new()
+            .with_alpha_size(8)
+            .with_transparency(cfg!(cgl_backend) && self.transparent);
+
+        if self.samples > 0 {
+            template.with_multisampling(self.samples)
+        } else {
+            template
+        }
+    }
+
+    pub(crate) fn surface_attributes(&self) -> SurfaceAttributesBuilder<WindowSurface> {
+        SurfaceAttributesBuilder::new().with_srgb(Some(self.srgb))
+    }
+
+    pub(crate) fn context_attributes(&self) -> ContextAttributesBuilder {
+        let version = Some(Version::new(self.gl_version[0], self.gl_version[1]));
+        let api = match self.gl_api {
+            GlApi::OpenGl => ContextApi::OpenGl(version),
+            GlApi::Gles => ContextApi::Gles(version),
+        };
+        let profile = match self.gl_profile {
+            GlProfile::Core => glutin::context::GlProfile::Core,
+            GlProfile::Compatibility => glutin::context::GlProfile::Compatibility,
+        };
+
+        let attributes = ContextAttributesBuilder::new()
+            .with_profile(profile)
+            .with_context_api(api);
+
+        if self.debug_context && cfg!(not(wgl_backend)) {
+            attributes
+                .with_debug(true)
+                .with_robustness(glutin::context::Robustness::RobustLoseContextOnReset)
+        } else {
+            attributes
+        }
+    }
+
+    pub(crate) fn swap_interval(&self) -> SwapInterval {
+        match self.vsync {
+            VSync::Off | VSync::Interval(0) => SwapInterval::DontWait,
+            VSync::On => SwapInterval::Wait(NonZeroU32::new(1).unwrap()),
+            VSync::Interval(interval) => SwapInterval::Wait(NonZeroU32::new(interval).unwrap()),
+        }
+    }
+
+    fn fullscreen<T>(&self, elwt: &EventLoopWindowTarget<T>) -> Option<Fullscreen> {
+        match self.mode {
+            WindowMode::Windowed => None,
+            WindowMode::Borderless => Some(Fullscreen::Borderless(None)),
+            WindowMode::Fullscreen => {
+                let [width, height] = self.size;
+                let video_mode = elwt.primary_monitor()?.video_modes().min_by_key(|mode| {
+                    let size = mode.size();
+                    size.width.abs_diff(width) + size.height.abs_diff(height)
+                });
+                match video_mode {
+                    Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                    None => {
+                        wwg_log::wwg_warn!("No video mode available, falling back to borderless");
+                        Some(Fullscreen::Borderless(None))
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+mod common;
+
+use common::headless;
+use whirlwing::{
+    assets::{Handle, LoadState},
+    texture::TextureDesc,
+    error::WhirlwingErrorKind,
+};
+
+#[test]
+fn loads_are_deduplicated_by_path() {
+    let (_context, mut assets) = headless([64, 64], true, true);
+    let desc = TextureDesc::default();
+
+    let first = assets.load_texture("textures/wall.jpg", &desc).unwrap();
+    let second = assets.load_texture("textures/wall.jpg", &desc).unwrap();
+    assert!(Handle::ptr_eq(&first, &second));
+
+    let other = assets.load_texture("textures/container.jpg", &desc).unwrap();
+    assert!(!Handle::ptr_eq(&first, &other));
+}
+
+#[test]
+fn dropped_assets_are_reloaded() {
+    let (_context, mut assets) = headless([64, 64], true, true);
+
+    let mesh = assets.load_mesh("meshes/quad.obj").unwrap();
+    assert_eq!(mesh.get().unwrap().count(), 6);
+    drop(mesh);
+
+    let mesh = assets.load_mesh("meshes/quad.obj").unwrap();
+    let clone = mesh.clone();
+    assert_eq!(mesh.get().unwrap().count(), 6);
+    assert!(Handle::ptr_eq(&mesh, &clone));
+}
+
+#[test]
+fn missing_file_is_an_error() {
+    let (_context, mut assets) = headless([64, 64], true, true);
+
+    let error = assets
+        .load_texture("textures/missing.png", &TextureDesc::default())
+        .unwrap_err();
+    assert_eq!(*error.kind(), WhirlwingErrorKind::TextureLoadFailure);
+}
+
+#[test]
+fn async_loads_are_uploaded_on_update() {
+    let (_context, mut assets) = headless([64, 64], true, true);
+
+    let texture = assets.load_texture_async("textures/wall.jpg", &TextureDesc::default());
+    let mesh = assets.load_mesh_async("meshes/quad.obj");
+    let missing = assets.load_mesh_async("meshes/missing.obj");
+    assert!(matches!(texture.state(), LoadState::Pending));
+    assert!(Handle::ptr_eq(
+        &texture,
+        &assets.load_texture_async("textures/wall.jpg", &TextureDesc::default())
+    ));
+
+    while assets.pending_loads() > 0 {
+        assets.update();
+        std::thread::yield_now();
+    }
+
+    assert_eq!(texture.get().unwrap().width(), 512);
+    assert_eq!(mesh.get().unwrap().count(), 6);
+    match missing.state() {
+        LoadState::Failed(error) => {
+            assert_eq!(*error.kind(), WhirlwingErrorKind::MeshLoadFailure)
+        }
+        state => panic!("Expected failed load, got {state:?}"),
+    }
+}
+
+#[test]
+fn sync_load_finishes_pending_handle() {
+    let (_context, mut assets) = headless([64, 64], true, true);
+
+    let pending = assets.load_texture_async("textures/wall.jpg", &TextureDesc::default());
+    let loaded = assets
+        .load_texture("textures/wall.jpg", &TextureDesc::default())
+        .unwrap();
+    assert!(Handle::ptr_eq(&pending, &loaded));
+    assert!(pending.is_loaded());
+
+    assets.wait();
+    assert_eq!(assets.pending_loads(), 0);
+}
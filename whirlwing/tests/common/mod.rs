@@ -0,0 +1,234 @@
+//! Golden-image harness shared by the renderer integration tests.
+//!
+//! A scene is rendered offscreen for a number of frames with a fixed clock, the last frame
+//! is read back and compared against `res/golden/<name>.png`. On mismatch the rendered frame
+//! and a diff image are written to `target/golden/` next to each other.
+//!
+//! Set `WWG_BLESS=1` to (re)write the reference images instead of comparing against them.
+
+// Every test crate compiles its own copy of this module and uses only part of it.
+#![allow(dead_code)]
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use image::{Rgba, RgbaImage};
+use whirlwing::{
+    assets::Assets,
+    camera::Camera,
+    headless::HeadlessContext,
+    light::{Light, LightKind},
+    math::{Rotor3, Similarity3, Vec3},
+    scene::{Attachment, NodeId},
+    time::Time,
+    window::WindowConfig,
+};
+
+pub struct Scene {
+    pub name: &'static str,
+    pub camera: Camera,
+    pub size: [u32; 2],
+    pub frames: u32,
+    pub delta_time: Duration,
+    /// Largest accepted difference of a single colour channel.
+    pub tolerance: u8,
+}
+
+impl Scene {
+    pub fn new(name: &'static str, camera: Camera) -> Self {
+        Scene {
+            name,
+            camera,
+            size: [160, 120],
+            frames: 1,
+            delta_time: Duration::from_millis(16),
+            tolerance: 2,
+        }
+    }
+}
+
+pub fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap().to_path_buf()
+}
+
+/// Renders the scene and panics with a description of the mismatch if it differs from
+/// the reference image.
+pub fn assert_golden(scene: &Scene) {
+    let actual = render(scene);
+    let reference_path = workspace_root().join(format!("res/golden/{}.png", scene.name));
+
+    if std::env::var_os("WWG_BLESS").is_some() {
+        std::fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+        actual.save(&reference_path).unwrap();
+        return;
+    }
+
+    let reference = match image::open(&reference_path) {
+        Ok(reference) => reference.into_rgba8(),
+        Err(error) => panic!(
+            "Failed to open reference image {}: {error}\nRun with WWG_BLESS=1 to create it.",
+            reference_path.display()
+        ),
+    };
+
+    if let Some(mismatch) = compare(&reference, &actual, scene.tolerance) {
+        let output_dir = workspace_root().join("target/golden");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let actual_path = output_dir.join(format!("{}.actual.png", scene.name));
+        actual.save(&actual_path).unwrap();
+
+        let mut message = format!("Scene `{}` does not match its reference: {mismatch}", scene.name);
+        if let Mismatch::Pixels { diff, .. } = &mismatch {
+            let diff_path = output_dir.join(format!("{}.diff.png", scene.name));
+            diff.save(&diff_path).unwrap();
+            message += &format!("\nDiff written to {}", diff_path.display());
+        }
+        panic!("{message}\nRendered frame written to {}", actual_path.display());
+    }
+}
+
+/// Offscreen context of `size` with assets rooted at `res`. Without `tonemap` the default
+/// tonemapping pass is removed, so colours can be compared exactly.
+pub fn headless(size: [u32; 2], srgb: bool, tonemap: bool) -> (HeadlessContext, Assets) {
+    let config = WindowConfig {
+        size,
+        srgb,
+        ..WindowConfig::default()
+    };
+    let mut assets = Assets::new(workspace_root().join("res"));
+    let mut context = match HeadlessContext::new(&config, &mut assets) {
+        Ok(context) => context,
+        Err(error) => panic!("{error}"),
+    };
+    if !tonemap {
+        context.post_process_mut().remove("tonemap");
+    }
+    (context, assets)
+}
+
+/// Adds the unit cube with the container material to the scene of `context`, turned by
+/// 30 degrees around the vertical axis, and the sun lighting it.
+pub fn add_cube(context: &mut HeadlessContext, assets: &mut Assets) -> NodeId {
+    let mesh = assets.load_mesh("meshes/cube.obj").unwrap();
+    let material = assets.load_material("materials/container.toml").unwrap();
+    let rotation = Rotor3::from_rotation_xz(f32::to_radians(30.0));
+    let scene = context.scene_mut();
+    let cube = scene.add_node("cube", Similarity3::new(Vec3::zero(), rotation, 1.0));
+    scene
+        .attach(cube, Attachment::Model { mesh, material })
+        .unwrap();
+
+    // Lights shine along -Z of their node.
+    let direction = Vec3::new(-0.3, -0.6, -1.0).normalized();
+    let rotation = Rotor3::from_rotation_between(Vec3::new(0.0, 0.0, -1.0), direction);
+    let sun = scene.add_node("sun", Similarity3::new(Vec3::zero(), rotation, 1.0));
+    let light = Light::new(LightKind::Directional, Vec3::one(), 3.0);
+    scene.attach(sun, Attachment::Light(light)).unwrap();
+    cube
+}
+
+/// Renders the cube, see `add_cube`, seen from the camera of `scene`.
+pub fn render(scene: &Scene) -> RgbaImage {
+    let (mut context, mut assets) = headless(scene.size, true, true);
+    add_cube(&mut context, &mut assets);
+    assets.wait();
+
+    let mut time = Time::fixed(Duration::ZERO, scene.delta_time);
+    for _ in 0..scene.frames {
+        context.redraw(&scene.camera, &time);
+        time.advance();
+    }
+    context.read_pixels()
+}
+
+/// Width and height of the area whose colour differs from the top left pixel by more
+/// than `tolerance` in a channel, `None` if there is none.
+pub fn bounding_box(image: &RgbaImage, tolerance: u8) -> Option<(u32, u32)> {
+    let background = image.get_pixel(0, 0).0;
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let differs = pixel
+            .0
+            .iter()
+            .zip(background)
+            .any(|(a, b)| a.abs_diff(b) > tolerance);
+        if differs {
+            let (min_x, min_y, max_x, max_y) = bounds.unwrap_or((x, y, x, y));
+            bounds = Some((min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)));
+        }
+    }
+    bounds.map(|(min_x, min_y, max_x, max_y)| (max_x - min_x + 1, max_y - min_y + 1))
+}
+
+pub enum Mismatch {
+    Size {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    Pixels {
+        count: usize,
+        max_difference: u8,
+        diff: RgbaImage,
+    },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::Size { expected, actual } => {
+                write!(f, "expected size {expected:?}, got {actual:?}")
+            }
+            Mismatch::Pixels {
+                count,
+                max_difference,
+                ..
+            } => write!(
+                f,
+                "{count} pixels differ, largest channel difference is {max_difference}"
+            ),
+        }
+    }
+}
+
+/// Compares images channel by channel. The diff image shows the rendered frame dimmed
+/// to grey with every pixel outside of the tolerance painted red.
+pub fn compare(reference: &RgbaImage, actual: &RgbaImage, tolerance: u8) -> Option<Mismatch> {
+    if reference.dimensions() != actual.dimensions() {
+        return Some(Mismatch::Size {
+            expected: reference.dimensions(),
+            actual: actual.dimensions(),
+        });
+    }
+
+    let mut count = 0;
+    let mut max_difference = 0;
+    let diff = RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let expected = reference.get_pixel(x, y);
+        let pixel = actual.get_pixel(x, y);
+        let difference = expected
+            .0
+            .iter()
+            .zip(pixel.0.iter())
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap();
+        max_difference = max_difference.max(difference);
+
+        if difference > tolerance {
+            count += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let [r, g, b, _] = pixel.0;
+            let grey = ((r as u32 + g as u32 + b as u32) / 6) as u8;
+            Rgba([grey, grey, grey, 255])
+        }
+    });
+
+    (count > 0).then_some(Mismatch::Pixels {
+        count,
+        max_difference,
+        diff,
+    })
+}
@@ -0,0 +1,193 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+use std::ffi::c_void;
+
+mod common;
+
+use common::workspace_root;
+use whirlwing::{
+    assets::Assets,
+    compute::{memory_barrier, ComputeProgram, MemoryBarrier},
+    error::WhirlwingErrorKind,
+    headless::HeadlessContext,
+    shader::{ShaderProgramBuilder, ShaderStage},
+    window::WindowConfig,
+};
+
+fn setup(gl_version: [u8; 2]) -> (HeadlessContext, Assets) {
+    let mut assets = Assets::new(workspace_root().join("res"));
+    let config = WindowConfig {
+        gl_version,
+        ..WindowConfig::default()
+    };
+    let context = HeadlessContext::new(&config, &mut assets).unwrap();
+    (context, assets)
+}
+
+fn context_version() -> (i32, i32) {
+    let (mut major, mut minor) = (0, 0);
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+    (major, minor)
+}
+
+const VERTEX_SHADER: &str = "#version 330 core
+layout (location = 0) in vec3 aPos;
+void main() { gl_Position = vec4(aPos, 1.0); }
+";
+
+const GEOMETRY_SHADER: &str = "#version 330 core
+layout (points) in;
+layout (triangle_strip, max_vertices = 4) out;
+uniform float size;
+void main() {
+    for (int i = 0; i < 4; i++) {
+        vec2 corner = vec2(i % 2, i / 2) - 0.5;
+        gl_Position = gl_in[0].gl_Position + vec4(corner * size, 0.0, 0.0);
+        EmitVertex();
+    }
+    EndPrimitive();
+}
+";
+
+const FRAGMENT_SHADER: &str = "#version 330 core
+out vec4 FragColor;
+void main() { FragColor = vec4(1.0); }
+";
+
+const TESS_CONTROL_SHADER: &str = "#version 400 core
+layout (vertices = 3) out;
+uniform float level;
+void main() {
+    gl_out[gl_InvocationID].gl_Position = gl_in[gl_InvocationID].gl_Position;
+    gl_TessLevelOuter[0] = level;
+    gl_TessLevelOuter[1] = level;
+    gl_TessLevelOuter[2] = level;
+    gl_TessLevelInner[0] = level;
+}
+";
+
+const TESS_EVALUATION_SHADER: &str = "#version 400 core
+layout (triangles) in;
+void main() {
+    gl_Position = gl_TessCoord.x * gl_in[0].gl_Position
+        + gl_TessCoord.y * gl_in[1].gl_Position
+        + gl_TessCoord.z * gl_in[2].gl_Position;
+}
+";
+
+const COMPUTE_SHADER: &str = "#version 430 core
+layout (local_size_x = 64) in;
+layout (std430, binding = 0) buffer Particles { float values[]; };
+uniform uint count;
+uniform float step;
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    if (index < count) {
+        values[index] += step * float(index);
+    }
+}
+";
+
+#[test]
+fn builder_links_any_stage_combination() {
+    let (_context, _assets) = setup([4, 3]);
+
+    let geometry = ShaderProgramBuilder::new()
+        .with_source(ShaderStage::Fragment, FRAGMENT_SHADER)
+        .with_source(ShaderStage::Geometry, GEOMETRY_SHADER)
+        .with_source(ShaderStage::Vertex, VERTEX_SHADER)
+        .build()
+        .unwrap();
+    assert!(geometry.uniform("size").is_some());
+
+    let tessellation = ShaderProgramBuilder::new()
+        .with_source(ShaderStage::Vertex, VERTEX_SHADER)
+        .with_source(ShaderStage::TessControl, TESS_CONTROL_SHADER)
+        .with_source(ShaderStage::TessEvaluation, TESS_EVALUATION_SHADER)
+        .with_source(ShaderStage::Fragment, FRAGMENT_SHADER)
+        .build()
+        .unwrap();
+    assert!(tessellation.uniform("level").is_some());
+}
+
+#[test]
+fn builder_rejects_incomplete_pipelines() {
+    let (_context, _assets) = setup([4, 3]);
+
+    let without_vertex = ShaderProgramBuilder::new()
+        .with_source(ShaderStage::Fragment, FRAGMENT_SHADER)
+        .build();
+    assert!(without_vertex.is_err());
+
+    let without_evaluation = ShaderProgramBuilder::new()
+        .with_source(ShaderStage::Vertex, VERTEX_SHADER)
+        .with_source(ShaderStage::TessControl, TESS_CONTROL_SHADER)
+        .build();
+    assert!(without_evaluation.is_err());
+
+    let compute = ShaderProgramBuilder::new()
+        .with_source(ShaderStage::Vertex, VERTEX_SHADER)
+        .with_source(ShaderStage::Compute, COMPUTE_SHADER)
+        .build();
+    assert!(compute.is_err());
+}
+
+#[test]
+fn compute_program_updates_a_buffer() {
+    let (_context, _assets) = setup([4, 3]);
+    let program = ComputeProgram::from_source(COMPUTE_SHADER).unwrap();
+    assert_eq!(program.work_group_size(), [64, 1, 1]);
+
+    const COUNT: usize = 100;
+    let mut values = [1.0f32; COUNT];
+    let mut buffer = 0;
+    unsafe {
+        gl::GenBuffers(1, &mut buffer);
+        gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, buffer);
+        gl::BufferData(
+            gl::SHADER_STORAGE_BUFFER,
+            std::mem::size_of_val(&values) as isize,
+            values.as_ptr() as *const c_void,
+            gl::DYNAMIC_DRAW,
+        );
+        gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 0, buffer);
+    }
+
+    program.shader().bind();
+    program.shader().set("count", &(COUNT as u32)).unwrap();
+    program.shader().set("step", &0.5f32).unwrap();
+    program.dispatch_invocations([COUNT as u32, 1, 1]);
+    memory_barrier(MemoryBarrier::BUFFER_UPDATE | MemoryBarrier::SHADER_STORAGE);
+
+    unsafe {
+        gl::GetBufferSubData(
+            gl::SHADER_STORAGE_BUFFER,
+            0,
+            std::mem::size_of_val(&values) as isize,
+            values.as_mut_ptr() as *mut c_void,
+        );
+        gl::DeleteBuffers(1, &buffer);
+    }
+    for (index, value) in values.iter().enumerate() {
+        assert_eq!(*value, 1.0 + 0.5 * index as f32);
+    }
+}
+
+#[test]
+fn old_contexts_report_missing_stages() {
+    let (_context, _assets) = setup([3, 3]);
+    if context_version() >= (4, 3) {
+        // The driver gave us a newer context than requested, nothing to check.
+        return;
+    }
+
+    let error = ComputeProgram::from_source(COMPUTE_SHADER).unwrap_err();
+    assert_eq!(*error.kind(), WhirlwingErrorKind::UnsupportedFeature);
+    assert!(
+        error.content().contains("Compute Shader needs OpenGL 4.3"),
+        "{error}"
+    );
+}
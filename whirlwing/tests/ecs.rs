@@ -0,0 +1,166 @@
+use std::{cell::RefCell, rc::Rc};
+
+use whirlwing::{
+    ecs::World,
+    error::WhirlwingErrorKind,
+    schedule::{Commands, Schedule, Stage},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Position(f32);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Velocity(f32);
+
+#[derive(Debug, PartialEq)]
+struct Name(&'static str);
+
+#[derive(Debug, Default)]
+struct Frames(u32);
+
+#[test]
+fn queries_visit_matching_entities() {
+    let mut world = World::new();
+    let a = world.spawn((Position(0.0), Velocity(1.0)));
+    let b = world.spawn((Position(10.0), Velocity(-2.0), Name("b")));
+    let c = world.spawn((Position(5.0),));
+    let d = world.spawn((Name("d"),));
+
+    for (_, (position, velocity)) in world.query::<(&mut Position, &Velocity)>().iter() {
+        position.0 += velocity.0;
+    }
+    assert_eq!(*world.get::<Position>(a).unwrap(), Position(1.0));
+    assert_eq!(*world.get::<Position>(b).unwrap(), Position(8.0));
+    assert_eq!(*world.get::<Position>(c).unwrap(), Position(5.0));
+
+    let mut named: Vec<_> = world
+        .query::<(&Name, Option<&Position>)>()
+        .iter()
+        .map(|(entity, (name, position))| (entity, name.0, position.copied()))
+        .collect();
+    named.sort_by_key(|(entity, ..)| *entity);
+    assert_eq!(named, [(b, "b", Some(Position(8.0))), (d, "d", None)]);
+
+    let mut optional = world.query::<(Option<&mut Velocity>,)>();
+    assert_eq!(optional.iter().count(), 4);
+    assert!(optional.get(d).unwrap().0.is_none());
+    drop(optional);
+
+    // Components nobody inserted match nothing.
+    assert_eq!(world.query::<(&Position, &Frames)>().iter().count(), 0);
+}
+
+#[test]
+fn despawned_entities_lose_their_components() {
+    let mut world = World::new();
+    let a = world.spawn((Position(1.0), Name("a")));
+    let b = world.spawn((Position(2.0),));
+    assert_eq!(world.len(), 2);
+
+    world.despawn(a).unwrap();
+    assert!(!world.contains(a));
+    assert!(world.get::<Name>(a).is_none());
+    assert_eq!(
+        *world.despawn(a).unwrap_err().kind(),
+        WhirlwingErrorKind::EcsFailure
+    );
+    assert_eq!(
+        *world.insert(a, (Velocity(0.0),)).unwrap_err().kind(),
+        WhirlwingErrorKind::EcsFailure
+    );
+
+    // The slot of `a` is reused under a new id.
+    let c = world.spawn((Velocity(3.0),));
+    assert_ne!(a, c);
+    assert!(world.get::<Position>(c).is_none());
+    assert_eq!(*world.get::<Position>(b).unwrap(), Position(2.0));
+
+    world.insert(b, (Velocity(4.0), Position(5.0))).unwrap();
+    assert_eq!(*world.get::<Position>(b).unwrap(), Position(5.0));
+    assert_eq!(world.remove::<Velocity>(b), Some(Velocity(4.0)));
+    assert_eq!(world.query::<&Velocity>().iter().count(), 1);
+    world.get_mut::<Position>(b).unwrap().0 = 6.0;
+    assert_eq!(*world.get::<Position>(b).unwrap(), Position(6.0));
+}
+
+#[test]
+fn resources_are_shared_by_type() {
+    let mut world = World::new();
+    assert!(world.resource::<Frames>().is_none());
+    assert!(world.insert_resource(Frames(1)).is_none());
+    world.resource_mut::<Frames>().unwrap().0 += 1;
+    assert_eq!(world.resource::<Frames>().unwrap().0, 2);
+    assert_eq!(world.insert_resource(Frames(7)).unwrap().0, 2);
+    assert_eq!(world.remove_resource::<Frames>().unwrap().0, 7);
+    assert!(!world.contains_resource::<Frames>());
+}
+
+#[test]
+#[should_panic(expected = "already borrowed")]
+fn conflicting_borrows_panic() {
+    let mut world = World::new();
+    world.spawn((Position(0.0),));
+    let _ = world.query::<(&mut Position, &mut Position)>();
+}
+
+#[test]
+fn stages_run_in_order_and_apply_commands() {
+    let mut world = World::new();
+    world.insert_resource(Frames::default());
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut schedule = Schedule::new();
+
+    let render_log = log.clone();
+    schedule.add_system(Stage::Render, move |world: &World, _: &mut Commands| {
+        let count = world.query::<&Position>().iter().count();
+        render_log.borrow_mut().push(format!("render {count}"));
+    });
+    let update_log = log.clone();
+    schedule.add_system(
+        Stage::Update,
+        move |world: &World, commands: &mut Commands| {
+            let mut frames = world.resource_mut::<Frames>().unwrap();
+            frames.0 += 1;
+            if frames.0 == 1 {
+                let entity = commands.spawn((Position(0.0), Velocity(1.0)));
+                // Reserved right away, the components arrive at the end of the stage.
+                assert!(world.contains(entity));
+                assert!(world.get::<Position>(entity).is_none());
+            }
+            let count = world.query::<&Position>().iter().count();
+            update_log.borrow_mut().push(format!("update {count}"));
+        },
+    );
+    schedule.add_system(
+        Stage::PostUpdate,
+        |world: &World, commands: &mut Commands| {
+            for (entity, (position, velocity)) in world.query::<(&mut Position, &Velocity)>().iter()
+            {
+                position.0 += velocity.0;
+                if position.0 >= 2.0 {
+                    commands.despawn(entity);
+                }
+            }
+        },
+    );
+    let pre_update_log = log.clone();
+    schedule.add_system(Stage::PreUpdate, move |_: &World, _: &mut Commands| {
+        pre_update_log.borrow_mut().push("pre update".to_string());
+    });
+
+    schedule.run(&mut world);
+    schedule.run(&mut world);
+    assert_eq!(
+        *log.borrow(),
+        [
+            "pre update",
+            "update 0",
+            "render 1",
+            "pre update",
+            "update 1",
+            "render 0",
+        ]
+    );
+    assert!(world.is_empty());
+    assert_eq!(world.resource::<Frames>().unwrap().0, 2);
+}
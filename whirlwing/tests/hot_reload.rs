@@ -0,0 +1,114 @@
+#![cfg(all(
+    feature = "hot_reload",
+    not(any(target_os = "macos", target_os = "ios"))
+))]
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+mod common;
+
+use common::headless;
+use whirlwing::assets::Assets;
+
+const VERTEX_SHADER: &str = "#version 330 core
+layout (location = 0) in vec3 aPos;
+void main() { gl_Position = vec4(aPos, 1.0); }
+";
+
+const FRAGMENT_SHADER: &str = "#version 330 core
+out vec4 FragColor;
+uniform vec4 color;
+void main() { FragColor = color; }
+";
+
+fn shader_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("shader.vert"), VERTEX_SHADER).unwrap();
+    fs::write(dir.join("shader.frag"), FRAGMENT_SHADER).unwrap();
+    dir
+}
+
+/// Calls `update` until `done` returns true, the file watcher reports changes asynchronously.
+fn update_until(assets: &mut Assets, mut done: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        assets.update();
+        if done() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+#[test]
+fn changed_shader_is_recompiled() {
+    let dir = shader_dir("hot_reload_changed");
+    let (_context, mut assets) = headless([64, 64], true, true);
+    assets.enable_hot_reload().unwrap();
+
+    let shader = assets
+        .load_shader(dir.join("shader.vert"), dir.join("shader.frag"))
+        .unwrap();
+    let old_id = shader.get().unwrap().id();
+
+    let changed = FRAGMENT_SHADER.replace("FragColor = color", "FragColor = color * 0.5");
+    fs::write(dir.join("shader.frag"), changed).unwrap();
+
+    assert!(update_until(&mut assets, || shader.get().unwrap().id() != old_id));
+}
+
+#[test]
+fn broken_shader_keeps_previous_program() {
+    let dir = shader_dir("hot_reload_broken");
+    let (_context, mut assets) = headless([64, 64], true, true);
+
+    // Shaders loaded before enabling hot reload are watched as well.
+    let shader = assets
+        .load_shader(dir.join("shader.vert"), dir.join("shader.frag"))
+        .unwrap();
+    assets.enable_hot_reload().unwrap();
+    let old_id = shader.get().unwrap().id();
+
+    fs::write(
+        dir.join("shader.frag"),
+        "#version 330 core\nvoid main() { oops }\n",
+    )
+    .unwrap();
+    update_until(&mut assets, || false);
+    assert_eq!(shader.get().unwrap().id(), old_id);
+
+    fs::write(dir.join("shader.frag"), FRAGMENT_SHADER).unwrap();
+    assert!(update_until(&mut assets, || shader.get().unwrap().id() != old_id));
+}
+
+#[test]
+fn changed_include_recompiles_the_shader() {
+    let dir = shader_dir("hot_reload_include");
+    fs::write(dir.join("color.glsl"), "const float scale = 1.0;\n").unwrap();
+    let fragment_shader = FRAGMENT_SHADER.replace(
+        "uniform vec4 color;",
+        "uniform vec4 color;\n#include \"color.glsl\"",
+    );
+    fs::write(
+        dir.join("shader.frag"),
+        fragment_shader.replace("= color", "= color * scale"),
+    )
+    .unwrap();
+
+    let (_context, _) = headless([64, 64], true, true);
+    // Includes are resolved relative to the asset root.
+    let mut assets = Assets::new(&dir);
+    assets.enable_hot_reload().unwrap();
+    let shader = assets.load_shader("shader.vert", "shader.frag").unwrap();
+    let old_id = shader.get().unwrap().id();
+
+    fs::write(dir.join("color.glsl"), "const float scale = 0.5;\n").unwrap();
+    assert!(update_until(&mut assets, || shader.get().unwrap().id() != old_id));
+}
@@ -0,0 +1,176 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+use std::time::Duration;
+
+mod common;
+
+use common::{add_cube, headless};
+use whirlwing::{
+    assets::Handle,
+    camera::Camera,
+    headless::HeadlessContext,
+    instancing::{InstanceBuffer, InstanceData},
+    light::MAX_LIGHTS,
+    material::Material,
+    math::{Mat4, Rotor3, Vec3, Vec4},
+    mesh::{AttributeType, VertexLayout},
+    shader::{ShaderProgramBuilder, ShaderStage},
+    time::Time,
+};
+
+const INSTANCED_VERTEX_SHADER: &str = "#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (std140) uniform Camera {
+    mat4 view;
+    mat4 projection;
+    vec3 camera_position;
+};
+layout (location = 3) in mat4 instance_model;
+layout (location = 7) in vec4 instance_tint;
+layout (location = 8) in vec4 instance_data;
+out vec4 tint;
+void main() {
+    gl_Position = projection * view * instance_model * vec4(aPos, 1.0);
+    tint = instance_tint * instance_data.x;
+}
+";
+
+const OFFSET_VERTEX_SHADER: &str = "#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 3) in vec2 offset;
+layout (location = 4) in vec4 color;
+out vec4 tint;
+void main() {
+    gl_Position = vec4(aPos.xy * 0.2 + offset, 0.0, 1.0);
+    tint = color;
+}
+";
+
+const FRAGMENT_SHADER: &str = "#version 330 core
+in vec4 tint;
+out vec4 FragColor;
+void main() { FragColor = tint; }
+";
+
+fn redraw(context: &mut HeadlessContext) {
+    let camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Rotor3::identity());
+    context.redraw(&camera, &Time::fixed(Duration::ZERO, Duration::ZERO));
+}
+
+fn instance(x: f32, y: f32, tint: Vec4) -> InstanceData {
+    InstanceData {
+        tint,
+        data: Vec4::new(1.0, 0.0, 0.0, 0.0),
+        ..InstanceData::new(Mat4::from_translation(Vec3::new(x, y, 0.0)))
+    }
+}
+
+#[test]
+fn repeated_items_become_one_instanced_draw() {
+    let (mut context, mut assets) = headless([64, 64], true, false);
+    add_cube(&mut context, &mut assets);
+    let quad = assets.load_mesh("meshes/quad.obj").unwrap();
+    let shader = ShaderProgramBuilder::new()
+        .with_source(ShaderStage::Vertex, INSTANCED_VERTEX_SHADER)
+        .with_source(ShaderStage::Fragment, FRAGMENT_SHADER)
+        .build()
+        .unwrap();
+    let material = Handle::new(Material::new(Handle::new(shader)));
+
+    let green = Vec4::new(0.0, 1.0, 0.0, 1.0);
+    let blue = Vec4::new(0.0, 0.0, 1.0, 1.0);
+    let queue = context.render_queue_mut();
+    queue.submit_instance(quad.clone(), material.clone(), instance(-1.5, 0.0, green));
+    queue.submit_instance(quad.clone(), material.clone(), instance(1.5, 0.0, blue));
+    // Off screen.
+    for index in 0..38 {
+        let item = instance(index as f32, 50.0, green);
+        queue.submit_instance(quad.clone(), material.clone(), item);
+    }
+    redraw(&mut context);
+
+    let stats = context.render_stats();
+    assert_eq!(stats.items, 41);
+    // The cube of the scene uses an instanced shader as well, with a call of its own.
+    assert_eq!(stats.instances, 41);
+    assert_eq!(stats.draw_calls, 2);
+
+    let pixels = context.read_pixels();
+    assert_eq!(pixels.get_pixel(8, 32).0, [0, 255, 0, 255]);
+    assert_eq!(pixels.get_pixel(56, 32).0, [0, 0, 255, 255]);
+
+    // The queue is emptied, only the cube is left.
+    redraw(&mut context);
+    assert_eq!(context.render_stats().draw_calls, 1);
+    assert_ne!(context.read_pixels().get_pixel(8, 32).0, [0, 255, 0, 255]);
+}
+
+#[test]
+fn engine_materials_are_drawn_instanced() {
+    let (mut context, mut assets) = headless([64, 64], false, false);
+    let quad = assets.load_mesh("meshes/quad.obj").unwrap();
+    let mut lit = Material::lit(&mut assets, MAX_LIGHTS).unwrap();
+    lit.set("specular_color", Vec3::zero());
+    let lit = Handle::new(lit);
+    let container = assets.load_material("materials/container.toml").unwrap();
+    assets.wait();
+
+    let red = Vec4::new(1.0, 0.0, 0.0, 1.0);
+    let queue = context.render_queue_mut();
+    queue.submit_instance(quad.clone(), lit.clone(), instance(-1.5, 0.0, red));
+    queue.submit_instance(quad.clone(), lit, instance(1.5, 0.0, red));
+    for y in [-1.5, 1.5] {
+        let transform = Mat4::from_translation(Vec3::new(0.0, y, 0.0));
+        queue.submit(quad.clone(), container.clone(), transform);
+    }
+    redraw(&mut context);
+
+    let stats = context.render_stats();
+    assert_eq!((stats.instances, stats.draw_calls), (4, 2));
+    // The tint of the instance reaches the lit colour.
+    let [r, g, b, _] = context.read_pixels().get_pixel(8, 32).0;
+    assert!(r > 0 && g == 0 && b == 0, "{:?}", [r, g, b]);
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Offset {
+    offset: [f32; 2],
+    color: [u8; 4],
+}
+
+#[test]
+fn instance_buffers_use_custom_layouts() {
+    let (mut context, mut assets) = headless([64, 64], true, false);
+    let quad = assets.load_mesh("meshes/quad.obj").unwrap();
+    let shader = ShaderProgramBuilder::new()
+        .with_source(ShaderStage::Vertex, OFFSET_VERTEX_SHADER)
+        .with_source(ShaderStage::Fragment, FRAGMENT_SHADER)
+        .build()
+        .unwrap();
+
+    let layout = VertexLayout::new()
+        .push(3, 2, AttributeType::Float, false)
+        .push(4, 4, AttributeType::UnsignedByte, true);
+    let mut instances = InstanceBuffer::new(layout);
+    instances.upload(&[
+        Offset {
+            offset: [-0.8, -0.8],
+            color: [255, 0, 0, 255],
+        },
+        Offset {
+            offset: [0.8, 0.8],
+            color: [0, 0, 255, 255],
+        },
+    ]);
+    assert_eq!(instances.len(), 2);
+
+    redraw(&mut context);
+    shader.bind();
+    quad.get().unwrap().draw_instanced(&instances);
+
+    let pixels = context.read_pixels();
+    // The first row of the image is the top.
+    assert_eq!(pixels.get_pixel(6, 57).0, [255, 0, 0, 255]);
+    assert_eq!(pixels.get_pixel(57, 6).0, [0, 0, 255, 255]);
+}
@@ -0,0 +1,131 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+use std::time::Duration;
+
+mod common;
+
+use common::headless;
+use whirlwing::{
+    assets::{Assets, Handle},
+    camera::Camera,
+    ecs::World,
+    headless::HeadlessContext,
+    light::{Light, LightKind, Lights, MAX_LIGHTS},
+    material::Material,
+    math::{Isometry3, Mat4, Rotor3, Vec3, Vec4},
+    time::Time,
+};
+
+/// Plain linear output, so the lighting can be compared in 8-bit space.
+fn setup() -> (HeadlessContext, Assets) {
+    headless([64, 64], false, false)
+}
+
+fn point(intensity: f32) -> Light {
+    Light::new(LightKind::Point { range: 5.0 }, Vec3::one(), intensity)
+}
+
+/// Red channel in the middle of a dark lit quad covering the centre of the screen,
+/// which a sun submitted after the other lights of the frame lights as well.
+fn render(context: &mut HeadlessContext, assets: &mut Assets, max_lights: usize) -> u8 {
+    let mut material = Material::lit(assets, max_lights).unwrap();
+    material.set("diffuse_color", Vec4::new(0.1, 0.1, 0.1, 1.0));
+    material.set("specular_color", Vec3::zero());
+    let quad = assets.load_mesh("meshes/quad.obj").unwrap();
+    let transform = Mat4::from_translation(Vec3::new(0.0, 0.0, 1.5)) * Mat4::from_scale(3.0);
+    context
+        .render_queue_mut()
+        .submit(quad, Handle::new(material), transform);
+    let sun = Light::new(LightKind::Directional, Vec3::one(), 3.0);
+    let direction = Vec3::new(-0.3, -0.6, -1.0).normalized();
+    context.lights_mut().submit(sun, Vec3::zero(), direction);
+
+    let camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Rotor3::identity());
+    context.redraw(&camera, &Time::fixed(Duration::ZERO, Duration::ZERO));
+    context.read_pixels().get_pixel(32, 32).0[0]
+}
+
+#[test]
+fn light_kinds_brighten_what_they_reach() {
+    let (mut context, mut assets) = setup();
+    let base = render(&mut context, &mut assets, MAX_LIGHTS);
+    assert!(base > 0 && base < 200, "{base}");
+    // Lights only last one frame.
+    assert!(context.lights_mut().is_empty());
+
+    let toward_quad = Vec3::new(0.0, 0.0, -1.0);
+    let lights = context.lights_mut();
+    lights.submit(point(1.0), Vec3::new(0.0, 0.0, 2.0), toward_quad);
+    let near = render(&mut context, &mut assets, MAX_LIGHTS);
+    assert!(near > base + 5, "{near} <= {base}");
+
+    // Out of range.
+    let lights = context.lights_mut();
+    lights.submit(point(1.0), Vec3::new(0.0, 0.0, 7.0), toward_quad);
+    assert_eq!(render(&mut context, &mut assets, MAX_LIGHTS), base);
+
+    let spot = |outer_angle: f32| {
+        let kind = LightKind::Spot {
+            range: 5.0,
+            inner_angle: outer_angle * 0.5,
+            outer_angle,
+        };
+        Light::new(kind, Vec3::one(), 1.0)
+    };
+    // The middle of the quad is 0.5 away from the axis of the spot and 1 in front of it.
+    let offset_spot = Vec3::new(0.5, 0.0, 2.5);
+    context
+        .lights_mut()
+        .submit(spot(0.2), offset_spot, toward_quad);
+    assert_eq!(render(&mut context, &mut assets, MAX_LIGHTS), base);
+    context
+        .lights_mut()
+        .submit(spot(1.2), offset_spot, toward_quad);
+    let inside = render(&mut context, &mut assets, MAX_LIGHTS);
+    assert!(inside > base + 5, "{inside} <= {base}");
+
+    // Submitted lights come before the sun, which is cut off with one light per draw.
+    let dark = Light::new(LightKind::Directional, Vec3::one(), 0.0);
+    context.lights_mut().submit(dark, Vec3::zero(), toward_quad);
+    let ambient_only = render(&mut context, &mut assets, 1);
+    assert!(ambient_only < base, "{ambient_only} >= {base}");
+}
+
+#[test]
+fn lights_are_gathered_and_limited() {
+    let mut world = World::new();
+    let rotation = Rotor3::from_rotation_xz(std::f32::consts::FRAC_PI_2);
+    let placement = Isometry3::new(Vec3::new(1.0, 2.0, 3.0), rotation);
+    world.spawn((point(2.0), placement));
+    world.spawn((point(1.0),));
+
+    let mut lights = Lights::new();
+    lights.gather(&world);
+    assert_eq!(lights.len(), 1);
+    let (light, position, direction) = lights.iter().next().unwrap();
+    assert_eq!(light.intensity, 2.0);
+    assert_eq!(*position, Vec3::new(1.0, 2.0, 3.0));
+    let mut expected = Vec3::new(0.0, 0.0, -1.0);
+    rotation.rotate_vec(&mut expected);
+    assert!((*direction - expected).mag() < 1e-5);
+
+    // Past the capacity directional lights stay, then the closest ones.
+    lights.clear();
+    for distance in (1..=20).rev() {
+        lights.submit(
+            point(1.0),
+            Vec3::new(distance as f32, 0.0, 0.0),
+            Vec3::unit_x(),
+        );
+    }
+    let sun = Light::new(LightKind::Directional, Vec3::one(), 1.0);
+    lights.submit(sun, Vec3::zero(), Vec3::unit_y());
+    let uniforms = lights.uniforms(Vec3::zero());
+    assert_eq!(uniforms.count, MAX_LIGHTS as u32);
+    assert_eq!(uniforms.lights[0].kind, 0);
+    let farthest = uniforms.lights[1..]
+        .iter()
+        .map(|light| light.position.x)
+        .fold(0.0, f32::max);
+    assert_eq!(farthest, (MAX_LIGHTS - 1) as f32);
+}
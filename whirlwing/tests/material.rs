@@ -0,0 +1,188 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+use std::{fs, path::PathBuf};
+
+mod common;
+
+use common::headless;
+use image::{Rgba, RgbaImage};
+use whirlwing::{
+    assets::{Assets, Handle},
+    error::WhirlwingErrorKind,
+    material::{Material, MaterialParam},
+    math::Vec2,
+    shader::Shader,
+};
+
+const VERTEX_SHADER: &str = "#version 330 core
+layout (location = 0) in vec3 aPos;
+uniform vec2 offset;
+void main() { gl_Position = vec4(aPos.xy + offset, aPos.z, 1.0); }
+";
+
+const FRAGMENT_SHADER: &str = "#version 330 core
+out vec4 FragColor;
+uniform sampler2D albedo;
+uniform sampler2D detail;
+uniform vec3 tint;
+uniform vec4 glow;
+uniform float roughness;
+uniform int mode;
+void main() {
+    vec4 color = texture(albedo, vec2(0.5)) * texture(detail, vec2(0.5));
+    FragColor = color * vec4(tint, roughness) + glow * float(mode);
+}
+";
+
+const MATERIAL: &str = r##"
+vertex_shader = "shader.vert"
+fragment_shader = "shader.frag"
+defines = { UNUSED = 1 }
+
+[params]
+albedo = { texture = "albedo.png", wrap = "clamp_to_edge", filter = "nearest" }
+detail = { texture = "detail.png", flip_vertically = true }
+tint = "#ff8000"
+glow = "#00000080"
+roughness = 1
+mode = 2
+offset = [0.25, -0.5]
+unused = 3.5
+"##;
+
+/// Asset root with the test shaders, two textures and `material.toml`.
+fn material_root(name: &str, material: &str) -> PathBuf {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("shader.vert"), VERTEX_SHADER).unwrap();
+    fs::write(root.join("shader.frag"), FRAGMENT_SHADER).unwrap();
+    for texture in ["albedo.png", "detail.png"] {
+        RgbaImage::from_pixel(2, 2, Rgba([255, 255, 255, 255]))
+            .save(root.join(texture))
+            .unwrap();
+    }
+    fs::write(root.join("material.toml"), material).unwrap();
+    root
+}
+
+fn uniform_f32<const N: usize>(shader: &Shader, name: &str) -> [f32; N] {
+    let mut values = [0.0; N];
+    let location = shader.uniform(name).unwrap().location;
+    unsafe { gl::GetUniformfv(shader.id(), location, values.as_mut_ptr()) };
+    values
+}
+
+fn uniform_i32(shader: &Shader, name: &str) -> i32 {
+    let mut value = 0;
+    let location = shader.uniform(name).unwrap().location;
+    unsafe { gl::GetUniformiv(shader.id(), location, &mut value) };
+    value
+}
+
+#[test]
+fn material_files_set_every_parameter() {
+    let (_context, _) = headless([64, 64], true, true);
+    let mut assets = Assets::new(material_root("material_file", MATERIAL));
+
+    let material = assets.load_material("material.toml").unwrap();
+    assets.wait();
+    assert!(Handle::ptr_eq(
+        &material,
+        &assets.load_material("material.toml").unwrap()
+    ));
+
+    let material = material.get().unwrap();
+    assert!(matches!(
+        material.param("tint"),
+        Some(MaterialParam::Vec3(_))
+    ));
+    assert!(matches!(
+        material.param("glow"),
+        Some(MaterialParam::Vec4(_))
+    ));
+    assert!(matches!(
+        material.param("roughness"),
+        Some(MaterialParam::Float(_))
+    ));
+    assert!(matches!(
+        material.param("mode"),
+        Some(MaterialParam::Int(2))
+    ));
+    material.bind().unwrap();
+
+    let shader = material.shader().get().unwrap();
+    // Colours are decoded from sRGB, alpha is not.
+    let decode = |srgb: f32| ((srgb + 0.055) / 1.055).powf(2.4);
+    let tint = uniform_f32::<3>(shader, "tint");
+    assert_eq!(tint, [1.0, decode(128.0 / 255.0), 0.0]);
+    assert!((tint[1] - 0.2158).abs() < 1e-4);
+    assert_eq!(
+        uniform_f32::<4>(shader, "glow"),
+        [0.0, 0.0, 0.0, 128.0 / 255.0]
+    );
+    assert_eq!(uniform_f32::<1>(shader, "roughness"), [1.0]);
+    assert_eq!(uniform_f32::<2>(shader, "offset"), [0.25, -0.5]);
+    assert_eq!(uniform_i32(shader, "mode"), 2);
+
+    // Textures take units in parameter name order.
+    assert_eq!(uniform_i32(shader, "albedo"), 0);
+    assert_eq!(uniform_i32(shader, "detail"), 1);
+    let Some(MaterialParam::Texture(detail)) = material.param("detail") else {
+        panic!("detail is not a texture");
+    };
+    let mut bound = 0;
+    unsafe {
+        gl::ActiveTexture(gl::TEXTURE1);
+        gl::GetIntegerv(gl::TEXTURE_BINDING_2D, &mut bound);
+    }
+    assert_eq!(bound as u32, detail.get().unwrap().id());
+}
+
+#[test]
+fn materials_built_in_code_assign_units_in_order() {
+    let (_context, _) = headless([64, 64], true, true);
+    let root = material_root("material_code", MATERIAL);
+    let mut assets = Assets::new(&root);
+
+    let shader = assets.load_shader("shader.vert", "shader.frag").unwrap();
+    let texture = assets
+        .load_texture("albedo.png", &Default::default())
+        .unwrap();
+    let mut material = Material::new(shader);
+    material.set("detail", texture.clone());
+    material.set("albedo", texture);
+    material.set("offset", Vec2::new(1.0, 2.0));
+    material.set("offset", Vec2::new(3.0, 4.0));
+    assert_eq!(material.params().len(), 3);
+    material.bind().unwrap();
+
+    let shader = material.shader().get().unwrap();
+    assert_eq!(uniform_i32(shader, "detail"), 0);
+    assert_eq!(uniform_i32(shader, "albedo"), 1);
+    assert_eq!(uniform_f32::<2>(shader, "offset"), [3.0, 4.0]);
+}
+
+#[test]
+fn invalid_material_files_are_errors() {
+    let (_context, _) = headless([64, 64], true, true);
+    for (name, params) in [
+        ("material_bad_color", "tint = \"#ff80\""),
+        ("material_bad_vector", "offset = [1.0, 2.0, 3.0, 4.0, 5.0]"),
+        (
+            "material_bad_texture",
+            "albedo = { texture = \"a.png\", wrapping = \"repeat\" }",
+        ),
+    ] {
+        let material = format!(
+            "vertex_shader = \"shader.vert\"\nfragment_shader = \"shader.frag\"\n[params]\n{params}\n"
+        );
+        let mut assets = Assets::new(material_root(name, &material));
+        let error = assets.load_material("material.toml").unwrap_err();
+        assert_eq!(
+            *error.kind(),
+            WhirlwingErrorKind::MaterialLoadFailure,
+            "{error}"
+        );
+    }
+}
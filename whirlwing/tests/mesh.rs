@@ -0,0 +1,111 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+mod common;
+
+use common::headless;
+use whirlwing::{
+    mesh::{AttributeType, Indices, Mesh, VertexLayout},
+    render_target::{RenderTarget, RenderTargetBuilder},
+    shader::{Shader, ShaderProgramBuilder, ShaderStage},
+    texture::TextureFormat,
+};
+
+const VERTEX_SHADER: &str = "#version 330 core
+layout (location = 0) in vec2 aPos;
+void main() { gl_Position = vec4(aPos, 0.5, 1.0); }
+";
+
+const FRAGMENT_SHADER: &str = "#version 330 core
+out vec4 color;
+void main() { color = vec4(1.0, 0.0, 0.0, 1.0); }
+";
+
+/// Corners of the whole target, counter-clockwise from the lower left.
+const CORNERS: [f32; 8] = [-1.0, -1.0, 1.0, -1.0, 1.0, 1.0, -1.0, 1.0];
+
+fn setup() -> (RenderTarget, Shader) {
+    let target = RenderTargetBuilder::new(8, 8)
+        .with_color(TextureFormat::Rgba8)
+        .build()
+        .unwrap();
+    let shader = ShaderProgramBuilder::new()
+        .with_source(ShaderStage::Vertex, VERTEX_SHADER)
+        .with_source(ShaderStage::Fragment, FRAGMENT_SHADER)
+        .build()
+        .unwrap();
+    (target, shader)
+}
+
+/// Clears the target, draws the mesh and returns which of the pixels on the left, bottom,
+/// right and top edge are covered. None of them lies on a diagonal of the target.
+fn draw(target: &RenderTarget, shader: &Shader, mesh: &Mesh) -> [bool; 4] {
+    target.bind();
+    unsafe {
+        gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+    }
+    shader.bind();
+    mesh.draw();
+
+    // Rows of the image go from top to bottom.
+    let pixels = target.read_pixels(0).unwrap();
+    [(0, 4), (4, 7), (7, 4), (3, 0)].map(|(x, y)| pixels.get_pixel(x, y).0 == [255, 0, 0, 255])
+}
+
+#[test]
+fn u16_indices_are_drawn() {
+    let (_context, _) = headless([16, 16], true, true);
+    let (target, shader) = setup();
+    let layout = VertexLayout::new().push(0, 2, AttributeType::Float, false);
+
+    let quad = Mesh::new(&CORNERS, &layout, Indices::U16(&[0, 1, 2, 2, 3, 0]));
+    assert!(quad.is_indexed());
+    assert_eq!(quad.count(), 6);
+    assert_eq!(draw(&target, &shader, &quad), [true; 4]);
+
+    // Only the lower right half.
+    let half = Mesh::new(&CORNERS, &layout, Indices::U16(&[0, 1, 2]));
+    assert_eq!(half.count(), 3);
+    assert_eq!(draw(&target, &shader, &half), [false, true, true, false]);
+}
+
+#[test]
+fn u32_indices_are_drawn() {
+    let (_context, _) = headless([16, 16], true, true);
+    let (target, shader) = setup();
+    let layout = VertexLayout::new().push(0, 2, AttributeType::Float, false);
+
+    let quad = Mesh::new(&CORNERS, &layout, Indices::U32(&[0, 1, 2, 2, 3, 0]));
+    assert!(quad.is_indexed());
+    assert_eq!(quad.count(), 6);
+    assert_eq!(draw(&target, &shader, &quad), [true; 4]);
+}
+
+#[test]
+fn meshes_without_indices_draw_every_vertex() {
+    let (_context, _) = headless([16, 16], true, true);
+    let (target, shader) = setup();
+    let layout = VertexLayout::new().push(0, 2, AttributeType::Float, false);
+
+    // Lower left half, the count comes from the vertex data and the layout stride.
+    let vertices: [f32; 6] = [-1.0, -1.0, 1.0, -1.0, -1.0, 1.0];
+    let triangle = Mesh::new(&vertices, &layout, Indices::None);
+    assert!(!triangle.is_indexed());
+    assert_eq!(triangle.count(), 3);
+    assert_eq!(
+        draw(&target, &shader, &triangle),
+        [true, true, false, false]
+    );
+
+    // Interleaved data with an unused attribute counts vertices by stride, not by float.
+    let layout = VertexLayout::new()
+        .push(0, 2, AttributeType::Float, false)
+        .push(1, 1, AttributeType::Float, false);
+    let vertices: [f32; 9] = [-1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 1.0, 1.0, 0.0];
+    let triangle = Mesh::new(&vertices, &layout, Indices::None);
+    assert_eq!(triangle.count(), 3);
+    assert_eq!(
+        draw(&target, &shader, &triangle),
+        [false, true, true, false]
+    );
+}
@@ -0,0 +1,300 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+use std::time::Duration;
+
+mod common;
+
+use common::{add_cube, headless, workspace_root};
+use image::RgbaImage;
+use whirlwing::{
+    assets::{Assets, Handle},
+    camera::Camera,
+    headless::HeadlessContext,
+    material::Material,
+    math::{Rotor3, Vec3, Vec4},
+    post_process::{
+        Bloom, ColorGrading, Effect, Tonemap, Tonemapper, Vignette, FULLSCREEN_VERTEX_SHADER,
+    },
+    shader::{ShaderProgramBuilder, ShaderStage},
+    texture::{Texture3D, TextureFormat},
+    time::Time,
+    window::WindowConfig,
+};
+
+const INVERT_SHADER: &str = "#version 330 core
+out vec4 FragColor;
+in vec2 uv;
+uniform sampler2D source;
+uniform vec4 tint;
+void main() { FragColor = vec4(1.0 - texture(source, uv).rgb, 1.0) * tint; }
+";
+
+/// Context without sRGB encoding or tonemapping, the effects are compared in 8-bit space.
+fn setup() -> (HeadlessContext, Assets) {
+    let (mut context, mut assets) = headless([64, 64], false, false);
+    add_cube(&mut context, &mut assets);
+    (context, assets)
+}
+
+fn render(context: &mut HeadlessContext) -> RgbaImage {
+    let camera = Camera::new(Vec3::new(0.0, 0.0, 3.0), Rotor3::identity());
+    render_with(context, &camera)
+}
+
+fn render_with(context: &mut HeadlessContext, camera: &Camera) -> RgbaImage {
+    context.redraw(camera, &Time::fixed(Duration::ZERO, Duration::ZERO));
+    context.read_pixels()
+}
+
+fn invert(assets: &Assets, tint: Vec4) -> Effect {
+    let shader = ShaderProgramBuilder::new()
+        .with_file(
+            ShaderStage::Vertex,
+            assets.resolve(FULLSCREEN_VERTEX_SHADER),
+        )
+        .with_source(ShaderStage::Fragment, INVERT_SHADER)
+        .build()
+        .unwrap();
+    let mut material = Material::new(Handle::new(shader));
+    material.set("tint", tint);
+    Effect::Custom(material)
+}
+
+fn assert_close(actual: [u8; 4], expected: [u8; 4]) {
+    let close = actual
+        .iter()
+        .zip(expected)
+        .all(|(actual, expected)| actual.abs_diff(expected) <= 2);
+    assert!(close, "{actual:?} != {expected:?}");
+}
+
+/// The corner shows the clear colour, the centre the cube.
+const CORNER: (u32, u32) = (1, 1);
+const CENTER: (u32, u32) = (32, 32);
+
+fn pixel(image: &RgbaImage, (x, y): (u32, u32)) -> [u8; 4] {
+    image.get_pixel(x, y).0
+}
+
+#[test]
+fn custom_passes_run_in_order_and_can_be_toggled() {
+    let (mut context, assets) = setup();
+    let original = render(&mut context);
+
+    let post_process = context.post_process_mut();
+    post_process.push("invert", invert(&assets, Vec4::one()));
+    let inverted = render(&mut context);
+    for (x, y) in [CORNER, CENTER] {
+        let [r, g, b, _] = pixel(&original, (x, y));
+        assert_close(pixel(&inverted, (x, y)), [255 - r, 255 - g, 255 - b, 255]);
+    }
+
+    // Inverting twice gives the original, the tint is a material parameter.
+    let post_process = context.post_process_mut();
+    post_process.push(
+        "invert again",
+        invert(&assets, Vec4::new(1.0, 0.0, 1.0, 1.0)),
+    );
+    let twice = render(&mut context);
+    let [r, _, b, _] = pixel(&original, CORNER);
+    assert_close(pixel(&twice, CORNER), [r, 0, b, 255]);
+
+    let post_process = context.post_process_mut();
+    assert!(post_process.set_enabled("invert", false));
+    assert!(!post_process.set_enabled("missing", false));
+    let names: Vec<_> = post_process.passes().collect();
+    assert_eq!(names, [("invert", false), ("invert again", true)]);
+    assert_close(
+        pixel(&render(&mut context), CORNER),
+        [255 - r, 0, 255 - b, 255],
+    );
+
+    // Without enabled passes the scene is drawn directly.
+    context
+        .post_process_mut()
+        .set_enabled("invert again", false);
+    assert!(!context.post_process_mut().is_active());
+    assert_eq!(render(&mut context), original);
+}
+
+#[test]
+fn colour_effects_follow_their_formulas() {
+    let (mut context, _assets) = setup();
+    let original = render(&mut context);
+    let [r, g, b, _] = pixel(&original, CORNER);
+    let channels = |f: &dyn Fn(f32) -> f32| {
+        let channel = |value: u8| (f(value as f32 / 255.0) * 255.0).round() as u8;
+        [channel(r), channel(g), channel(b), 255]
+    };
+
+    let gamma = Effect::Gamma(2.2);
+    context.post_process_mut().push("effect", gamma);
+    let expected = channels(&|value| value.powf(1.0 / 2.2));
+    assert_close(pixel(&render(&mut context), CORNER), expected);
+
+    let tonemap = Tonemap {
+        curve: Tonemapper::Reinhard,
+        exposure: 2.0,
+    };
+    context
+        .post_process_mut()
+        .push("effect", Effect::Tonemap(tonemap));
+    let expected = channels(&|value| 2.0 * value / (2.0 * value + 1.0));
+    assert_close(pixel(&render(&mut context), CORNER), expected);
+
+    // Changed in place between frames.
+    if let Some(Effect::Tonemap(tonemap)) = context.post_process_mut().effect_mut("effect") {
+        tonemap.exposure = 1.0;
+    }
+    let expected = channels(&|value| value / (value + 1.0));
+    assert_close(pixel(&render(&mut context), CORNER), expected);
+
+    // 2x2x2 table swapping red and blue.
+    let mut texels = Vec::new();
+    for blue in [0, 255] {
+        for green in [0, 255] {
+            for red in [0, 255] {
+                texels.extend([blue, green, red]);
+            }
+        }
+    }
+    let grading = ColorGrading {
+        lut: Handle::new(Texture3D::from_rgb8([2, 2, 2], &texels)),
+        strength: 1.0,
+    };
+    context
+        .post_process_mut()
+        .push("effect", Effect::ColorGrading(grading));
+    assert_close(pixel(&render(&mut context), CORNER), [b, g, r, 255]);
+
+    let identity = ColorGrading {
+        lut: Handle::new(Texture3D::identity_lut(16)),
+        strength: 1.0,
+    };
+    context
+        .post_process_mut()
+        .push("effect", Effect::ColorGrading(identity));
+    assert_close(
+        pixel(&render(&mut context), CENTER),
+        pixel(&original, CENTER),
+    );
+}
+
+#[test]
+fn screen_space_effects_change_the_image() {
+    let (mut context, _assets) = setup();
+    let original = render(&mut context);
+
+    let vignette = Vignette {
+        intensity: 1.0,
+        smoothness: 0.5,
+    };
+    context
+        .post_process_mut()
+        .push("effect", Effect::Vignette(vignette));
+    let vignetted = render(&mut context);
+    assert_eq!(pixel(&vignetted, CENTER), pixel(&original, CENTER));
+    let corner = pixel(&vignetted, (0, 0));
+    assert!(corner[..3].iter().all(|channel| *channel < 8), "{corner:?}");
+
+    let bloom = Bloom {
+        threshold: 0.0,
+        intensity: 1.0,
+        iterations: 2,
+    };
+    context
+        .post_process_mut()
+        .push("effect", Effect::Bloom(bloom));
+    let bloomed = render(&mut context);
+    let [r, g, b, _] = pixel(&original, CORNER);
+    let brighter = pixel(&bloomed, CORNER);
+    assert!(
+        brighter[0] > r && brighter[1] > g && brighter[2] > b,
+        "{brighter:?}"
+    );
+
+    // Flat areas are left alone, only edges are smoothed.
+    context.post_process_mut().push("effect", Effect::Fxaa);
+    let smoothed = render(&mut context);
+    assert_close(pixel(&smoothed, CORNER), pixel(&original, CORNER));
+    assert_ne!(smoothed, original);
+}
+
+#[test]
+fn hdr_scenes_are_tonemapped_and_encoded_as_srgb() {
+    let (mut context, _assets) = headless([64, 64], true, true);
+    let post_process = context.post_process_mut();
+    assert_eq!(post_process.scene_format(), TextureFormat::Rgba16F);
+    assert_eq!(post_process.exposure(), Some(1.0));
+    if let Some(Effect::Tonemap(tonemap)) = post_process.effect_mut("tonemap") {
+        tonemap.curve = Tonemapper::Reinhard;
+    }
+
+    // Values above 1 survive until the tonemap pass.
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 3.0), Rotor3::identity());
+    camera.set_clear_color(Vec4::new(4.0, 1.0, 0.25, 1.0));
+    let encode = |linear: f32| {
+        let srgb = if linear <= 0.0031308 {
+            linear * 12.92
+        } else {
+            1.055 * linear.powf(1.0 / 2.4) - 0.055
+        };
+        (srgb * 255.0).round() as u8
+    };
+    let reinhard = |value: f32| value / (value + 1.0);
+    let expected = [encode(0.8), encode(0.5), encode(reinhard(0.25)), 255];
+    assert_close(pixel(&render_with(&mut context, &camera), CORNER), expected);
+
+    assert!(context.post_process_mut().set_exposure(0.25));
+    let expected = [
+        encode(0.5),
+        encode(reinhard(0.25)),
+        encode(reinhard(0.0625)),
+        255,
+    ];
+    assert_close(pixel(&render_with(&mut context, &camera), CORNER), expected);
+
+    // Without tonemapping the scene is only encoded.
+    context.post_process_mut().set_enabled("tonemap", false);
+    assert_eq!(context.post_process_mut().exposure(), None);
+    assert!(!context.post_process_mut().set_exposure(1.0));
+    camera.set_clear_color(Vec4::new(0.2, 1.0, 0.0, 1.0));
+    let expected = [encode(0.2), 255, 0, 255];
+    assert_close(pixel(&render_with(&mut context, &camera), CORNER), expected);
+
+    let mut linear = setup().0;
+    let [r, ..] = pixel(&render_with(&mut linear, &camera), CORNER);
+    assert_eq!(r, 51);
+}
+
+#[test]
+fn the_default_stack_keeps_multisampling() {
+    // Textures are still loading, so the cube is drawn in one colour and only its
+    // silhouette can mix with the clear colour.
+    let blended = |samples: u8| {
+        let config = WindowConfig {
+            size: [160, 120],
+            samples,
+            ..WindowConfig::default()
+        };
+        let mut assets = Assets::new(workspace_root().join("res"));
+        let mut context = HeadlessContext::new(&config, &mut assets).unwrap();
+        add_cube(&mut context, &mut assets);
+        assert!(context.post_process_mut().is_active());
+        assert_eq!(context.post_process_mut().samples(), samples as u32);
+
+        let mut camera = Camera::new(Vec3::new(0.0, 2.5, 4.0), Rotor3::identity());
+        camera.rotate_yz(f32::to_radians(-30.0));
+        let image = render_with(&mut context, &camera);
+        let background = pixel(&image, CORNER);
+        let cube = pixel(&image, (80, 60));
+        let differs = |a: [u8; 4], b: [u8; 4]| a.iter().zip(b).any(|(a, b)| a.abs_diff(b) > 2);
+        assert!(differs(background, cube));
+        image
+            .pixels()
+            .filter(|pixel| differs(pixel.0, background) && differs(pixel.0, cube))
+            .count()
+    };
+    assert_eq!(blended(0), 0);
+    assert!(blended(4) > 50, "edges are not antialiased");
+}
@@ -0,0 +1,198 @@
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+mod common;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use whirlwing::{error::WhirlwingErrorKind, preprocessor::Preprocessor};
+
+/// Writes `files` into a fresh directory and returns its path.
+fn include_root(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&root);
+    for (path, source) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    root
+}
+
+#[test]
+fn includes_are_expanded_once() {
+    let root = include_root(
+        "preprocessor_once",
+        &[
+            ("common/math.glsl", "float square(float x) { return x * x; }\n"),
+            ("common/lighting.glsl", "#include \"common/math.glsl\"\nfloat light() { return 1.0; }\n"),
+            (
+                "shader.frag",
+                "#version 330 core\n#include \"common/lighting.glsl\"\n#include \"common/math.glsl\"\nvoid main() {}\n",
+            ),
+        ],
+    );
+
+    let processed = Preprocessor::new(&root)
+        .process_file(root.join("shader.frag"))
+        .unwrap();
+    assert_eq!(
+        processed.source(),
+        "#version 330 core\n\
+         float square(float x) { return x * x; }\n\
+         float light() { return 1.0; }\n\
+         void main() {}\n"
+    );
+    assert_eq!(
+        processed.includes(),
+        [
+            root.join("common/lighting.glsl"),
+            root.join("common/math.glsl")
+        ]
+    );
+}
+
+#[test]
+fn lines_map_back_to_their_files() {
+    let root = include_root(
+        "preprocessor_lines",
+        &[
+            ("common.glsl", "// first\n// second\n"),
+            (
+                "shader.vert",
+                "#version 330 core\n#include \"common.glsl\"\nvoid main() {}\n",
+            ),
+        ],
+    );
+
+    let processed = Preprocessor::new(&root)
+        .define("FOO", "1")
+        .define("BAR", "")
+        .process_file(root.join("shader.vert"))
+        .unwrap();
+    assert!(processed
+        .source()
+        .starts_with("#version 330 core\n#define FOO 1\n#define BAR\n"));
+
+    let vertex = root.join("shader.vert");
+    let common = root.join("common.glsl");
+    assert_eq!(processed.original_line(1), Some((vertex.as_path(), 1)));
+    assert_eq!(processed.original_line(2), None);
+    assert_eq!(processed.original_line(5), Some((common.as_path(), 2)));
+    assert_eq!(processed.original_line(6), Some((vertex.as_path(), 3)));
+    assert_eq!(processed.original_line(7), None);
+
+    let log =
+        processed.map_log("0:5(3): error: syntax error\n0(6) : error C0000: oops\nlinker error\n");
+    assert_eq!(
+        log,
+        format!(
+            "{common}:2(3): error: syntax error\n{vertex}:3 : error C0000: oops\nlinker error\n",
+            common = common.display(),
+            vertex = vertex.display()
+        )
+    );
+}
+
+#[test]
+fn defines_follow_a_version_after_comments() {
+    let source =
+        "// Licence header\n\n/* Block\n   comment */\n#version 330 core\nvoid main() {}\n";
+    let processed = Preprocessor::new("res")
+        .define("FOO", "1")
+        .process_str(source, "commented")
+        .unwrap();
+    assert_eq!(
+        processed.source(),
+        "// Licence header\n\n/* Block\n   comment */\n#version 330 core\n#define FOO 1\nvoid main() {}\n"
+    );
+    assert_eq!(
+        processed.original_line(5),
+        Some((Path::new("commented"), 5))
+    );
+    assert_eq!(processed.original_line(6), None);
+    assert_eq!(
+        processed.original_line(7),
+        Some((Path::new("commented"), 6))
+    );
+
+    // Without a version the defines come first.
+    let processed = Preprocessor::new("res")
+        .define("FOO", "1")
+        .process_str("// Comment\nvoid main() {}\n", "unversioned")
+        .unwrap();
+    assert_eq!(
+        processed.source(),
+        "#define FOO 1\n// Comment\nvoid main() {}\n"
+    );
+}
+
+#[test]
+fn include_cycles_are_errors() {
+    let root = include_root(
+        "preprocessor_cycle",
+        &[
+            ("a.glsl", "#include \"b.glsl\"\n"),
+            ("b.glsl", "\n#include \"a.glsl\"\n"),
+            ("shader.frag", "#version 330 core\n#include \"a.glsl\"\n"),
+        ],
+    );
+
+    let error = Preprocessor::new(&root)
+        .process_file(root.join("shader.frag"))
+        .unwrap_err();
+    assert_eq!(*error.kind(), WhirlwingErrorKind::ShaderCompilationFailure);
+    let b = root.join("b.glsl");
+    assert!(error
+        .content()
+        .contains(&format!("{}:2: Include cycle", b.display())));
+}
+
+#[test]
+fn missing_include_names_the_including_line() {
+    let root = include_root("preprocessor_missing", &[]);
+
+    let error = Preprocessor::new(&root)
+        .process_str("#version 330 core\n\n#include \"missing.glsl\"\n", "shader")
+        .unwrap_err();
+    assert!(error
+        .content()
+        .starts_with("shader:3: Failed to read include"));
+
+    let error = Preprocessor::new(Path::new(""))
+        .process_str("#include missing.glsl\n", "shader")
+        .unwrap_err();
+    assert!(error.content().starts_with("shader:1: Malformed include"));
+}
+
+/// Compile errors in included files are reported at their original location.
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+#[test]
+fn compile_errors_point_into_includes() {
+    use whirlwing::assets::Assets;
+
+    let root = include_root(
+        "preprocessor_compile_error",
+        &[
+            ("broken.glsl", "float ok() { return 1.0; }\nfloat broken() { return }\n"),
+            (
+                "shader.vert",
+                "#version 330 core\nvoid main() { gl_Position = vec4(0.0); }\n",
+            ),
+            (
+                "shader.frag",
+                "#version 330 core\n#include \"broken.glsl\"\nout vec4 color;\nvoid main() { color = vec4(1.0); }\n",
+            ),
+        ],
+    );
+
+    let (_context, _) = common::headless([64, 64], true, true);
+    let mut assets = Assets::new(&root);
+
+    let error = assets
+        .load_shader_with_defines("shader.vert", "shader.frag", &[("UNUSED", "1")])
+        .unwrap_err();
+    assert_eq!(*error.kind(), WhirlwingErrorKind::ShaderCompilationFailure);
+    let location = format!("{}:2", root.join("broken.glsl").display());
+    assert!(error.content().contains(&location), "{}", error.content());
+}
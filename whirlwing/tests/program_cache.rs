@@ -0,0 +1,86 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+use std::{fs, path::PathBuf};
+
+mod common;
+
+use common::headless;
+use whirlwing::{program_cache::ProgramCache, shader::Shader};
+
+const VERTEX_SHADER: &str = "#version 330 core
+layout (location = 0) in vec3 aPos;
+uniform mat4 model;
+void main() { gl_Position = model * vec4(aPos, 1.0); }
+";
+
+const FRAGMENT_SHADER: &str = "#version 330 core
+out vec4 FragColor;
+uniform vec4 color;
+void main() { FragColor = color; }
+";
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn cached_files(dir: &PathBuf) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| entries.map(|entry| entry.unwrap().path()).collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn linked_programs_are_reused() {
+    let (_context, _) = headless([64, 64], true, true);
+    let dir = cache_dir("program_cache_reused");
+    let cache = ProgramCache::new(&dir);
+
+    let compiled = Shader::from_str_cached(VERTEX_SHADER, FRAGMENT_SHADER, &cache).unwrap();
+    let files = cached_files(&dir);
+    if !ProgramCache::is_supported() {
+        assert!(files.is_empty());
+        return;
+    }
+    assert_eq!(files.len(), 1);
+    let stored = fs::read(&files[0]).unwrap();
+
+    let loaded = Shader::from_str_cached(VERTEX_SHADER, FRAGMENT_SHADER, &cache).unwrap();
+    assert_eq!(cached_files(&dir), files);
+    assert_eq!(fs::read(&files[0]).unwrap(), stored);
+    let uniforms = |shader: &Shader| {
+        let mut uniforms = shader.uniforms();
+        uniforms.sort_by(|a, b| a.name.cmp(&b.name));
+        uniforms
+    };
+    assert_eq!(uniforms(&loaded), uniforms(&compiled));
+    loaded.bind();
+    loaded.set("color", &whirlwing::math::Vec4::one()).unwrap();
+
+    // A different program gets its own entry.
+    let other = FRAGMENT_SHADER.replace("FragColor = color", "FragColor = color * 0.5");
+    Shader::from_str_cached(VERTEX_SHADER, &other, &cache).unwrap();
+    assert_eq!(cached_files(&dir).len(), 2);
+}
+
+#[test]
+fn rejected_binaries_fall_back_to_compilation() {
+    let (_context, _) = headless([64, 64], true, true);
+    if !ProgramCache::is_supported() {
+        return;
+    }
+    let dir = cache_dir("program_cache_rejected");
+    let cache = ProgramCache::new(&dir);
+
+    Shader::from_str_cached(VERTEX_SHADER, FRAGMENT_SHADER, &cache).unwrap();
+    let files = cached_files(&dir);
+    let mut corrupted = fs::read(&files[0]).unwrap();
+    corrupted.truncate(4);
+    corrupted.extend_from_slice(b"not a program binary");
+    fs::write(&files[0], &corrupted).unwrap();
+
+    let shader = Shader::from_str_cached(VERTEX_SHADER, FRAGMENT_SHADER, &cache).unwrap();
+    assert!(shader.uniform("color").is_some());
+    assert_ne!(fs::read(&files[0]).unwrap(), corrupted);
+}
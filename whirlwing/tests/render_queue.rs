@@ -0,0 +1,146 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+use std::time::Duration;
+
+mod common;
+
+use common::{add_cube, headless};
+use whirlwing::{
+    assets::{Assets, Handle},
+    camera::Camera,
+    headless::HeadlessContext,
+    material::Material,
+    math::{Mat4, Rotor3, Vec3, Vec4},
+    render_queue::RenderStats,
+    shader::{ShaderProgramBuilder, ShaderStage},
+    time::Time,
+};
+
+const VERTEX_SHADER: &str = "#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (std140) uniform Camera {
+    mat4 view;
+    mat4 projection;
+    vec3 camera_position;
+};
+uniform mat4 model;
+void main() { gl_Position = projection * view * model * vec4(aPos, 1.0); }
+";
+
+const FRAGMENT_SHADER: &str = "#version 330 core
+out vec4 FragColor;
+uniform sampler2D albedo;
+uniform vec4 color;
+void main() { FragColor = color * texture(albedo, vec2(0.5)).a; }
+";
+
+fn material(
+    assets: &mut Assets,
+    texture: &str,
+    color: Vec4,
+    transparent: bool,
+) -> Handle<Material> {
+    let shader = ShaderProgramBuilder::new()
+        .with_source(ShaderStage::Vertex, VERTEX_SHADER)
+        .with_source(ShaderStage::Fragment, FRAGMENT_SHADER)
+        .build()
+        .unwrap();
+    let texture = assets.load_texture(texture, &Default::default()).unwrap();
+    let mut material = Material::new(Handle::new(shader));
+    material.set("albedo", texture);
+    material.set("color", color);
+    material.set_transparent(transparent);
+    Handle::new(material)
+}
+
+fn at(z: f32) -> Mat4 {
+    Mat4::from_translation(Vec3::new(0.0, 0.0, z))
+}
+
+fn redraw(context: &mut HeadlessContext) {
+    let camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Rotor3::identity());
+    context.redraw(&camera, &Time::fixed(Duration::ZERO, Duration::ZERO));
+}
+
+#[test]
+fn items_are_grouped_by_state() {
+    let (mut context, mut assets) = headless([64, 64], true, true);
+    add_cube(&mut context, &mut assets);
+    let quad = assets.load_mesh("meshes/quad.obj").unwrap();
+    let red = material(
+        &mut assets,
+        "textures/wall.jpg",
+        Vec4::new(1.0, 0.0, 0.0, 1.0),
+        false,
+    );
+    let green = material(
+        &mut assets,
+        "textures/awesomeface.png",
+        Vec4::new(0.0, 1.0, 0.0, 1.0),
+        false,
+    );
+
+    for (index, material) in [&red, &green, &red, &green, &red].into_iter().enumerate() {
+        let transform = at(-(index as f32));
+        context
+            .render_queue_mut()
+            .submit(quad.clone(), material.clone(), transform);
+    }
+    redraw(&mut context);
+
+    // The cube of the scene and its container material come on top, the container shader
+    // is drawn instanced.
+    assert_eq!(
+        context.render_stats(),
+        RenderStats {
+            items: 6,
+            draw_calls: 6,
+            instances: 1,
+            program_changes: 3,
+            material_changes: 3,
+            texture_changes: 4,
+            vertex_array_changes: 2,
+        }
+    );
+    assert!(context.render_queue_mut().is_empty());
+
+    // Items with assets which are not loaded are skipped.
+    let pending = assets.load_mesh_async("meshes/missing.obj");
+    context
+        .render_queue_mut()
+        .submit(pending, red.clone(), Mat4::identity());
+    redraw(&mut context);
+    let stats = context.render_stats();
+    assert_eq!((stats.items, stats.draw_calls), (2, 1));
+}
+
+#[test]
+fn transparent_items_are_drawn_back_to_front() {
+    let (mut context, mut assets) = headless([64, 64], true, true);
+    add_cube(&mut context, &mut assets);
+    let quad = assets.load_mesh("meshes/quad.obj").unwrap();
+    let near = material(
+        &mut assets,
+        "textures/wall.jpg",
+        Vec4::new(1.0, 0.0, 0.0, 0.5),
+        true,
+    );
+    let far = material(
+        &mut assets,
+        "textures/wall.jpg",
+        Vec4::new(0.0, 0.0, 1.0, 0.5),
+        true,
+    );
+
+    // Submitted nearest first, in front of the cube.
+    context
+        .render_queue_mut()
+        .submit(quad.clone(), near, at(3.5));
+    context.render_queue_mut().submit(quad, far, at(3.0));
+    redraw(&mut context);
+
+    // Red blended over blue keeps more red than blue.
+    let center = context.read_pixels().get_pixel(32, 32).0;
+    assert!(center[0] > center[2], "{center:?}");
+    assert_eq!(context.render_stats().draw_calls, 3);
+}
@@ -0,0 +1,161 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+mod common;
+
+use common::headless;
+use whirlwing::{
+    error::WhirlwingErrorKind,
+    mesh::{AttributeType, Indices, Mesh, VertexLayout},
+    render_target::{AttachmentStorage, RenderTargetBuilder, RenderTargets},
+    shader::{ShaderProgramBuilder, ShaderStage},
+    texture::TextureFormat,
+};
+
+const VERTEX_SHADER: &str = "#version 330 core
+layout (location = 0) in vec2 aPos;
+void main() { gl_Position = vec4(aPos, 0.5, 1.0); }
+";
+
+const FRAGMENT_SHADER: &str = "#version 330 core
+layout (location = 0) out vec4 first;
+layout (location = 1) out vec4 second;
+void main() {
+    first = vec4(1.0, 0.0, 0.0, 1.0);
+    second = vec4(0.0, 0.0, 1.0, 1.0);
+}
+";
+
+/// Triangle covering the lower left half of the target.
+fn triangle() -> Mesh {
+    let layout = VertexLayout::new().push(0, 2, AttributeType::Float, false);
+    let vertices: [f32; 6] = [-1.0, -1.0, 1.0, -1.0, -1.0, 1.0];
+    Mesh::new(&vertices, &layout, Indices::None)
+}
+
+fn clear() {
+    unsafe {
+        gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+    }
+}
+
+#[test]
+fn fragment_outputs_go_to_their_attachments() {
+    let (_context, _) = headless([16, 16], true, true);
+    let target = RenderTargetBuilder::new(8, 8)
+        .with_color(TextureFormat::Rgba8)
+        .with_color(TextureFormat::Rgba16F)
+        .with_depth(TextureFormat::Depth24, AttachmentStorage::Texture)
+        .build()
+        .unwrap();
+    let shader = ShaderProgramBuilder::new()
+        .with_source(ShaderStage::Vertex, VERTEX_SHADER)
+        .with_source(ShaderStage::Fragment, FRAGMENT_SHADER)
+        .build()
+        .unwrap();
+
+    target.bind();
+    clear();
+    shader.bind();
+    triangle().draw();
+
+    let first = target.read_pixels(0).unwrap();
+    let second = target.read_pixels(1).unwrap();
+    assert_eq!(first.get_pixel(1, 6).0, [255, 0, 0, 255]);
+    assert_eq!(second.get_pixel(1, 6).0, [0, 0, 255, 255]);
+    // Outside of the triangle.
+    assert_eq!(first.get_pixel(6, 1).0, [0, 0, 0, 255]);
+    assert!(target.read_pixels(2).is_none());
+
+    assert_eq!(target.color_texture(1).unwrap().width(), 8);
+    assert!(target.color_texture(2).is_none());
+    assert!(target.depth_texture().is_some());
+}
+
+#[test]
+fn multisampled_targets_are_resolved() {
+    let (_context, _) = headless([16, 16], true, true);
+    let target = RenderTargetBuilder::new(8, 8)
+        .with_color(TextureFormat::Rgba8)
+        .with_depth(TextureFormat::Depth24Stencil8, AttachmentStorage::Texture)
+        .with_samples(4)
+        .build()
+        .unwrap();
+    let shader = ShaderProgramBuilder::new()
+        .with_source(ShaderStage::Vertex, VERTEX_SHADER)
+        .with_source(ShaderStage::Fragment, FRAGMENT_SHADER)
+        .build()
+        .unwrap();
+
+    target.bind();
+    clear();
+    shader.bind();
+    triangle().draw();
+    target.resolve();
+
+    let pixels = target.read_pixels(0).unwrap();
+    assert_eq!(pixels.get_pixel(1, 6).0, [255, 0, 0, 255]);
+    assert_eq!(pixels.get_pixel(6, 1).0, [0, 0, 0, 255]);
+    // Pixels on the diagonal are partly covered.
+    let edge = pixels.get_pixel(3, 3).0;
+    assert!(edge[0] > 0 && edge[0] < 255, "{edge:?}");
+
+    assert_eq!(target.samples(), 4);
+    assert!(target.color_texture(0).is_some());
+    assert!(target.depth_texture().is_some());
+}
+
+#[test]
+fn invalid_targets_are_errors() {
+    let (_context, _) = headless([16, 16], true, true);
+    let kind = |builder: RenderTargetBuilder| *builder.build().unwrap_err().kind();
+
+    let empty = RenderTargetBuilder::new(0, 8).with_color(TextureFormat::Rgba8);
+    assert_eq!(kind(empty), WhirlwingErrorKind::FramebufferFailure);
+    let depth_as_color = RenderTargetBuilder::new(8, 8).with_color(TextureFormat::Depth24);
+    assert_eq!(kind(depth_as_color), WhirlwingErrorKind::FramebufferFailure);
+    let samples = RenderTargetBuilder::new(8, 8)
+        .with_color(TextureFormat::Rgba8)
+        .with_samples(1024);
+    assert_eq!(kind(samples), WhirlwingErrorKind::UnsupportedFeature);
+
+    // Depth only, e.g. for shadow maps.
+    let shadow_map = RenderTargetBuilder::new(8, 8)
+        .with_depth(TextureFormat::Depth32F, AttachmentStorage::Texture)
+        .build()
+        .unwrap();
+    assert!(shadow_map.read_pixels(0).is_none());
+}
+
+#[test]
+fn window_scaled_targets_follow_resizes() {
+    let (_context, _) = headless([16, 16], true, true);
+    let mut targets = RenderTargets::new();
+    let half = RenderTargetBuilder::new(8, 8)
+        .with_color(TextureFormat::Rgba16F)
+        .with_window_scale(0.5)
+        .build()
+        .unwrap();
+    let fixed = RenderTargetBuilder::new(8, 8)
+        .with_color(TextureFormat::Rgba8)
+        .build()
+        .unwrap();
+    targets.insert("half", half).unwrap();
+    targets.insert("fixed", fixed).unwrap();
+
+    targets.resize(64, 30).unwrap();
+    let half = targets.get("half").unwrap();
+    assert_eq!((half.width(), half.height()), (32, 15));
+    assert!(half.color_texture(0).is_some());
+    let fixed = targets.get("fixed").unwrap();
+    assert_eq!((fixed.width(), fixed.height()), (8, 8));
+
+    // Targets added later get the current window size.
+    let full = RenderTargetBuilder::new(1, 1)
+        .with_color(TextureFormat::Rgba8)
+        .with_window_scale(1.0)
+        .build()
+        .unwrap();
+    assert!(targets.insert("half", full).unwrap().is_some());
+    assert_eq!(targets.get("half").unwrap().width(), 64);
+}
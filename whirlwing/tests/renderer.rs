@@ -0,0 +1,116 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+use std::time::Duration;
+
+mod common;
+
+use common::{add_cube, assert_golden, bounding_box, compare, headless, render, Scene};
+use image::{Rgba, RgbaImage};
+use whirlwing::{
+    camera::Camera,
+    math::{Rotor3, Similarity3, Vec3},
+    scene::{Attachment, Projection},
+    time::Time,
+};
+
+#[test]
+fn cube() {
+    let camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Rotor3::identity());
+    let scene = Scene::new("cube", camera);
+    // The unit cube turned by 30 degrees, 5 units away. Checked before comparing so a
+    // distorted projection cannot be blessed.
+    let (width, height) = bounding_box(&render(&scene), 12).unwrap();
+    assert!(
+        width.abs_diff(45) <= 2 && height.abs_diff(38) <= 2,
+        "{width}x{height}"
+    );
+    assert_golden(&scene);
+}
+
+#[test]
+fn cube_from_above() {
+    let mut camera = Camera::new(Vec3::new(0.0, 2.5, 4.0), Rotor3::identity());
+    camera.rotate_yz(f32::to_radians(-30.0));
+    assert_golden(&Scene {
+        frames: 3,
+        ..Scene::new("cube_from_above", camera)
+    });
+}
+
+#[test]
+fn projection_uses_the_output_aspect_ratio() {
+    let camera = || Camera::new(Vec3::new(0.0, 0.0, 5.0), Rotor3::identity());
+    let square = render(&Scene {
+        size: [120, 120],
+        ..Scene::new("square", camera())
+    });
+    let wide = render(&Scene {
+        size: [240, 120],
+        ..Scene::new("wide", camera())
+    });
+
+    // The vertical field of view is fixed, so the cube keeps its size in pixels.
+    let (square_width, square_height) = bounding_box(&square, 12).unwrap();
+    let (wide_width, wide_height) = bounding_box(&wide, 12).unwrap();
+    assert!(
+        square_width.abs_diff(wide_width) <= 1,
+        "{square_width} != {wide_width}"
+    );
+    assert_eq!(square_height, wide_height);
+}
+
+#[test]
+fn the_active_scene_camera_places_the_view() {
+    let position = Vec3::new(0.0, 0.0, 5.0);
+    let camera = Camera::new(position, Rotor3::identity());
+    let expected = render(&Scene::new("resource", camera));
+
+    let (mut context, mut assets) = headless([160, 120], true, true);
+    add_cube(&mut context, &mut assets);
+    assets.wait();
+    let scene = context.scene_mut();
+    let eye = scene.add_node("eye", Similarity3::new(position, Rotor3::identity(), 1.0));
+    scene
+        .attach(eye, Attachment::Camera(Projection::default()))
+        .unwrap();
+    scene.set_active_camera(Some(eye)).unwrap();
+
+    // The `Camera` resource is ignored for the view.
+    let elsewhere = Camera::new(Vec3::new(0.0, 0.0, 50.0), Rotor3::identity());
+    let time = Time::fixed(Duration::ZERO, Duration::ZERO);
+    context.redraw(&elsewhere, &time);
+    let actual = context.read_pixels();
+    if let Some(mismatch) = compare(&expected, &actual, 2) {
+        panic!("{mismatch}");
+    }
+
+    // A view half as wide and high doubles the size of the cube.
+    let narrow = Projection {
+        fov_y: 2.0 * (Projection::default().fov_y * 0.5).tan().atan2(2.0),
+        ..Projection::default()
+    };
+    let scene = context.scene_mut();
+    scene.attach(eye, Attachment::Camera(narrow)).unwrap();
+    context.redraw(&elsewhere, &time);
+    let (width, height) = bounding_box(&context.read_pixels(), 12).unwrap();
+    let (expected_width, expected_height) = bounding_box(&expected, 12).unwrap();
+    assert!(
+        width.abs_diff(2 * expected_width) <= 3 && height.abs_diff(2 * expected_height) <= 3,
+        "{width}x{height}"
+    );
+}
+
+#[test]
+fn compare_respects_tolerance() {
+    let reference = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+    let mut actual = reference.clone();
+    actual.put_pixel(1, 2, Rgba([102, 100, 100, 255]));
+    assert!(compare(&reference, &actual, 2).is_none());
+
+    actual.put_pixel(3, 0, Rgba([100, 90, 100, 255]));
+    let mismatch = compare(&reference, &actual, 2).unwrap();
+    assert_eq!(
+        mismatch.to_string(),
+        "1 pixels differ, largest channel difference is 10"
+    );
+}
@@ -0,0 +1,200 @@
+use whirlwing::{
+    error::WhirlwingErrorKind,
+    light::{Light, LightKind},
+    math::{Isometry3, Rotor3, Similarity3, Vec3},
+    scene::{Attachment, Projection, Scene},
+};
+
+fn assert_close(a: Vec3, b: Vec3) {
+    assert!((a - b).mag() < 1e-5, "{a:?} != {b:?}");
+}
+
+fn translation(x: f32, y: f32, z: f32) -> Similarity3 {
+    Similarity3::new(Vec3::new(x, y, z), Rotor3::identity(), 1.0)
+}
+
+#[test]
+fn world_transforms_compose_through_the_hierarchy() {
+    let mut scene = Scene::new();
+    let quarter_turn = Rotor3::from_rotation_xz(std::f32::consts::FRAC_PI_2);
+    let root = scene.add_node(
+        "root",
+        Similarity3::new(Vec3::new(1.0, 0.0, 0.0), quarter_turn, 2.0),
+    );
+    let child = scene
+        .add_child(root, "child", translation(0.0, 0.0, -1.0))
+        .unwrap();
+    let grandchild = scene
+        .add_child(child, "grandchild", translation(0.0, 1.0, 0.0))
+        .unwrap();
+
+    let expected = |local: Vec3| {
+        let world = scene.node(root).unwrap().transform();
+        world.transform_vec(local)
+    };
+    let child_position = scene.world_transform(child).unwrap().translation;
+    assert_close(child_position, expected(Vec3::new(0.0, 0.0, -1.0)));
+    let grandchild_world = scene.world_transform(grandchild).unwrap();
+    assert_close(
+        grandchild_world.translation,
+        expected(Vec3::new(0.0, 1.0, -1.0)),
+    );
+    assert_eq!(grandchild_world.scale, 2.0);
+
+    let matrix = scene.world_matrix(grandchild).unwrap();
+    assert_eq!(matrix, grandchild_world.into_homogeneous_matrix());
+
+    // Moving the root moves the cached descendants along.
+    scene
+        .set_isometry(
+            root,
+            Isometry3::new(Vec3::new(0.0, 5.0, 0.0), Rotor3::identity()),
+        )
+        .unwrap();
+    assert_close(
+        scene.world_transform(grandchild).unwrap().translation,
+        Vec3::new(0.0, 6.0, -1.0),
+    );
+    assert_close(
+        scene
+            .world_matrix(child)
+            .unwrap()
+            .transform_point3(Vec3::zero()),
+        Vec3::new(0.0, 5.0, -1.0),
+    );
+}
+
+#[test]
+fn reparenting_keeps_the_local_transform() {
+    let mut scene = Scene::new();
+    let a = scene.add_node("a", translation(1.0, 0.0, 0.0));
+    let b = scene.add_node("b", translation(0.0, 2.0, 0.0));
+    let child = scene
+        .add_child(a, "child", translation(0.0, 0.0, 3.0))
+        .unwrap();
+    let leaf = scene
+        .add_child(child, "leaf", translation(1.0, 1.0, 1.0))
+        .unwrap();
+    assert_close(
+        scene.world_transform(leaf).unwrap().translation,
+        Vec3::new(2.0, 1.0, 4.0),
+    );
+
+    scene.set_parent(child, Some(b)).unwrap();
+    assert_eq!(scene.node(child).unwrap().parent(), Some(b));
+    assert!(scene.node(a).unwrap().children().is_empty());
+    assert_close(
+        scene.world_transform(leaf).unwrap().translation,
+        Vec3::new(1.0, 3.0, 4.0),
+    );
+
+    scene.set_parent(child, None).unwrap();
+    assert_eq!(scene.roots(), [a, b, child]);
+    assert_close(
+        scene.world_transform(leaf).unwrap().translation,
+        Vec3::new(1.0, 1.0, 4.0),
+    );
+
+    let error = scene.set_parent(child, Some(leaf)).unwrap_err();
+    assert_eq!(*error.kind(), WhirlwingErrorKind::SceneFailure);
+    let error = scene.set_parent(child, Some(child)).unwrap_err();
+    assert_eq!(*error.kind(), WhirlwingErrorKind::SceneFailure);
+}
+
+#[test]
+fn removing_a_node_removes_its_subtree() {
+    let mut scene = Scene::new();
+    let root = scene.add_node("root", Similarity3::identity());
+    let child = scene
+        .add_child(root, "child", Similarity3::identity())
+        .unwrap();
+    let leaf = scene
+        .add_child(child, "leaf", Similarity3::identity())
+        .unwrap();
+    let other = scene
+        .add_child(root, "other", Similarity3::identity())
+        .unwrap();
+    assert_eq!(scene.len(), 4);
+
+    scene.remove(child).unwrap();
+    assert_eq!(scene.len(), 2);
+    assert!(!scene.contains(child));
+    assert!(!scene.contains(leaf));
+    assert_eq!(scene.node(root).unwrap().children(), [other]);
+    assert_eq!(scene.find("leaf"), None);
+
+    // The freed slot is reused without reviving the old id.
+    let new = scene.add_node("new", Similarity3::identity());
+    assert_ne!(new, child);
+    assert_ne!(new, leaf);
+    assert!(scene.world_transform(leaf).is_none());
+    assert_eq!(
+        *scene
+            .set_transform(leaf, Similarity3::identity())
+            .unwrap_err()
+            .kind(),
+        WhirlwingErrorKind::SceneFailure
+    );
+}
+
+#[test]
+fn cameras_and_lights_follow_their_nodes() {
+    let mut scene = Scene::new();
+    let rig = scene.add_node("rig", translation(0.0, 0.0, 5.0));
+    let camera = scene
+        .add_child(rig, "camera", translation(0.0, 1.0, 0.0))
+        .unwrap();
+    let projection = Projection {
+        fov_y: 1.0,
+        near: 0.5,
+        far: 50.0,
+    };
+    scene
+        .attach(camera, Attachment::Camera(projection))
+        .unwrap();
+
+    let (view, attached) = scene.camera(camera).unwrap();
+    assert_eq!(attached, projection);
+    assert_close(view.position(), Vec3::new(0.0, 1.0, 5.0));
+    let uniforms = scene.camera_uniforms(camera, 1.5).unwrap();
+    assert_eq!(uniforms.projection, projection.matrix(1.5));
+    assert_close(uniforms.position, Vec3::new(0.0, 1.0, 5.0));
+    assert!(scene.camera(rig).is_none());
+
+    // Only camera nodes can become the active camera, removing the node unsets it.
+    assert!(scene.active_camera().is_none());
+    let error = scene.set_active_camera(Some(rig)).unwrap_err();
+    assert_eq!(*error.kind(), WhirlwingErrorKind::SceneFailure);
+    scene.set_active_camera(Some(camera)).unwrap();
+    assert_eq!(scene.active_camera(), Some(camera));
+    scene.set_active_camera(None).unwrap();
+    assert!(scene.active_camera().is_none());
+    scene.set_active_camera(Some(camera)).unwrap();
+    scene.remove(rig).unwrap();
+    assert!(scene.active_camera().is_none());
+
+    let lamp = scene.add_node(
+        "lamp",
+        Similarity3::new(
+            Vec3::new(2.0, 0.0, 0.0),
+            Rotor3::from_rotation_yz(std::f32::consts::FRAC_PI_2),
+            1.0,
+        ),
+    );
+    let light = Light::new(LightKind::Point { range: 10.0 }, Vec3::one(), 2.0);
+    assert!(scene
+        .attach(lamp, Attachment::Light(light))
+        .unwrap()
+        .is_none());
+    let lights: Vec<_> = scene.lights().collect();
+    assert_eq!(lights.len(), 1);
+    assert_eq!(*lights[0].0, light);
+    assert_close(lights[0].1, Vec3::new(2.0, 0.0, 0.0));
+    let mut direction = Vec3::new(0.0, 0.0, -1.0);
+    Rotor3::from_rotation_yz(std::f32::consts::FRAC_PI_2).rotate_vec(&mut direction);
+    assert_close(lights[0].2, direction);
+
+    assert!(matches!(scene.detach(lamp), Some(Attachment::Light(_))));
+    assert_eq!(scene.lights().count(), 0);
+    assert_eq!(scene.models().count(), 0);
+}
@@ -0,0 +1,236 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+mod common;
+
+use common::headless;
+use whirlwing::{headless::HeadlessContext, math::Mat4, shader::Shader};
+
+const VERTEX_SHADER: &str = "#version 330 core
+layout (location = 0) in vec3 position;
+layout (location = 2) in vec2 uv;
+uniform mat4 model;
+out vec2 frag_uv;
+void main() {
+    frag_uv = uv;
+    gl_Position = model * vec4(position, 1.0);
+}
+";
+
+const FRAGMENT_SHADER: &str = "#version 330 core
+in vec2 frag_uv;
+out vec4 color;
+uniform sampler2D albedo;
+uniform float weights[4];
+void main() {
+    color = texture(albedo, frag_uv) * (weights[0] + weights[3]);
+}
+";
+
+fn setup() -> (HeadlessContext, Shader) {
+    let (context, _) = headless([64, 64], true, true);
+    let shader = Shader::from_str(VERTEX_SHADER, FRAGMENT_SHADER).unwrap();
+    (context, shader)
+}
+
+#[test]
+fn active_variables_are_reflected() {
+    let (_context, shader) = setup();
+
+    let attributes: Vec<_> = shader
+        .attributes()
+        .into_iter()
+        .map(|attribute| (attribute.name, attribute.location, attribute.gl_type))
+        .collect();
+    assert_eq!(
+        attributes,
+        [
+            ("position".to_string(), 0, gl::FLOAT_VEC3),
+            ("uv".to_string(), 2, gl::FLOAT_VEC2)
+        ]
+    );
+    assert!(shader.has_attribute("uv"));
+    assert!(!shader.has_attribute("normal"));
+
+    let mut uniforms: Vec<_> = shader
+        .uniforms()
+        .into_iter()
+        .map(|uniform| (uniform.name, uniform.gl_type, uniform.size))
+        .collect();
+    uniforms.sort();
+    assert_eq!(
+        uniforms,
+        [
+            ("albedo".to_string(), gl::SAMPLER_2D, 1),
+            ("model".to_string(), gl::FLOAT_MAT4, 1),
+            ("weights".to_string(), gl::FLOAT, 4)
+        ]
+    );
+
+    let weights = shader.uniform("weights").unwrap();
+    let last = shader.uniform("weights[3]").unwrap();
+    assert_eq!(last.location, weights.location + 3);
+    assert!(shader.uniform("weights[4]").is_none());
+
+    assert!(shader.has_uniform("model"));
+    assert!(shader.has_uniform("weights[3]"));
+    assert!(!shader.has_uniform("weights[4]"));
+    assert!(!shader.has_uniform("missing"));
+}
+
+#[test]
+fn setters_accept_matching_types() {
+    let (_context, shader) = setup();
+    shader.bind();
+
+    shader.set_int("albedo", 0).unwrap();
+    shader.set_float("weights[1]", 0.5).unwrap();
+    shader.set_mat4("model", &Mat4::identity()).unwrap();
+}
+
+// The checks are skipped in release builds.
+#[cfg(debug_assertions)]
+#[test]
+fn setters_reject_unknown_names_and_wrong_types() {
+    use whirlwing::error::WhirlwingErrorKind;
+
+    let (_context, shader) = setup();
+    shader.bind();
+
+    let error = shader.set_float("missing", 1.0).unwrap_err();
+    assert_eq!(*error.kind(), WhirlwingErrorKind::UniformFailure);
+
+    let error = shader.set_float("model", 1.0).unwrap_err();
+    assert_eq!(*error.kind(), WhirlwingErrorKind::UniformFailure);
+    assert!(error.content().contains("mat4"), "{}", error.content());
+
+    assert!(shader.set_int("weights", 1).is_err());
+}
+
+const COVERAGE_SHADER: &str = "#version 330 core
+out vec4 color;
+uniform bool flag;
+uniform uint count;
+uniform vec3 tint;
+uniform ivec2 cell;
+uniform uvec4 mask;
+uniform mat3 normal_matrix;
+uniform vec3 light_positions[3];
+uniform mat4 bones[2];
+uniform sampler2D textures[2];
+void main() {
+    vec4 sum = texture(textures[0], vec2(0.0)) + texture(textures[1], vec2(0.0));
+    sum.xyz += tint + vec3(cell, 0.0) + vec3(mask.xyz) + float(count) + normal_matrix[0];
+    sum.xyz += light_positions[0] + light_positions[2] + (bones[1] * vec4(1.0)).xyz;
+    color = flag ? sum : vec4(0.0);
+}
+";
+
+#[test]
+fn all_uniform_types_are_written() {
+    use whirlwing::math::{IVec2, Mat3, UVec4, Vec3};
+
+    let (_context, _) = setup();
+    let shader = Shader::from_str(VERTEX_SHADER, COVERAGE_SHADER).unwrap();
+    shader.bind();
+
+    let float_at = |name: &str, len: usize| {
+        let mut values = vec![0.0f32; len];
+        let location = shader.uniform(name).unwrap().location;
+        unsafe { gl::GetUniformfv(shader.id(), location, values.as_mut_ptr()) };
+        values
+    };
+    let int_at = |name: &str, len: usize| {
+        let mut values = vec![0i32; len];
+        let location = shader.uniform(name).unwrap().location;
+        unsafe { gl::GetUniformiv(shader.id(), location, values.as_mut_ptr()) };
+        values
+    };
+
+    shader.set("flag", &true).unwrap();
+    shader.set("count", &7u32).unwrap();
+    shader.set("tint", &Vec3::new(0.1, 0.2, 0.3)).unwrap();
+    shader.set("cell", &IVec2::new(-4, 5)).unwrap();
+    shader.set("mask", &UVec4::new(1, 2, 3, 4)).unwrap();
+    shader.set("normal_matrix", &Mat3::from_scale(2.0)).unwrap();
+    shader
+        .set("light_positions", &[Vec3::unit_x(), Vec3::unit_y()])
+        .unwrap();
+    shader.set("light_positions[2]", &Vec3::unit_z()).unwrap();
+    shader
+        .set("bones", &vec![Mat4::identity(), Mat4::from_scale(3.0)])
+        .unwrap();
+    shader.set("textures", &[3, 4][..]).unwrap();
+
+    assert_eq!(int_at("flag", 1), [1]);
+    assert_eq!(int_at("count", 1), [7]);
+    assert_eq!(float_at("tint", 3), [0.1, 0.2, 0.3]);
+    assert_eq!(int_at("cell", 2), [-4, 5]);
+    assert_eq!(int_at("mask", 4), [1, 2, 3, 4]);
+    assert_eq!(float_at("normal_matrix", 9)[..4], [2.0, 0.0, 0.0, 0.0]);
+    assert_eq!(float_at("light_positions[1]", 3), [0.0, 1.0, 0.0]);
+    assert_eq!(float_at("light_positions[2]", 3), [0.0, 0.0, 1.0]);
+    assert_eq!(float_at("bones[1]", 16)[0], 3.0);
+    assert_eq!(int_at("textures[1]", 1), [4]);
+}
+
+// The checks are skipped in release builds.
+#[cfg(debug_assertions)]
+#[test]
+fn arrays_are_bounds_checked() {
+    use whirlwing::math::Vec3;
+
+    let (_context, _) = setup();
+    let shader = Shader::from_str(VERTEX_SHADER, COVERAGE_SHADER).unwrap();
+    shader.bind();
+
+    assert!(shader.set("light_positions", &[Vec3::zero(); 3]).is_ok());
+    assert!(shader.set("light_positions", &[Vec3::zero(); 4]).is_err());
+    assert!(shader
+        .set("light_positions[1]", &[Vec3::zero(); 3])
+        .is_err());
+    assert!(shader.set("tint", &[1.0f32, 2.0, 3.0]).is_err());
+}
+
+#[test]
+fn compile_errors_carry_diagnostics() {
+    use whirlwing::{
+        error::WhirlwingErrorKind,
+        shader::ShaderStage,
+        shader_error::{Severity, ShaderError},
+    };
+
+    let (_context, _shader) = setup();
+    let broken = "#version 330 core\nout vec4 color;\nvoid main() {\n    color = missing;\n}\n";
+    let error = Shader::from_str(VERTEX_SHADER, broken).unwrap_err();
+    assert_eq!(*error.kind(), WhirlwingErrorKind::ShaderCompilationFailure);
+
+    let details = ShaderError::of(&error).unwrap();
+    assert_eq!(details.stage, Some(ShaderStage::Fragment));
+    assert_eq!(
+        details.sources,
+        ["<fragment shader>"].map(std::path::PathBuf::from)
+    );
+    let diagnostic = details.errors().next().unwrap();
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.line, Some(4));
+    assert_eq!(
+        diagnostic.file.as_deref(),
+        Some(std::path::Path::new("<fragment shader>"))
+    );
+    assert!(diagnostic.message.contains("missing"), "{diagnostic}");
+    assert!(error.content().contains(&details.log));
+}
+
+#[test]
+fn link_errors_are_reported() {
+    use whirlwing::shader_error::ShaderError;
+
+    let (_context, _shader) = setup();
+    let no_main = "#version 330 core\nout vec4 color;\nvoid helper() { color = vec4(1.0); }\n";
+    let error = Shader::from_str(VERTEX_SHADER, no_main).unwrap_err();
+
+    let details = ShaderError::of(&error).unwrap();
+    assert_eq!(details.stage, None);
+    assert_eq!(details.sources.len(), 2);
+    assert!(details.errors().count() > 0, "{}", details.log);
+}
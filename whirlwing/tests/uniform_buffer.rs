@@ -0,0 +1,129 @@
+#![cfg(not(any(target_os = "macos", target_os = "ios")))]
+
+mod common;
+
+use common::headless;
+use whirlwing::{
+    camera::CameraUniforms,
+    math::{Mat4, Vec3, Vec4},
+    shader::Shader,
+    std140,
+    uniform_buffer::{Padding, Std140, UniformBuffer, FIRST_FREE_BINDING},
+};
+
+std140! {
+    struct Light {
+        position: Vec3,
+        radius: f32,
+        color: Vec3,
+        _padding: Padding<4>,
+    }
+}
+
+std140! {
+    struct Lights {
+        lights: [Light; 4],
+        ambient: Vec4,
+        count: u32,
+        _padding: Padding<12>,
+    }
+}
+
+std140! {
+    struct MissingPadding {
+        a: Vec3,
+        b: Vec3,
+    }
+}
+
+std140! {
+    struct FloatArray {
+        weights: [f32; 4],
+    }
+}
+
+#[test]
+fn std140_layouts_are_checked() {
+    assert_eq!(Light::SIZE, 32);
+    assert_eq!(Lights::SIZE, 4 * 32 + 16 + 16);
+    assert_eq!(Lights::check_layout(), Ok(()));
+    assert_eq!(CameraUniforms::SIZE, 144);
+    assert_eq!(CameraUniforms::check_layout(), Ok(()));
+
+    let error = MissingPadding::check_layout().unwrap_err();
+    assert_eq!(
+        error,
+        "`MissingPadding::b` is at offset 12, std140 places it at 16"
+    );
+    let error = FloatArray::check_layout().unwrap_err();
+    assert!(
+        error.starts_with("`FloatArray::weights`: Array elements"),
+        "{error}"
+    );
+}
+
+const VERTEX_SHADER: &str = "#version 330 core
+layout (std140) uniform Camera {
+    mat4 view;
+    mat4 projection;
+    vec3 camera_position;
+};
+layout (std140) uniform Material {
+    vec4 tint;
+};
+void main() {
+    gl_Position = projection * view * vec4(camera_position, 1.0) * tint;
+}
+";
+
+const FRAGMENT_SHADER: &str = "#version 330 core
+out vec4 color;
+void main() { color = vec4(1.0); }
+";
+
+#[test]
+fn reserved_blocks_are_bound_automatically() {
+    let (_context, _) = headless([64, 64], true, true);
+    let shader = Shader::from_str(VERTEX_SHADER, FRAGMENT_SHADER).unwrap();
+
+    let camera = shader.uniform_block("Camera").unwrap();
+    assert_eq!(camera.binding, 0);
+    assert_eq!(camera.data_size, CameraUniforms::SIZE);
+
+    assert!(shader
+        .bind_uniform_block("Missing", FIRST_FREE_BINDING)
+        .is_err());
+    shader
+        .bind_uniform_block("Material", FIRST_FREE_BINDING)
+        .unwrap();
+    let material = shader.uniform_block("Material").unwrap();
+    assert_eq!(material.binding, FIRST_FREE_BINDING);
+
+    let mut binding = 0;
+    unsafe {
+        gl::GetActiveUniformBlockiv(
+            shader.id(),
+            material.index,
+            gl::UNIFORM_BLOCK_BINDING,
+            &mut binding,
+        );
+    }
+    assert_eq!(binding as u32, FIRST_FREE_BINDING);
+}
+
+#[test]
+fn buffers_reject_invalid_layouts() {
+    let (_context, _) = headless([64, 64], true, true);
+
+    let data = MissingPadding {
+        a: Vec3::zero(),
+        b: Vec3::zero(),
+    };
+    assert!(UniformBuffer::new(FIRST_FREE_BINDING, &data).is_err());
+
+    let mut data = CameraUniforms::default();
+    data.view = Mat4::identity();
+    let buffer = UniformBuffer::new(FIRST_FREE_BINDING, &data).unwrap();
+    buffer.update(&data);
+    assert_eq!(buffer.binding(), FIRST_FREE_BINDING);
+}
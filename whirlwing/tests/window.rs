@@ -0,0 +1,55 @@
+mod common;
+
+use common::workspace_root;
+use whirlwing::{
+    error::WhirlwingErrorKind,
+    window::{VSync, WindowConfig, WindowMode},
+};
+
+#[test]
+fn missing_fields_take_their_defaults() {
+    let config = WindowConfig::from_toml_str("title = \"Test\"\nsamples = 8").unwrap();
+    let default = WindowConfig::default();
+
+    assert_eq!(config.title, "Test");
+    assert_eq!(config.samples, 8);
+    assert_eq!(config.size, default.size);
+    assert_eq!(config.srgb, default.srgb);
+    assert_eq!(config.gl_version, default.gl_version);
+}
+
+#[test]
+fn unknown_fields_are_rejected() {
+    let error = WindowConfig::from_toml_str("titel = \"Test\"").unwrap_err();
+    assert_eq!(*error.kind(), WhirlwingErrorKind::ConfigLoadFailure);
+}
+
+#[test]
+fn sample_counts_must_be_a_power_of_two() {
+    for samples in [0, 1, 2, 4, 16] {
+        let config = WindowConfig::from_toml_str(&format!("samples = {samples}")).unwrap();
+        assert_eq!(config.samples, samples);
+    }
+    for samples in [3, 6, 255] {
+        let error = WindowConfig::from_toml_str(&format!("samples = {samples}")).unwrap_err();
+        assert_eq!(*error.kind(), WhirlwingErrorKind::ConfigLoadFailure);
+    }
+}
+
+#[test]
+fn playground_config_loads_from_file() {
+    let config = WindowConfig::from_toml_file(workspace_root().join("res/window.toml")).unwrap();
+
+    assert_eq!(config.title, "Whirlwing Playground");
+    assert_eq!(config.size, [800, 600]);
+    assert_eq!(config.mode, WindowMode::Windowed);
+    assert_eq!(config.vsync, VSync::On);
+    assert_eq!(config.samples, 4);
+}
+
+#[test]
+fn missing_file_fails_to_load() {
+    let error =
+        WindowConfig::from_toml_file(workspace_root().join("res/missing.toml")).unwrap_err();
+    assert_eq!(*error.kind(), WhirlwingErrorKind::ConfigLoadFailure);
+}